@@ -0,0 +1,275 @@
+//! Procedural macros for `live2d-cubism-core-sys`.
+//!
+//! Use through the `macros` feature of `live2d-cubism-core-sys`, which re-exports them.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use proc_macro::TokenStream;
+use quote::{quote, format_ident};
+use serde::Deserialize;
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Ident, LitStr, Token, Visibility};
+
+/// Generates a struct of typed parameter handles from a `.cdi3.json`.
+///
+/// The path is relative to the directory of the invoking crate's `Cargo.toml`.
+///
+/// ```ignore
+/// live2d_params!(pub struct HaruParams = "assets/Haru/Haru.cdi3.json");
+///
+/// let params = HaruParams::resolve(model.get_static())?;
+/// params.param_angle_x().set(&mut model.write_dynamic(), 30.0);
+/// ```
+///
+/// Each parameter becomes a method named after its ID in snake case, e.g. `ParamEyeLOpen` becomes `param_eye_l_open`,
+/// so a misspelled ID fails to compile. Names that would be keywords or clash with `resolve` get a trailing `_`,
+/// e.g. `type_` or `resolve_`.
+#[proc_macro]
+pub fn live2d_params(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as ParamsInput);
+
+  match expand_live2d_params(input) {
+    Ok(tokens) => tokens.into(),
+    Err(err) => err.to_compile_error().into(),
+  }
+}
+
+/// Embeds a `.moc3` as a `&'static [u8]`, validating its header at compile time.
+///
+/// The path is relative to the directory of the invoking crate's `Cargo.toml`.
+///
+/// ```ignore
+/// static MOC_BYTES: &[u8] = include_moc!("assets/Haru/Haru.moc3");
+///
+/// let moc = cubism_core.moc_from_bytes(MOC_BYTES)?;
+/// ```
+///
+/// Compilation fails if the file is not a little-endian moc3. Its version is not checked against the ones known to
+/// this crate, since newer mocs may still load (see `MocVersion::Newer` and `MocLoadOptions::allow_newer`):
+/// whether the linked _Live2D® Cubism SDK Core_ supports it is only known at runtime.
+#[proc_macro]
+pub fn include_moc(input: TokenStream) -> TokenStream {
+  let path = parse_macro_input!(input as LitStr);
+
+  match expand_include_moc(path) {
+    Ok(tokens) => tokens.into(),
+    Err(err) => err.to_compile_error().into(),
+  }
+}
+
+/// Magic bytes at the start of every moc3.
+const MOC3_MAGIC: &[u8; 4] = b"MOC3";
+/// Size of the header fields checked by [`include_moc!`]: magic, version and endianness flag.
+const MOC3_HEADER_SIZE: usize = 6;
+
+fn expand_include_moc(path: LitStr) -> syn::Result<proc_macro2::TokenStream> {
+  let full_path = resolve_path(&path.value());
+  let full_path_str = full_path.to_string_lossy().into_owned();
+
+  let bytes = std::fs::read(&full_path)
+    .map_err(|err| syn::Error::new(path.span(), format!("Failed to read \"{full_path_str}\": {err}")))?;
+
+  check_moc3_header(&bytes).map_err(|reason| syn::Error::new(path.span(), format!("\"{full_path_str}\" {reason}")))?;
+
+  Ok(quote! {
+    {
+      const BYTES: &[u8] = include_bytes!(#full_path_str);
+      BYTES
+    }
+  })
+}
+
+/// Checks what can be checked of a moc3 without _Live2D® Cubism SDK Core_, returning why it cannot be loaded if so.
+fn check_moc3_header(bytes: &[u8]) -> Result<(), String> {
+  if bytes.len() < MOC3_HEADER_SIZE || &bytes[..4] != MOC3_MAGIC {
+    return Err("is not a moc3 file".to_owned());
+  }
+  // `csmMocVersion_Unknown`; any other version may be supported by the Core.
+  if bytes[4] == 0 {
+    return Err("has an invalid moc3 version: 0".to_owned());
+  }
+  if bytes[5] != 0 {
+    return Err("is a big-endian moc3, which is not supported".to_owned());
+  }
+  Ok(())
+}
+
+struct ParamsInput {
+  visibility: Visibility,
+  name: Ident,
+  path: LitStr,
+}
+impl Parse for ParamsInput {
+  fn parse(input: ParseStream) -> syn::Result<Self> {
+    let visibility = input.parse()?;
+    input.parse::<Token![struct]>()?;
+    let name = input.parse()?;
+    input.parse::<Token![=]>()?;
+    let path = input.parse()?;
+    Ok(Self { visibility, name, path })
+  }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Cdi3Json {
+  parameters: Vec<Cdi3Parameter>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Cdi3Parameter {
+  id: String,
+  #[serde(default)]
+  name: String,
+}
+
+fn expand_live2d_params(input: ParamsInput) -> syn::Result<proc_macro2::TokenStream> {
+  let ParamsInput { visibility, name, path } = input;
+
+  let full_path = resolve_path(&path.value());
+  let full_path_str = full_path.to_string_lossy().into_owned();
+
+  let json = std::fs::read_to_string(&full_path)
+    .map_err(|err| syn::Error::new(path.span(), format!("Failed to read \"{full_path_str}\": {err}")))?;
+  let cdi3: Cdi3Json = serde_json::from_str(&json)
+    .map_err(|err| syn::Error::new(path.span(), format!("Failed to parse \"{full_path_str}\" as a cdi3.json: {err}")))?;
+
+  let mut method_names = HashSet::new();
+  let mut ids = Vec::with_capacity(cdi3.parameters.len());
+  let mut methods = Vec::with_capacity(cdi3.parameters.len());
+  for parameter in cdi3.parameters.iter() {
+    let method_name = method_name(&parameter.id);
+    if !method_names.insert(method_name.clone()) {
+      return Err(syn::Error::new(path.span(), format!("Parameter ID \"{}\" maps to the same method name as another: `{method_name}`", parameter.id)));
+    }
+    let method = format_ident!("{}", method_name);
+
+    let doc = if parameter.name.is_empty() {
+      format!("`{}`", parameter.id)
+    } else {
+      format!("`{}`: {}", parameter.id, parameter.name)
+    };
+
+    ids.push(parameter.id.as_str());
+    methods.push((method, doc));
+  }
+
+  let field_names: Vec<_> = methods.iter().map(|(method, _)| method).collect();
+  let docs = methods.iter().map(|(_, doc)| doc);
+  let parameter_count = ids.len();
+  let krate = quote!(::live2d_cubism_core_sys::core);
+
+  Ok(quote! {
+    #[doc = concat!("Typed parameter handles generated from `", #full_path_str, "`.")]
+    #[derive(Debug, Clone, Copy)]
+    #visibility struct #name {
+      #(#field_names: #krate::ParameterHandle,)*
+    }
+    impl #name {
+      /// Parameter IDs, in the order they appear in the cdi3.json.
+      pub const IDS: [&'static str; #parameter_count] = [#(#ids),*];
+
+      /// Looks up all parameters in `model_static`.
+      pub fn resolve(model_static: &#krate::ModelStatic) -> ::std::result::Result<Self, #krate::MissingParameterError> {
+        ::std::result::Result::Ok(Self {
+          #(#field_names: #krate::ParameterHandle::resolve(model_static, #ids)?,)*
+        })
+      }
+
+      #(
+        #[doc = #docs]
+        pub fn #field_names(&self) -> #krate::ParameterHandle {
+          self.#field_names
+        }
+      )*
+    }
+    // Makes the invoking crate rebuild when the file changes.
+    const _: &[u8] = include_bytes!(#full_path_str);
+  })
+}
+
+fn resolve_path(path: &str) -> PathBuf {
+  let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from).unwrap_or_default();
+  manifest_dir.join(path)
+}
+
+/// Names of the items generated by `live2d_params!` besides the parameter methods.
+const RESERVED_METHOD_NAMES: &[&str] = &["resolve"];
+
+/// Name of the method for the parameter with `id`, suffixed with `_` if it would be a keyword or a reserved name.
+fn method_name(id: &str) -> String {
+  let snake = to_snake_case(id);
+  if RESERVED_METHOD_NAMES.contains(&snake.as_str()) || syn::parse_str::<Ident>(&snake).is_err() {
+    format!("{snake}_")
+  } else {
+    snake
+  }
+}
+
+/// Converts e.g. `ParamEyeLOpen` into `param_eye_l_open`.
+fn to_snake_case(id: &str) -> String {
+  let chars: Vec<char> = id.chars().collect();
+  let mut snake = String::with_capacity(id.len() + 4);
+  for (i, &c) in chars.iter().enumerate() {
+    if !c.is_ascii_alphanumeric() {
+      if !snake.ends_with('_') {
+        snake.push('_');
+      }
+      continue;
+    }
+    if c.is_ascii_uppercase() && i > 0 {
+      let previous = chars[i - 1];
+      let next_is_lowercase = chars.get(i + 1).is_some_and(|next| next.is_ascii_lowercase());
+      let is_word_start = previous.is_ascii_lowercase() || previous.is_ascii_digit() || (previous.is_ascii_uppercase() && next_is_lowercase);
+      if is_word_start && !snake.ends_with('_') {
+        snake.push('_');
+      }
+    }
+    snake.push(c.to_ascii_lowercase());
+  }
+
+  let snake = snake.trim_matches('_');
+  if snake.is_empty() || snake.starts_with(|c: char| c.is_ascii_digit()) {
+    format!("p_{snake}")
+  } else {
+    snake.to_owned()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn snake_case() {
+    assert_eq!(to_snake_case("ParamEyeLOpen"), "param_eye_l_open");
+    assert_eq!(to_snake_case("ParamAngleX2"), "param_angle_x2");
+    assert_eq!(to_snake_case("ParamMouthOpenY"), "param_mouth_open_y");
+    assert_eq!(to_snake_case("PARAM_BODY_ANGLE_X"), "param_body_angle_x");
+    assert_eq!(to_snake_case("ParamHTTPSwitch"), "param_http_switch");
+    assert_eq!(to_snake_case("Param3DTilt"), "param3_d_tilt");
+    assert_eq!(to_snake_case("2ndHair"), "p_2nd_hair");
+    assert_eq!(to_snake_case("Param Hair-Front"), "param_hair_front");
+  }
+
+  #[test]
+  fn moc3_header() {
+    assert!(check_moc3_header(b"MOC3\x04\x00rest").is_ok());
+    // Newer than any version known to this crate, e.g. from Cubism 5.
+    assert!(check_moc3_header(b"MOC3\x05\x00").is_ok());
+    assert!(check_moc3_header(b"MOC3\xff\x00").is_ok());
+    assert!(check_moc3_header(b"MOC3\x00\x00").is_err());
+    assert!(check_moc3_header(b"MOC3\x04\x01").is_err());
+    assert!(check_moc3_header(b"MOC3\x04").is_err());
+    assert!(check_moc3_header(b"MOCK\x04\x00").is_err());
+  }
+
+  #[test]
+  fn reserved_method_names() {
+    assert_eq!(method_name("Resolve"), "resolve_");
+    assert_eq!(method_name("Type"), "type_");
+    assert_eq!(method_name("ParamResolve"), "param_resolve");
+  }
+}
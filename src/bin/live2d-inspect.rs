@@ -0,0 +1,422 @@
+//! Prints the static description of a `.moc3`, or of the moc referenced by a `.model3.json`.
+//!
+//! Usage: `live2d-inspect [--json | --csv <parameters|parts|drawables>] <path/to/model.moc3 | path/to/model.model3.json>`
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use live2d_cubism_core_sys::core as live2d_core;
+use live2d_core::{ConstantDrawableFlags, DrawableIndex};
+
+const USAGE: &str = "Usage: live2d-inspect [--json | --csv <parameters|parts|drawables>] <path/to/model.moc3 | path/to/model.model3.json>";
+
+#[derive(Debug, Clone, Copy)]
+enum OutputFormat {
+  Text,
+  Json,
+  Csv(CsvTable),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CsvTable {
+  Parameters,
+  Parts,
+  Drawables,
+}
+
+fn main() -> ExitCode {
+  let (format, path) = match parse_args(std::env::args().skip(1)) {
+    Some(args) => args,
+    None => {
+      eprintln!("{USAGE}");
+      return ExitCode::FAILURE;
+    }
+  };
+
+  match inspect(&path, format) {
+    Ok(()) => ExitCode::SUCCESS,
+    Err(message) => {
+      eprintln!("error: {message}");
+      ExitCode::FAILURE
+    }
+  }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Option<(OutputFormat, PathBuf)> {
+  let mut format = OutputFormat::Text;
+  let mut path = None;
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--json" => format = OutputFormat::Json,
+      "--csv" => {
+        format = OutputFormat::Csv(match args.next()?.as_str() {
+          "parameters" => CsvTable::Parameters,
+          "parts" => CsvTable::Parts,
+          "drawables" => CsvTable::Drawables,
+          _ => return None,
+        });
+      }
+      _ if arg.starts_with('-') => return None,
+      _ if path.is_none() => path = Some(PathBuf::from(arg)),
+      _ => return None,
+    }
+  }
+  Some((format, path?))
+}
+
+fn inspect(path: &Path, format: OutputFormat) -> Result<(), String> {
+  let moc_path = resolve_moc_path(path)?;
+  let moc_bytes = std::fs::read(&moc_path).map_err(|err| format!("Failed to read \"{}\": {err}", moc_path.display()))?;
+
+  // SAFETY: Nothing else in this process uses `csmGetLogFunction` or `csmSetLogFunction`.
+  unsafe {
+    live2d_core::CubismCore::set_log_function(|message| eprintln!("[Live2D Cubism Core] {message}"));
+  }
+
+  let cubism_core = live2d_core::CubismCore::default();
+  let moc = cubism_core.moc_from_bytes(&moc_bytes).map_err(|err| format!("Failed to load \"{}\": {err}", moc_path.display()))?;
+  let model = live2d_core::Model::from_moc(&moc);
+
+  let description = ModelDescription::new(&moc_path, &cubism_core, &moc, &model);
+
+  let stdout = std::io::stdout();
+  let mut out = stdout.lock();
+  let result = match format {
+    OutputFormat::Text => write_text(&mut out, &description),
+    OutputFormat::Json => serde_json::to_writer_pretty(&mut out, &description)
+      .map_err(std::io::Error::from)
+      .and_then(|()| writeln!(out)),
+    OutputFormat::Csv(table) => write_csv(&mut out, &description, table),
+  };
+  result.map_err(|err| format!("Failed to write output: {err}"))
+}
+
+//
+// Description
+//
+
+/// Everything printed about a model, in all output formats.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ModelDescription {
+  file: String,
+  core_version: String,
+  latest_supported_moc_version: String,
+  moc_version: String,
+  canvas: CanvasDescription,
+  capabilities: Vec<String>,
+  texture_count: usize,
+  total_vertex_count: usize,
+  total_triangle_count: usize,
+  parameters: Vec<ParameterDescription>,
+  parts: Vec<PartDescription>,
+  drawables: Vec<DrawableDescription>,
+  mask_groups: Vec<MaskGroupDescription>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct CanvasDescription {
+  width_in_pixels: f32,
+  height_in_pixels: f32,
+  origin_x_in_pixels: f32,
+  origin_y_in_pixels: f32,
+  pixels_per_unit: f32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ParameterDescription {
+  index: usize,
+  id: String,
+  #[serde(rename = "Type")]
+  ty: String,
+  min: f32,
+  max: f32,
+  default: f32,
+  keys: Vec<f32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct PartDescription {
+  index: usize,
+  id: String,
+  parent: Option<usize>,
+  opacity: f32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct DrawableDescription {
+  index: usize,
+  id: String,
+  texture: usize,
+  vertex_count: u32,
+  triangle_count: usize,
+  parent: Option<usize>,
+  blend: &'static str,
+  is_double_sided: bool,
+  is_inverted_mask: bool,
+  masks: Vec<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct MaskGroupDescription {
+  masks: Vec<String>,
+  consumers: Vec<String>,
+  inverted_consumers: Vec<String>,
+}
+
+impl ModelDescription {
+  fn new(moc_path: &Path, cubism_core: &live2d_core::CubismCore, moc: &live2d_core::Moc, model: &live2d_core::Model) -> Self {
+    let model_static = model.get_static();
+    let dynamic = model.read_dynamic();
+    let canvas_info = model_static.canvas_info();
+    let statistics = model_static.geometry_statistics();
+
+    let parameters = model_static.parameters().iter()
+      .enumerate()
+      .map(|(index, parameter)| ParameterDescription {
+        index,
+        id: parameter.id().to_owned(),
+        ty: format!("{:?}", parameter.ty()),
+        min: parameter.value_range().0,
+        max: parameter.value_range().1,
+        default: parameter.default_value(),
+        keys: parameter.keys().to_vec(),
+      })
+      .collect();
+
+    let parts = model_static.parts().iter()
+      .enumerate()
+      .map(|(index, part)| PartDescription {
+        index,
+        id: part.id().to_owned(),
+        parent: part.parent_part_index(),
+        opacity: dynamic.part_opacities()[index],
+      })
+      .collect();
+
+    let drawables = model_static.drawables().iter()
+      .map(|drawable| {
+        let flagset = drawable.constant_flagset();
+        let blend = if flagset.contains(ConstantDrawableFlags::BlendAdditive) {
+          "Additive"
+        } else if flagset.contains(ConstantDrawableFlags::BlendMultiplicative) {
+          "Multiplicative"
+        } else {
+          "Normal"
+        };
+        DrawableDescription {
+          index: drawable.index().as_usize(),
+          id: drawable.id().to_owned(),
+          texture: drawable.texture_index().as_usize(),
+          vertex_count: drawable.vertex_count(),
+          triangle_count: drawable.triangle_indices().len() / 3,
+          parent: drawable.parent_part_index(),
+          blend,
+          is_double_sided: flagset.contains(ConstantDrawableFlags::IsDoubleSided),
+          is_inverted_mask: flagset.contains(ConstantDrawableFlags::IsInvertedMask),
+          masks: drawable.masks().to_vec(),
+        }
+      })
+      .collect();
+
+    let drawable_id = |index: DrawableIndex| {
+      model_static.get_drawable(index).map_or_else(|| index.to_string(), |drawable| drawable.id().to_owned())
+    };
+    let mask_groups = model_static.mask_groups().groups().iter()
+      .map(|group| MaskGroupDescription {
+        masks: group.masks().iter().map(|&mask| drawable_id(mask)).collect(),
+        consumers: group.consumers().iter().filter(|consumer| !consumer.is_inverted).map(|consumer| drawable_id(consumer.drawable_index)).collect(),
+        inverted_consumers: group.consumers().iter().filter(|consumer| consumer.is_inverted).map(|consumer| drawable_id(consumer.drawable_index)).collect(),
+      })
+      .collect();
+
+    Self {
+      file: moc_path.display().to_string(),
+      core_version: cubism_core.version().to_string(),
+      latest_supported_moc_version: cubism_core.latest_supported_moc_version().to_string(),
+      moc_version: moc.version().to_string(),
+      canvas: CanvasDescription {
+        width_in_pixels: canvas_info.size_in_pixels.0,
+        height_in_pixels: canvas_info.size_in_pixels.1,
+        origin_x_in_pixels: canvas_info.origin_in_pixels.0,
+        origin_y_in_pixels: canvas_info.origin_in_pixels.1,
+        pixels_per_unit: canvas_info.pixels_per_unit,
+      },
+      capabilities: model.capabilities().into_iter().map(|flag| format!("{flag:?}")).collect(),
+      texture_count: statistics.texture_count,
+      total_vertex_count: statistics.total_vertex_count,
+      total_triangle_count: statistics.total_triangle_count,
+      parameters,
+      parts,
+      drawables,
+      mask_groups,
+    }
+  }
+}
+
+//
+// Text
+//
+
+fn write_text(out: &mut impl Write, description: &ModelDescription) -> std::io::Result<()> {
+  writeln!(out, "File:                 {}", description.file)?;
+  writeln!(out, "Core version:         {}", description.core_version)?;
+  writeln!(out, "Latest supported moc: {}", description.latest_supported_moc_version)?;
+  writeln!(out, "Moc version:          {}", description.moc_version)?;
+
+  let canvas = &description.canvas;
+  writeln!(out)?;
+  writeln!(out, "Canvas")?;
+  writeln!(out, "  Size:            {} x {} px", canvas.width_in_pixels, canvas.height_in_pixels)?;
+  writeln!(out, "  Origin:          ({}, {}) px", canvas.origin_x_in_pixels, canvas.origin_y_in_pixels)?;
+  writeln!(out, "  Pixels per unit: {}", canvas.pixels_per_unit)?;
+  writeln!(out, "  Capabilities:    {}", description.capabilities.join(", "))?;
+
+  writeln!(out)?;
+  writeln!(out, "Parameters ({})", description.parameters.len())?;
+  writeln!(out, "  {:>5}  {:<32}  {:<10}  {:>10}  {:>10}  {:>10}", "Index", "ID", "Type", "Min", "Max", "Default")?;
+  for parameter in description.parameters.iter() {
+    writeln!(
+      out,
+      "  {:>5}  {:<32}  {:<10}  {:>10.3}  {:>10.3}  {:>10.3}",
+      parameter.index, parameter.id, parameter.ty, parameter.min, parameter.max, parameter.default,
+    )?;
+  }
+
+  writeln!(out)?;
+  writeln!(out, "Parts ({})", description.parts.len())?;
+  writeln!(out, "  {:>5}  {:<32}  {:>6}  {:>7}", "Index", "ID", "Parent", "Opacity")?;
+  for part in description.parts.iter() {
+    writeln!(out, "  {:>5}  {:<32}  {:>6}  {:>7.3}", part.index, part.id, optional_index(part.parent), part.opacity)?;
+  }
+
+  writeln!(out)?;
+  writeln!(out, "Drawables ({})", description.drawables.len())?;
+  writeln!(out, "  {:>5}  {:<32}  {:>7}  {:>8}  {:>9}  {:>6}  {:<14}  Masks", "Index", "ID", "Texture", "Vertices", "Triangles", "Parent", "Blend")?;
+  for drawable in description.drawables.iter() {
+    writeln!(
+      out,
+      "  {:>5}  {:<32}  {:>7}  {:>8}  {:>9}  {:>6}  {:<14}  {}",
+      drawable.index,
+      drawable.id,
+      drawable.texture,
+      drawable.vertex_count,
+      drawable.triangle_count,
+      optional_index(drawable.parent),
+      drawable.blend,
+      index_list(&drawable.masks, ", "),
+    )?;
+  }
+
+  writeln!(out)?;
+  writeln!(out, "Mask groups ({})", description.mask_groups.len())?;
+  for (group_index, group) in description.mask_groups.iter().enumerate() {
+    let consumers: Vec<_> = group.consumers.iter()
+      .cloned()
+      .chain(group.inverted_consumers.iter().map(|consumer| format!("{consumer} (inverted)")))
+      .collect();
+    writeln!(out, "  #{group_index}: [{}] -> [{}]", group.masks.join(", "), consumers.join(", "))?;
+  }
+
+  writeln!(out)?;
+  writeln!(out, "Geometry")?;
+  writeln!(out, "  Vertices:  {}", description.total_vertex_count)?;
+  writeln!(out, "  Triangles: {}", description.total_triangle_count)?;
+  writeln!(out, "  Textures:  {}", description.texture_count)?;
+
+  Ok(())
+}
+
+//
+// CSV
+//
+
+fn write_csv(out: &mut impl Write, description: &ModelDescription, table: CsvTable) -> std::io::Result<()> {
+  match table {
+    CsvTable::Parameters => {
+      writeln!(out, "Index,Id,Type,Min,Max,Default,Keys")?;
+      for parameter in description.parameters.iter() {
+        let keys = parameter.keys.iter().map(|key| key.to_string()).collect::<Vec<_>>().join(" ");
+        writeln!(
+          out,
+          "{},{},{},{},{},{},{}",
+          parameter.index, csv_field(&parameter.id), parameter.ty, parameter.min, parameter.max, parameter.default, csv_field(&keys),
+        )?;
+      }
+    }
+    CsvTable::Parts => {
+      writeln!(out, "Index,Id,Parent,Opacity")?;
+      for part in description.parts.iter() {
+        writeln!(out, "{},{},{},{}", part.index, csv_field(&part.id), part.parent.map(|parent| parent.to_string()).unwrap_or_default(), part.opacity)?;
+      }
+    }
+    CsvTable::Drawables => {
+      writeln!(out, "Index,Id,Texture,VertexCount,TriangleCount,Parent,Blend,IsDoubleSided,IsInvertedMask,Masks")?;
+      for drawable in description.drawables.iter() {
+        writeln!(
+          out,
+          "{},{},{},{},{},{},{},{},{},{}",
+          drawable.index,
+          csv_field(&drawable.id),
+          drawable.texture,
+          drawable.vertex_count,
+          drawable.triangle_count,
+          drawable.parent.map(|parent| parent.to_string()).unwrap_or_default(),
+          drawable.blend,
+          drawable.is_double_sided,
+          drawable.is_inverted_mask,
+          csv_field(&index_list(&drawable.masks, " ")),
+        )?;
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Quotes `value` if it contains a character with special meaning in CSV.
+fn csv_field(value: &str) -> std::borrow::Cow<'_, str> {
+  if value.contains([',', '"', '\n', '\r']) {
+    format!("\"{}\"", value.replace('"', "\"\"")).into()
+  } else {
+    value.into()
+  }
+}
+
+//
+// Helpers
+//
+
+/// Reads the moc path from `FileReferences.Moc` if `path` is a `.model3.json`, otherwise returns `path` as is.
+fn resolve_moc_path(path: &Path) -> Result<PathBuf, String> {
+  let is_model3_json = path.file_name()
+    .and_then(|file_name| file_name.to_str())
+    .is_some_and(|file_name| file_name.to_ascii_lowercase().ends_with(".model3.json"));
+  if !is_model3_json {
+    return Ok(path.to_owned());
+  }
+
+  let json = std::fs::read(path).map_err(|err| format!("Failed to read \"{}\": {err}", path.display()))?;
+  let model3: serde_json::Value = serde_json::from_slice(&json).map_err(|err| format!("Failed to parse \"{}\": {err}", path.display()))?;
+  let moc = model3.get("FileReferences")
+    .and_then(|file_references| file_references.get("Moc"))
+    .and_then(|moc| moc.as_str())
+    .ok_or_else(|| format!("\"{}\" has no FileReferences.Moc", path.display()))?;
+
+  Ok(path.parent().unwrap_or(Path::new("")).join(moc))
+}
+
+fn optional_index(index: Option<usize>) -> String {
+  index.map_or_else(|| "-".to_owned(), |index| index.to_string())
+}
+
+fn index_list(indices: &[usize], separator: &str) -> String {
+  indices.iter().map(|index| index.to_string()).collect::<Vec<_>>().join(separator)
+}
@@ -5,6 +5,11 @@ use std::{
 };
 
 fn main() {
+  // The `mock` feature neither links nor binds the Core, so no SDK is needed.
+  if env::var_os("CARGO_FEATURE_MOCK").is_some() {
+    return;
+  }
+
   let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap();
   match target_arch.as_str() {
     "wasm32" => handle_target_web(),
@@ -21,21 +26,27 @@ fn handle_target_native() {
 
   let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap();
 
-  let core_platform_lib_dir_path = {
-    let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap();
+  let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap();
+  let target_env = env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default();
+  if target_os == "linux" && target_env == "musl" {
+    // The SDK only provides a static library built against glibc, which has no dependencies beyond libc and libm.
+    println!("cargo:warning=Live2D Cubism SDK Core provides no build for musl; linking the glibc build of the static library. \
+      If linking fails with undefined glibc-specific symbols (e.g. `__*_chk`), this target is not usable with this SDK version.");
+  }
+  let arch_dir_name =
+    match target_os.as_str() {
+      "windows" => target_arch.as_str(),
+      "android" =>
+        match target_arch.as_str() {
+          "aarch64" => "arm64-v8a",
+          "arm" => "armeabi-v7a",
+          v => v,
+        },
+      "linux" => target_arch.as_str(),
+      target_os => panic!("Unexpected target_os: {}", target_os),
+    };
 
-    let arch_dir_name =
-      match target_os.as_str() {
-        "windows" => target_arch.as_str(),
-        "android" =>
-          match target_arch.as_str() {
-            "aarch64" => "arm64-v8a",
-            "arm" => "armeabi-v7a",
-            v => v,
-          },
-        "linux" => target_arch.as_str(),
-        target_os => panic!("Unexpected target_os: {}", target_os),
-      };
+  let core_platform_lib_dir_path = {
     let platform_lib_dir_name =
       match target_os.as_str() {
         "windows" => format!("{}/142", arch_dir_name),
@@ -44,17 +55,35 @@ fn handle_target_native() {
 
     PathBuf::from(&cubism_core_dir).join("lib").join(&target_os).join(platform_lib_dir_name)
   };
+  println!("cargo:rustc-check-cfg=cfg(live2d_cubism_core_debug_lib)");
+  let use_debug_lib = env::var_os("CARGO_FEATURE_DEBUG_LIB").is_some();
   let core_platform_lib_name = {
-    match target_os.as_str() {
-      "windows" => "Live2DCubismCore_MD",
-      _ => "Live2DCubismCore"
+    match (target_os.as_str(), use_debug_lib) {
+      ("windows", false) => "Live2DCubismCore_MD",
+      ("windows", true) => "Live2DCubismCore_MDd",
+      (_, true) => {
+        println!("cargo:warning=No debug build of Live2D Cubism SDK Core is provided for {}, linking the release build.", target_os);
+        "Live2DCubismCore"
+      },
+      (_, false) => "Live2DCubismCore",
     }
   };
+  if use_debug_lib && target_os == "windows" {
+    println!("cargo:rustc-cfg=live2d_cubism_core_debug_lib");
+  }
 
   println!("cargo:rustc-link-search=native={}", core_platform_lib_dir_path.to_str().unwrap());
   println!("cargo:rustc-link-lib=static={}", core_platform_lib_name);
 
   let core_include_dir_path = PathBuf::from(&cubism_core_dir).join("include");
+  let core_platform_dll_dir_path = PathBuf::from(&cubism_core_dir).join("dll").join(&target_os).join(arch_dir_name);
+
+  // Exposed to dependent crates' build scripts as `DEP_LIVE2DCUBISMCORE_INCLUDE`, etc.
+  println!("cargo:include={}", core_include_dir_path.to_str().unwrap());
+  println!("cargo:lib_dir={}", core_platform_lib_dir_path.to_str().unwrap());
+  if core_platform_dll_dir_path.is_dir() {
+    println!("cargo:dll_dir={}", core_platform_dll_dir_path.to_str().unwrap());
+  }
 
   let bindings_builder = bindgen::Builder::default()
     .header(WRAPPER_HEADER)
@@ -67,6 +96,28 @@ fn handle_target_native() {
   bindings
     .write_to_file(out_dir.join("bindings.rs"))
     .expect("Failed to write bindings !");
+
+  #[cfg(feature = "capi")]
+  generate_capi_header(&out_dir);
+}
+
+#[cfg(feature = "capi")]
+fn generate_capi_header(out_dir: &std::path::Path) {
+  const CAPI_SOURCE: &str = "src/capi.rs";
+  const CBINDGEN_CONFIG: &str = "cbindgen.toml";
+
+  println!("cargo:rerun-if-changed={}", CAPI_SOURCE);
+  println!("cargo:rerun-if-changed={}", CBINDGEN_CONFIG);
+
+  let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+  let config = cbindgen::Config::from_file(PathBuf::from(&crate_dir).join(CBINDGEN_CONFIG)).expect("Failed to read cbindgen.toml !");
+
+  cbindgen::Builder::new()
+    .with_crate(&crate_dir)
+    .with_config(config)
+    .generate()
+    .expect("Unable to generate C API header !")
+    .write_to_file(out_dir.join("live2d_cubism_core_capi.h"));
 }
 
 fn handle_target_web() {
@@ -0,0 +1,173 @@
+//! A small C ABI on top of [`crate::core`], for hosts that can only consume C libraries.
+//!
+//! The header is generated by _cbindgen_ during the build, as `live2d_cubism_core_capi.h` in `OUT_DIR`.
+//! Build with e.g. `cargo rustc --release --features capi --crate-type cdylib` to get a linkable library.
+//!
+//! All handles are owned by the caller and MUST be released with their respective `*_destroy` function.
+//! Functions are thread-safe in the same way as their [`crate::core`] counterparts.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::ffi::{c_char, CStr};
+
+use crate::core::{CubismCore, Moc, Model};
+
+/// Opaque handle to a [`CubismCore`].
+pub struct L2dCore(CubismCore);
+/// Opaque handle to a [`Moc`].
+pub struct L2dMoc(Moc);
+/// Opaque handle to a [`Model`].
+pub struct L2dModel(Model);
+
+/// Creates a core. Never returns null.
+#[no_mangle]
+pub extern "C" fn l2d_core_create() -> *mut L2dCore {
+  Box::into_raw(Box::new(L2dCore(CubismCore::default())))
+}
+/// ## Safety
+/// - `core` MUST be null or a handle returned by [`l2d_core_create`] that has not been destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn l2d_core_destroy(core: *mut L2dCore) {
+  if !core.is_null() {
+    drop(Box::from_raw(core));
+  }
+}
+/// Gets the version of _Live2D® Cubism SDK Core_, as `csmGetVersion` does.
+///
+/// ## Safety
+/// - `core` MUST be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn l2d_core_version(core: *const L2dCore) -> u32 {
+  (*core).0.version().raw()
+}
+
+/// Deserializes a moc from `size` bytes at `bytes`, which are copied. Returns null on failure.
+///
+/// ## Safety
+/// - `core` MUST be a valid handle.
+/// - `bytes` MUST be valid for reads of `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn l2d_moc_load(core: *const L2dCore, bytes: *const u8, size: usize) -> *mut L2dMoc {
+  if bytes.is_null() {
+    return std::ptr::null_mut();
+  }
+  let bytes = std::slice::from_raw_parts(bytes, size);
+  match (*core).0.moc_from_bytes(bytes) {
+    Ok(moc) => Box::into_raw(Box::new(L2dMoc(moc))),
+    Err(err) => {
+      log::error!("l2d_moc_load: {err}");
+      std::ptr::null_mut()
+    }
+  }
+}
+/// ## Safety
+/// - `moc` MUST be null or a handle returned by [`l2d_moc_load`] that has not been destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn l2d_moc_destroy(moc: *mut L2dMoc) {
+  if !moc.is_null() {
+    drop(Box::from_raw(moc));
+  }
+}
+
+/// Instantiates a model from `moc`, which the model keeps alive. The moc may be destroyed afterwards.
+/// Returns null if memory for the model cannot be allocated.
+///
+/// ## Safety
+/// - `moc` MUST be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn l2d_model_create(moc: *const L2dMoc) -> *mut L2dModel {
+  match Model::try_from_moc(&(*moc).0) {
+    Ok(model) => Box::into_raw(Box::new(L2dModel(model))),
+    Err(_) => std::ptr::null_mut(),
+  }
+}
+/// ## Safety
+/// - `model` MUST be null or a handle returned by [`l2d_model_create`] that has not been destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn l2d_model_destroy(model: *mut L2dModel) {
+  if !model.is_null() {
+    drop(Box::from_raw(model));
+  }
+}
+
+/// ## Safety
+/// - `model` MUST be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn l2d_model_parameter_count(model: *const L2dModel) -> u32 {
+  (*model).0.get_static().parameters().len() as u32
+}
+/// Finds the index of the parameter with the NUL-terminated `id`, or returns `-1`.
+///
+/// ## Safety
+/// - `model` MUST be a valid handle.
+/// - `id` MUST be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn l2d_model_find_parameter(model: *const L2dModel, id: *const c_char) -> i32 {
+  if id.is_null() {
+    return -1;
+  }
+  CStr::from_ptr(id).to_str().ok()
+    .and_then(|id| (*model).0.get_static().parameter_index(id))
+    .map_or(-1, |index| index.as_usize() as i32)
+}
+/// Gets the value of the parameter at `index`, or `0` if out of range.
+///
+/// ## Safety
+/// - `model` MUST be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn l2d_model_get_parameter(model: *const L2dModel, index: u32) -> f32 {
+  let dynamic = (*model).0.read_dynamic();
+  dynamic.parameter_values().get(index as usize).copied().unwrap_or_default()
+}
+/// Sets the value of the parameter at `index`. Returns `false` if out of range.
+///
+/// ## Safety
+/// - `model` MUST be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn l2d_model_set_parameter(model: *const L2dModel, index: u32, value: f32) -> bool {
+  let mut dynamic = (*model).0.write_dynamic();
+  match dynamic.parameter_values_mut().get_mut(index as usize) {
+    Some(parameter_value) => {
+      *parameter_value = value;
+      true
+    }
+    None => false,
+  }
+}
+
+/// Resets dynamic drawable flags and updates the model, as `csmResetDrawableDynamicFlags` followed by `csmUpdateModel` do.
+///
+/// ## Safety
+/// - `model` MUST be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn l2d_model_update(model: *const L2dModel) {
+  let mut dynamic = (*model).0.write_dynamic();
+  dynamic.reset_drawable_dynamic_flags();
+  dynamic.update();
+}
+
+/// ## Safety
+/// - `model` MUST be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn l2d_model_drawable_count(model: *const L2dModel) -> u32 {
+  (*model).0.get_static().drawables().len() as u32
+}
+/// Gets the vertex positions of the drawable at `drawable_index` as `x, y` pairs of floats in model units,
+/// writing the vertex count into `out_vertex_count`. Returns null if out of range.
+///
+/// The pointer is valid until the next [`l2d_model_update`] or [`l2d_model_destroy`].
+///
+/// ## Safety
+/// - `model` MUST be a valid handle.
+/// - `out_vertex_count` MUST be null or valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn l2d_model_vertex_positions(model: *const L2dModel, drawable_index: u32, out_vertex_count: *mut u32) -> *const f32 {
+  let dynamic = (*model).0.read_dynamic();
+  let Some(positions) = dynamic.drawable_vertex_position_containers().get(drawable_index as usize) else {
+    return std::ptr::null();
+  };
+  if !out_vertex_count.is_null() {
+    *out_vertex_count = positions.len() as u32;
+  }
+  positions.as_ptr().cast()
+}
@@ -1,18 +1,142 @@
 #![cfg(feature = "core")]
 
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 pub mod base_types;
 pub mod model_types;
+pub mod mask_types;
+pub mod tween;
+pub mod animator;
+pub mod recorder;
+pub mod mesh_export;
+pub mod state_hash;
+pub mod snapshot;
+pub mod param_handle;
+pub mod builder;
+pub mod moc_cache;
+pub mod saved_state;
+pub mod presets;
+pub mod mediapipe;
+pub mod lip_sync;
+pub mod retarget;
+pub mod standard_params;
+pub mod blend;
+pub mod color_space;
+pub mod scene;
+pub mod hooks;
+pub mod observer;
+pub mod ecs;
+pub mod vertex_delta;
+pub mod arbitration;
+pub mod input_rig;
+pub mod sequencer;
+pub mod frame_recording;
+pub mod test_support;
+pub mod allocator;
+pub mod integrity;
+pub mod decrypt;
+pub mod schema_diff;
+pub mod history;
+pub mod validation;
+pub mod output_guard;
+pub mod frame_pacing;
+pub mod replication;
+pub mod discovery;
+pub mod expression;
+#[cfg(feature = "notify")]
+pub mod watcher;
+#[cfg(feature = "vmc")]
+pub mod vmc;
+#[cfg(feature = "audio")]
+pub mod audio;
+#[cfg(feature = "cpal")]
+pub mod mic;
+#[cfg(feature = "half")]
+pub mod half_float;
+#[cfg(feature = "rhai")]
+pub mod script;
+#[cfg(feature = "debug-storage")]
+pub mod debug_storage;
+#[cfg(feature = "mock")]
+pub mod mock;
+#[cfg(target_arch = "wasm32")]
+pub mod web_stream;
+#[cfg(feature = "websocket")]
+pub mod avatar_stream;
 
 pub use base_types::{Vector2, Vector4};
-pub use base_types::{MocError, CubismVersion, MocVersion};
-pub use base_types::{TextureIndex, DrawableIndex};
+pub use base_types::{MocError, OutOfMemoryError, CubismVersion, MocVersion, MocLoadOptions};
+pub use base_types::{TextureIndex, DrawableIndex, ParameterIndex, PartIndex};
 
 pub use model_types::CanvasInfo;
-pub use model_types::{ParameterType, Parameter};
+pub use model_types::{ModelCapabilityFlags, ModelCapabilityFlagSet};
+pub use model_types::{GeometryStatistics, TextureUsage};
+pub use model_types::{ParameterType, Parameter, ParameterClampMode};
 pub use model_types::Part;
-pub use model_types::{ConstantDrawableFlags, ConstantDrawableFlagSet, DynamicDrawableFlags, DynamicDrawableFlagSet, Drawable};
+pub use model_types::{ConstantDrawableFlags, ConstantDrawableFlagSet, DynamicDrawableFlags, DynamicDrawableFlagSet, DrawableReadbackFlags, DrawableReadbackFlagSet, UpdateSummary, Drawable, UvOrigin, TriangleWinding};
+
+pub use mask_types::{MaskConsumer, MaskGroup, MaskGroups};
+pub use tween::{Easing, ParamTween};
+pub use animator::{AnimationPhase, AnimationStage, ModelAnimator, StepMode};
+pub use recorder::ParameterRecorder;
+pub use mesh_export::MeshExportFormat;
+pub use param_handle::{ParameterHandle, MissingParameterError};
+pub use builder::{ModelBuilder, ModelBuildError, InitialState};
+pub use moc_cache::MocCache;
+pub use saved_state::{SavedState, SavedStateError};
+pub use presets::{Preset, Presets, PresetError};
+pub use mediapipe::{FaceLandmarkerFrame, MediaPipeFaceAdapter};
+pub use blend::{BlendMode, BlendFactor, BlendFactors, DrawableColorConstants};
+pub use scene::{Scene, SceneModelId, SceneTransform, SceneDrawItem, SceneDrawInstance, SceneDrawBatch};
+pub use hooks::PostUpdateHookId;
+pub use observer::{ParameterChange, ParameterChangeTracker};
+pub use vertex_delta::{RestPose, VertexDelta};
+pub use arbitration::{ArbitrationMode, ParameterArbiter, ParameterWriterId};
+pub use sequencer::Sequencer;
+pub use integrity::Sha256Digest;
+pub use schema_diff::{SchemaDiff, ParameterSchemaChanges, ParameterSchemaChangeSet, DrawableSchemaChanges, DrawableSchemaChangeSet};
+pub use history::ParameterHistory;
+pub use output_guard::{NonFiniteOutput, NonFiniteValue};
+pub use validation::{MeshIssues, MeshIssueSet, MeshValidationReport};
+pub use frame_pacing::FrameInterpolator;
+pub use replication::{ReplicationEncoder, ReplicationDecoder, ReplicationError};
+pub use discovery::{ParameterDiscovery, ParameterClassification, ParameterRole};
+pub use expression::{Expression, ExpressionParameter, ExpressionBlend, ExpressionCaptureOptions};
+pub use decrypt::{AssetDecryptor, AssetKind, DecryptError};
+pub use frame_recording::{FrameRecorder, FrameReader, FrameRecordingError, FrameContentFlags, FrameContentFlagSet, RecordedFrame};
+pub use input_rig::{InputRig, InputRigConfig, InputRigError, InputBinding, ResponseCurve};
+pub use ecs::{ModelHandleComponent, ParameterWrite, ParameterWrites, FrameOutput};
+pub use retarget::{ParameterRetargeter, RangeMapping};
+pub use lip_sync::{AmplitudeEnvelope, LipSync, VisemeEvent, VisemeShapeTable, VisemeLipSync};
+pub use snapshot::{ModelDynamicSnapshot, DiffTolerances, DrawableDiffFlags, DrawableDiffFlagSet, SnapshotDiff};
+#[cfg(feature = "serde")]
+pub use recorder::Motion3ExportOptions;
+#[cfg(all(feature = "notify", not(target_arch = "wasm32")))]
+pub use watcher::{ModelWatcher, ModelWatchError, ReloadEvent};
+#[cfg(all(feature = "vmc", not(target_arch = "wasm32")))]
+pub use vmc::{VmcAxis, VmcSource, VmcMapping, VmcMappingTable, VmcReceiver};
+#[cfg(feature = "audio")]
+pub use audio::AudioDecodeError;
+#[cfg(all(feature = "cpal", not(target_arch = "wasm32")))]
+pub use mic::{MicLipSync, MicLipSyncError, MicLipSyncStage};
+#[cfg(feature = "rhai")]
+pub use script::{ParameterScript, ScriptError};
+#[cfg(target_arch = "wasm32")]
+pub use web_stream::MocStreamError;
+#[cfg(feature = "websocket")]
+pub use avatar_stream::AvatarStreamError;
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+pub use avatar_stream::AvatarStreamSender;
+#[cfg(all(feature = "websocket", target_arch = "wasm32"))]
+pub use avatar_stream::WebAvatarStreamReceiver;
+#[cfg(feature = "mock")]
+pub use mock::MockMoc;
+#[cfg(not(target_arch = "wasm32"))]
+pub use allocator::{StorageAllocator, SystemStorageAllocator, PooledStorageAllocator};
 
 mod internal;
 
@@ -38,17 +162,21 @@ use internal::platform_iface::{
 pub struct CubismCore {
   #[allow(dead_code)]
   inner: PlatformCubismCore,
+  decryptor: Option<AssetDecryptor>,
 }
 impl CubismCore {
   /// Sets a global log handler function to intercept _Live2D® Cubism SDK Core_'s internal log.
   ///
+  /// The handler is process-global, replacing any previous one for every [`CubismCore`].
+  ///
   /// ## Safety
   /// - MUST be externally synchronized with calls to `csmGetLogFunction` and `csmSetLogFunction`.
   ///   This is a precaution since their threading behavior is not well documented.
   ///
   /// ## Platform-specific
-  /// - **Web:** Unsupported.
-  #[cfg(not(target_arch = "wasm32"))]
+  /// - **Web:** Routed through `Live2DCubismCore.Logging.csmSetLogFunction`.
+  ///   The handler is shared by all [`CubismCore`] instances, including ones created before this call.
+  ///   It may set another handler, and messages logged during its call are dropped.
   pub unsafe fn set_log_function<F>(f: F)
   where
     F: FnMut(&str) + Send + 'static,
@@ -65,29 +193,147 @@ impl CubismCore {
     self.inner.latest_supported_moc_version()
   }
 
+  /// Gets the moc3 version of `bytes` without keeping a `Moc` around.
+  ///
+  /// Whether the version is supported is not checked, making this suitable for asset validation.
+  ///
+  /// ## Platform-specific
+  /// - **Web:** A temporary `Live2DCubismCore.Moc` is created and released internally,
+  ///   since `Live2DCubismCore.Version.csmGetMocVersion` requires one.
+  pub fn moc_version_from_bytes(&self, bytes: &[u8]) -> Result<MocVersion, MocError> {
+    self.inner.moc_version_from_bytes(&self.decrypt_moc(bytes)?)
+  }
+
   /// Deserializes a `Moc` from bytes.
   pub fn moc_from_bytes(&self, bytes: &[u8]) -> Result<Moc, MocError> {
+    self.moc_from_bytes_with_options(bytes, &MocLoadOptions::default())
+  }
+  /// Deserializes a `Moc` from bytes, with [`MocLoadOptions`].
+  ///
+  /// [`MocLoadOptions::expected_sha256`] is checked against `bytes` as given, before decryption by [`CubismCore::set_decryptor`].
+  ///
+  /// ## Platform-specific
+  /// - **Web:** `Live2DCubismCore.Moc.fromArrayBuffer` checks the version by itself,
+  ///   so a moc it accepts is never rejected for its version.
+  // On web, `PlatformMoc` holds JavaScript objects and is neither `Send` nor `Sync`, but neither is `Moc` there.
+  #[cfg_attr(target_arch = "wasm32", allow(clippy::arc_with_non_send_sync))]
+  pub fn moc_from_bytes_with_options(&self, bytes: &[u8], options: &MocLoadOptions) -> Result<Moc, MocError> {
+    if let Some(expected) = options.expected_sha256 {
+      let actual = integrity::sha256(bytes);
+      if actual != expected {
+        return Err(MocError::IntegrityMismatch { expected, actual });
+      }
+    }
+
     self.inner
-      .platform_moc_from_bytes(bytes)
+      .platform_moc_from_bytes(&self.decrypt_moc(bytes)?, options)
       .map(|(moc_version, platform_moc)| {
         Moc {
           version: moc_version,
-          inner: platform_moc
+          inner: Arc::new(platform_moc),
+          model_count: Arc::default(),
         }
       })
   }
+
+  /// Sets the callback decrypting the bytes of every moc loaded through this [`CubismCore`], or removes it with `None`.
+  pub fn set_decryptor(&mut self, decryptor: Option<AssetDecryptor>) {
+    self.decryptor = decryptor;
+  }
+  /// Gets the callback set with [`CubismCore::set_decryptor`], e.g. to decrypt textures alike.
+  pub fn decryptor(&self) -> Option<&AssetDecryptor> {
+    self.decryptor.as_ref()
+  }
+  fn decrypt_moc<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, [u8]>, MocError> {
+    match &self.decryptor {
+      Some(decryptor) => decryptor.decrypt(AssetKind::Moc, bytes)
+        .map(Cow::Owned)
+        .map_err(|error| MocError::Decryption(error.into())),
+      None => Ok(Cow::Borrowed(bytes)),
+    }
+  }
+
+  /// Like [`CubismCore::moc_from_bytes`], but returns the moc already in `cache` if it was loaded from identical bytes.
+  ///
+  /// Bytes are compared through their length and SHA-256 digest.
+  pub fn moc_cached(&self, cache: &MocCache, bytes: &[u8]) -> Result<Moc, MocError> {
+    cache.get_or_load(self, bytes)
+  }
 }
 
 /// Cubism moc.
-#[derive(Debug)]
+///
+/// Cloning is cheap, as clones share the same underlying moc.
+/// Every [`Model`] keeps the moc it was created from alive, so a [`Moc`] can be dropped while its models are still in use.
+#[derive(Debug, Clone)]
 pub struct Moc {
   version: MocVersion,
-  inner: PlatformMoc,
+  inner: Arc<PlatformMoc>,
+  /// Shared between clones, incremented and decremented by [`ModelCountToken`].
+  model_count: Arc<AtomicUsize>,
 }
 impl Moc {
+  /// Returns `true` if both share the same underlying moc, as [`Arc::ptr_eq`] does.
+  pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+    Arc::ptr_eq(&this.inner, &other.inner)
+  }
+
   pub fn version(&self) -> MocVersion {
     self.version
   }
+
+  /// Number of [`Model`]s created from this moc, or any of its clones, that are still alive.
+  ///
+  /// Once it reaches `0`, the moc is only kept alive by clones of [`Moc`] itself, e.g. in a [`MocCache`].
+  pub fn model_count(&self) -> usize {
+    self.model_count.load(Ordering::Acquire)
+  }
+}
+
+/// Counts a live [`Model`] in [`Moc::model_count`] for as long as it is held.
+#[derive(Debug)]
+struct ModelCountToken(Arc<AtomicUsize>);
+impl ModelCountToken {
+  fn new(moc: &Moc) -> Self {
+    moc.model_count.fetch_add(1, Ordering::AcqRel);
+    Self(Arc::clone(&moc.model_count))
+  }
+}
+impl Drop for ModelCountToken {
+  fn drop(&mut self) {
+    self.0.fetch_sub(1, Ordering::AcqRel);
+  }
+}
+if_native! {
+  impl Moc {
+    /// Gets the underlying revived `csmMoc`, e.g. for C/C++ code instantiating its own `csmModel`s from it.
+    ///
+    /// ## Safety
+    /// Aliasing contract:
+    /// - The `csmMoc` is shared and treated as immutable by this crate, including by every [`Model`] created from it.
+    ///   It MUST NOT be written to, revived again or have its memory released through the pointer.
+    /// - The pointer is valid only for as long as this [`Moc`] is alive.
+    ///   Any `csmModel` initialized from it outside of this crate MUST NOT outlive this [`Moc`].
+    /// - Any `csmModel` initialized from it outside of this crate MUST be placed in separate memory,
+    ///   aligned to `csmAlignofModel` and at least `csmGetSizeofModel` bytes large.
+    ///
+    /// ## Platform-specific
+    /// - **Web:** Unsupported.
+    pub unsafe fn as_csm_moc_ptr(&self) -> *const crate::sys::csmMoc {
+      self.inner.csm_moc()
+    }
+
+    /// Size in bytes of the memory block of each [`Model`] created from this moc, as returned by `csmGetSizeofModel`,
+    /// e.g. to budget memory for a number of instances before creating them.
+    ///
+    /// Excludes the comparatively small allocations for [`ModelStatic`] and [`ModelDynamic`].
+    ///
+    /// ## Platform-specific
+    /// - **Web:** Unsupported, as models live in the memory of the JavaScript Core.
+    pub fn model_size_hint(&self) -> usize {
+      self.inner.model_storage_size() as usize
+    }
+  }
 }
 
 /// Cubism model.
@@ -95,22 +341,122 @@ impl Moc {
 pub struct Model {
   model_static: ModelStatic,
   model_dynamic: RwLock<ModelDynamic>,
+  post_update_hooks: hooks::PostUpdateHooks,
+  user_data: parking_lot::Mutex<Option<Arc<dyn std::any::Any + Send + Sync>>>,
+  _model_count_token: ModelCountToken,
+  /// Declared last to be dropped last, as the model references it.
+  moc: Moc,
 }
 impl Model {
+  /// Creates a model from `moc`, which it keeps alive.
+  ///
+  /// ## Panics
+  /// - If memory for the model cannot be allocated. See [`Model::try_from_moc`].
   pub fn from_moc(moc: &Moc) -> Self {
-    let (platform_model_static, platform_model_dynamic) = moc.inner.new_platform_model();
+    Self::try_from_moc(moc).unwrap_or_else(|err| panic!("{err}"))
+  }
+  /// Like [`Model::from_moc`], but fails instead of panicking if memory for the model cannot be allocated,
+  /// e.g. to report running out of memory with many large models.
+  ///
+  /// ## Platform-specific
+  /// - **Web:** Never fails, as memory is allocated by the JavaScript Core.
+  pub fn try_from_moc(moc: &Moc) -> Result<Self, OutOfMemoryError> {
+    let (platform_model_static, platform_model_dynamic) = moc.inner.new_platform_model()?;
 
     let model_static = ModelStatic {
       inner: platform_model_static,
     };
     let model_dynamic = ModelDynamic {
       inner: platform_model_dynamic,
+      drawable_user_visibilities: vec![true; model_static.drawables().len()].into_boxed_slice(),
+      parameter_value_ranges: model_static.parameters().iter().map(Parameter::value_range).collect(),
+      parameter_clamp_mode: ParameterClampMode::default(),
+      parameter_cyclics: vec![false; model_static.parameters().len()].into_boxed_slice(),
+      geometry_readback: true,
+      readback_mask: DrawableReadbackFlagSet::full(),
+      parameter_history: None,
     };
 
-    Self {
+    Ok(Self {
       model_static,
       model_dynamic: RwLock::new(model_dynamic),
+      post_update_hooks: hooks::PostUpdateHooks::default(),
+      user_data: parking_lot::Mutex::new(None),
+      _model_count_token: ModelCountToken::new(moc),
+      moc: moc.clone(),
+    })
+  }
+
+  /// Starts building a model from `moc` with an initial state.
+  ///
+  /// ```ignore
+  /// let model = Model::builder(&moc)
+  ///   .parameter("ParamAngleX", 15.0)
+  ///   .part_opacity("PartArmL", 0.0)
+  ///   .build()?;
+  /// ```
+  pub fn builder(moc: &Moc) -> ModelBuilder<'_> {
+    ModelBuilder::new(moc)
+  }
+
+  /// Creates a new model from `new_moc`, e.g. an edited version of this model's moc,
+  /// carrying over parameter values and part opacities by ID, and runs the first [`ModelDynamic::update`].
+  ///
+  /// Parameters and parts not found in this model keep the values from _Live2D® Cubism SDK Core_.
+  ///
+  /// Acquires a read lock for this model's [`ModelDynamic`].
+  pub fn reload_from(&self, new_moc: &Moc) -> Model {
+    let model = Model::from_moc(new_moc);
+    {
+      let mut dynamic = model.write_dynamic();
+      builder::copy_state_by_id(self.get_static(), &self.read_dynamic(), model.get_static(), &mut dynamic);
+      dynamic.update();
     }
+    model
+  }
+
+  /// Creates a new model from the same moc, copying parameter values, part opacities, drawable user visibilities,
+  /// the parameter clamp mode and cyclic parameters, and runs the first [`ModelDynamic::update`].
+  ///
+  /// Post-update hooks, user data and parameter history are not copied.
+  ///
+  /// Acquires a read lock for this model's [`ModelDynamic`].
+  pub fn duplicate(&self) -> Model {
+    let model = Model::from_moc(&self.moc);
+    {
+      let source = self.read_dynamic();
+      let mut dynamic = model.write_dynamic();
+      dynamic.parameter_values_mut().copy_from_slice(source.parameter_values());
+      dynamic.part_opacities_mut().copy_from_slice(source.part_opacities());
+      dynamic.drawable_user_visibilities.copy_from_slice(&source.drawable_user_visibilities);
+      dynamic.parameter_clamp_mode = source.parameter_clamp_mode;
+      dynamic.parameter_cyclics.copy_from_slice(&source.parameter_cyclics);
+      dynamic.geometry_readback = source.geometry_readback;
+      dynamic.readback_mask = source.readback_mask;
+      dynamic.update();
+    }
+    model
+  }
+
+  /// Resets the state of _Live2D® Cubism SDK Core_ for this model, e.g. to recover from pathological parameter values,
+  /// without reallocating: parameter values and part opacities return to their defaults.
+  ///
+  /// Settings of [`ModelDynamic`] itself, e.g. drawable user visibilities, are kept.
+  /// As with [`Model::from_moc`], the dynamic drawable data is only valid after the next [`ModelDynamic::update`].
+  ///
+  /// Acquires a write lock for [`ModelDynamic`].
+  ///
+  /// ## Platform-specific
+  /// - **Native:** Re-runs `csmInitializeModelInPlace` into the existing memory block.
+  /// - **Web:** Re-creates the `Live2DCubismCore.Model`, reusing the existing scratch buffers.
+  pub fn reinitialize(&self) {
+    let mut dynamic = self.model_dynamic.write();
+    self.moc.inner.reinitialize_platform_model(&mut dynamic.inner);
+  }
+
+  /// Gets the [`Moc`] this model was created from.
+  pub fn moc(&self) -> &Moc {
+    &self.moc
   }
 
   /// Gets [`ModelStatic`].
@@ -118,18 +464,124 @@ impl Model {
     &self.model_static
   }
 
+  /// Features used by the model, including multiply and screen colors in the current dynamic state.
+  ///
+  /// Acquires a read lock for [`ModelDynamic`].
+  pub fn capabilities(&self) -> ModelCapabilityFlagSet {
+    let mut capabilities = self.model_static.capabilities();
+
+    let dynamic = self.read_dynamic();
+    if dynamic.drawable_multiply_colors().iter().any(|color| (color.x, color.y, color.z) != (1.0, 1.0, 1.0)) {
+      capabilities |= ModelCapabilityFlags::MultiplyColors;
+    }
+    if dynamic.drawable_screen_colors().iter().any(|color| (color.x, color.y, color.z) != (0.0, 0.0, 0.0)) {
+      capabilities |= ModelCapabilityFlags::ScreenColors;
+    }
+
+    capabilities
+  }
+
+  /// Writes the current deformed meshes of all drawables (positions, UVs, indices) for debugging.
+  ///
+  /// With [`MeshExportFormat::Gltf`], fails with [`std::io::ErrorKind::InvalidData`] if a vertex position is not finite.
+  ///
+  /// Acquires a read lock for [`ModelDynamic`].
+  pub fn write_mesh<W: std::io::Write>(&self, writer: W, format: MeshExportFormat) -> std::io::Result<()> {
+    mesh_export::write_mesh(writer, &self.model_static, &self.read_dynamic(), format)
+  }
+  /// Like [`Model::write_mesh`], but into a file at `path`.
+  pub fn export_mesh<P: AsRef<std::path::Path>>(&self, path: P, format: MeshExportFormat) -> std::io::Result<()> {
+    let file = std::fs::File::create(path)?;
+    self.write_mesh(std::io::BufWriter::new(file), format)
+  }
+
   /// Acquires a read (shared) lock for [`ModelDynamic`].
-  pub fn read_dynamic(&self) -> ModelDynamicReadLockGuard {
+  pub fn read_dynamic(&self) -> ModelDynamicReadLockGuard<'_> {
     ModelDynamicReadLockGuard {
+      model_static: &self.model_static,
       inner: self.model_dynamic.read(),
     }
   }
   /// Acquires a write (mutable) lock for [`ModelDynamic`].
-  pub fn write_dynamic(&self) -> ModelDynamicWriteLockGuard {
+  pub fn write_dynamic(&self) -> ModelDynamicWriteLockGuard<'_> {
     ModelDynamicWriteLockGuard {
+      model_static: &self.model_static,
+      post_update_hooks: &self.post_update_hooks,
       inner: self.model_dynamic.write(),
     }
   }
+
+  /// Adds `hook` to be called after each [`ModelDynamicWriteLockGuard::update`], including by [`ModelAnimator`],
+  /// with the write lock still held.
+  ///
+  /// Hooks are not called by [`ModelDynamic::update`] on a `&mut ModelDynamic` obtained otherwise.
+  /// A hook MUST NOT add or remove hooks of the same model, or acquire a lock for its [`ModelDynamic`].
+  pub fn add_post_update_hook<F>(&self, hook: F) -> PostUpdateHookId
+  where
+    F: FnMut(&ModelStatic, &ModelDynamic) + Send + 'static,
+  {
+    self.post_update_hooks.add(Box::new(hook))
+  }
+  /// Returns `false` if the hook was already removed.
+  pub fn remove_post_update_hook(&self, id: PostUpdateHookId) -> bool {
+    self.post_update_hooks.remove(id)
+  }
+
+  /// Attaches `data` to this model, e.g. an engine's texture handles or entity ID, replacing any previous data.
+  pub fn set_user_data<T: std::any::Any + Send + Sync>(&self, data: T) {
+    *self.user_data.lock() = Some(Arc::new(data));
+  }
+  /// Gets the data attached with [`Model::set_user_data`], if any and of type `T`.
+  pub fn user_data<T: std::any::Any + Send + Sync>(&self) -> Option<Arc<T>> {
+    self.user_data.lock().clone()?.downcast().ok()
+  }
+  /// Detaches the data attached with [`Model::set_user_data`], if any.
+  pub fn clear_user_data(&self) {
+    *self.user_data.lock() = None;
+  }
+}
+if_native! {
+  impl CubismCore {
+    /// Gets a process-wide [`CubismCore`], created on first use.
+    ///
+    /// It uses [`SystemStorageAllocator`], and is otherwise equivalent to any other instance, as [`CubismCore`] holds
+    /// no other state of its own on Native.
+    ///
+    /// ## Platform-specific
+    /// - **Web:** Unavailable, as instances cannot be shared between threads. Create them with [`CubismCore::default`].
+    pub fn global() -> &'static CubismCore {
+      static GLOBAL: std::sync::OnceLock<CubismCore> = std::sync::OnceLock::new();
+      GLOBAL.get_or_init(CubismCore::default)
+    }
+
+    /// Creates a [`CubismCore`] whose mocs, and the models created from them, allocate their memory blocks from `allocator`.
+    ///
+    /// ## Platform-specific
+    /// - **Web:** Unavailable, as memory is allocated by the JavaScript Core.
+    pub fn with_allocator(allocator: Arc<dyn StorageAllocator>) -> Self {
+      Self {
+        inner: PlatformCubismCore::with_allocator(allocator),
+        decryptor: None,
+      }
+    }
+  }
+  impl Model {
+    /// Gets the underlying `csmModel`, for calling functions in [`crate::sys`] not yet covered by this interface.
+    ///
+    /// The pointer stays valid for as long as this [`Model`] is alive.
+    ///
+    /// ## Safety
+    /// - Whatever is read or written through the pointer MUST be synchronized as [`ModelDynamic`] is:
+    ///   hold a [`ModelDynamicReadLockGuard`] while reading dynamic state, and a [`ModelDynamicWriteLockGuard`] while writing it.
+    /// - Call [`ModelDynamic::update`] instead of `csmUpdateModel`, so that cached state is refreshed.
+    /// - The `csmModel` MUST NOT be re-initialized or have its memory released.
+    ///
+    /// ## Platform-specific
+    /// - **Web:** Unsupported.
+    pub unsafe fn as_csm_model_ptr(&self) -> *mut crate::sys::csmModel {
+      self.model_static.inner.csm_model()
+    }
+  }
 }
 
 /// Static properties of a model.
@@ -143,12 +595,77 @@ impl ModelStatic {
   pub fn parts(&self) -> &[Part] { self.inner.parts() }
   pub fn drawables(&self) -> &[Drawable] { self.inner.drawables() }
   pub fn get_drawable(&self, index: DrawableIndex) -> Option<&Drawable> { self.inner.get_drawable(index) }
+
+  /// Finds a parameter by its ID.
+  pub fn parameter_index(&self, id: &str) -> Option<ParameterIndex> {
+    self.parameters().iter().position(|parameter| parameter.id() == id).map(ParameterIndex::from)
+  }
+  /// Looks up the index of the part with `id`.
+  pub fn part_index(&self, id: &str) -> Option<PartIndex> {
+    self.parts().iter().position(|part| part.id() == id).map(PartIndex::from)
+  }
+
+  /// Features used by the model that are known from static properties alone.
+  ///
+  /// [`ModelCapabilityFlags::MultiplyColors`] and [`ModelCapabilityFlags::ScreenColors`] depend on the dynamic state,
+  /// and are only reported by [`Model::capabilities`].
+  pub fn capabilities(&self) -> ModelCapabilityFlagSet {
+    let mut capabilities = ModelCapabilityFlagSet::default();
+
+    if self.parameters().iter().any(|parameter| parameter.ty() == ParameterType::BlendShape) {
+      capabilities |= ModelCapabilityFlags::BlendShapeParameters;
+    }
+    for drawable in self.drawables() {
+      let constant_flagset = drawable.constant_flagset();
+
+      if !drawable.masks().is_empty() {
+        capabilities |= ModelCapabilityFlags::Masks;
+        if constant_flagset.contains(ConstantDrawableFlags::IsInvertedMask) {
+          capabilities |= ModelCapabilityFlags::InvertedMasks;
+        }
+      }
+      if constant_flagset.contains(ConstantDrawableFlags::BlendAdditive) {
+        capabilities |= ModelCapabilityFlags::AdditiveBlending;
+      }
+      if constant_flagset.contains(ConstantDrawableFlags::BlendMultiplicative) {
+        capabilities |= ModelCapabilityFlags::MultiplicativeBlending;
+      }
+    }
+
+    capabilities
+  }
+
+  /// Aggregate geometry totals over all drawables.
+  pub fn geometry_statistics(&self) -> GeometryStatistics {
+    GeometryStatistics::from_drawables(self.drawables())
+  }
+
+  /// The drawables using each texture, indexed by [`TextureIndex`], with their vertex and triangle totals.
+  pub fn drawables_by_texture(&self) -> Vec<TextureUsage> {
+    TextureUsage::from_drawables(self.drawables())
+  }
+
+  /// Builds the mask dependency graph of the drawables.
+  ///
+  /// This is computed on each call; keep the result around if needed every frame.
+  pub fn mask_groups(&self) -> MaskGroups {
+    MaskGroups::from_drawables(self.drawables())
+  }
 }
 
 /// Dynamic states of a model.
 #[derive(Debug)]
 pub struct ModelDynamic {
   inner: PlatformModelDynamic,
+  /// Set by the user, independently of [`DynamicDrawableFlags::IsVisible`].
+  drawable_user_visibilities: Box<[bool]>,
+  /// Copied from [`ModelStatic`] for [`ParameterClampMode`].
+  parameter_value_ranges: Box<[(f32, f32)]>,
+  parameter_clamp_mode: ParameterClampMode,
+  parameter_cyclics: Box<[bool]>,
+  geometry_readback: bool,
+  readback_mask: DrawableReadbackFlagSet,
+  parameter_history: Option<ParameterHistory>,
 }
 impl ModelDynamic {
   pub fn parameter_values(&self) -> &[f32] { self.inner.parameter_values() }
@@ -164,19 +681,306 @@ impl ModelDynamic {
   pub fn drawable_multiply_colors(&self) -> &[Vector4] { self.inner.drawable_multiply_colors() }
   pub fn drawable_screen_colors(&self) -> &[Vector4] { self.inner.drawable_screen_colors() }
 
+  /// Whether each drawable is visible as set with [`ModelDynamic::set_drawable_user_visible`], regardless of the model's own state.
+  pub fn drawable_user_visibilities(&self) -> &[bool] {
+    &self.drawable_user_visibilities
+  }
+  /// Hides or shows a drawable, e.g. for outfit toggles or to inspect individual meshes.
+  ///
+  /// _Live2D® Cubism SDK Core_ is unaware of this, so renderers need to check [`ModelDynamic::is_drawable_drawn`].
+  ///
+  /// ## Panics
+  /// - If `index` is out of range.
+  pub fn set_drawable_user_visible(&mut self, index: DrawableIndex, visible: bool) {
+    self.drawable_user_visibilities[index.as_usize()] = visible;
+  }
+  /// Shows all drawables hidden with [`ModelDynamic::set_drawable_user_visible`].
+  pub fn reset_drawable_user_visibilities(&mut self) {
+    self.drawable_user_visibilities.fill(true);
+  }
+  /// Whether a drawable should be drawn: both [`DynamicDrawableFlags::IsVisible`] and visible by the user.
+  ///
+  /// ## Panics
+  /// - If `index` is out of range.
+  pub fn is_drawable_drawn(&self, index: DrawableIndex) -> bool {
+    self.drawable_user_visibilities[index.as_usize()]
+      && self.drawable_dynamic_flagsets()[index.as_usize()].contains(DynamicDrawableFlags::IsVisible)
+  }
+
+  /// Gets every drawable in the order to draw them, back to front, so that every renderer layers them alike.
+  ///
+  /// Drawables are sorted by render order, which accounts for part draw orders, then by draw order, then by index,
+  /// so that the sequence is deterministic even if _Live2D® Cubism SDK Core_ reports ties.
+  /// Hidden drawables are included; skip them with [`ModelDynamic::is_drawable_drawn`].
+  pub fn drawing_sequence(&self) -> Vec<DrawableIndex> {
+    let mut sequence = Vec::new();
+    self.write_drawing_sequence(&mut sequence);
+    sequence
+  }
+  /// Like [`ModelDynamic::drawing_sequence`], but writes into `out` to reuse its allocation. `out` is cleared first.
+  pub fn write_drawing_sequence(&self, out: &mut Vec<DrawableIndex>) {
+    let render_orders = self.drawable_render_orders();
+    let draw_orders = self.drawable_draw_orders();
+
+    out.clear();
+    out.extend((0..render_orders.len()).map(DrawableIndex::from));
+    out.sort_unstable_by_key(|index| (render_orders[index.as_usize()], draw_orders[index.as_usize()], *index));
+  }
+
+  /// Gets the vertex positions of a drawable transformed into canvas pixels.
+  ///
+  /// See [`CanvasInfo::unit_to_pixel`] for the coordinate convention.
+  pub fn drawable_vertex_positions_in_pixels(&self, index: DrawableIndex, canvas_info: &CanvasInfo) -> Option<Vec<Vector2>> {
+    let mut positions = Vec::new();
+    self.write_drawable_vertex_positions_in_pixels(index, canvas_info, &mut positions)?;
+    Some(positions)
+  }
+  /// Like [`ModelDynamic::drawable_vertex_positions_in_pixels`], but writes into `out` to reuse its allocation.
+  ///
+  /// `out` is cleared first. Returns the number of vertices written, or `None` if `index` is out of range.
+  pub fn write_drawable_vertex_positions_in_pixels(&self, index: DrawableIndex, canvas_info: &CanvasInfo, out: &mut Vec<Vector2>) -> Option<usize> {
+    let positions = self.drawable_vertex_position_containers().get(index.as_usize())?;
+
+    out.clear();
+    out.extend(positions.iter().map(|&position| canvas_info.unit_to_pixel(position)));
+    Some(positions.len())
+  }
+
+  /// Orientation of the triangles of `drawable` at their current vertex positions, which deformation may flip.
+  ///
+  /// ## Panics
+  /// - If `drawable` is not of this model.
+  pub fn drawable_winding(&self, drawable: &Drawable) -> Option<TriangleWinding> {
+    TriangleWinding::of(self.drawable_vertex_position_containers()[drawable.index().as_usize()], drawable.triangle_indices())
+  }
+
+  /// Takes an owned copy of the current state.
+  pub fn snapshot(&self) -> ModelDynamicSnapshot {
+    ModelDynamicSnapshot::new(self)
+  }
+  /// Sets parameter values and part opacities to a weighted blend of the ones in `a` and `b`,
+  /// e.g. for simple transitions between saved poses.
+  ///
+  /// `t` is clamped to `[0, 1]`, where `0` yields `a` and `1` yields `b`.
+  /// Cyclic parameters are blended the shorter way around their range, wrapping into it.
+  /// Drawable states in the snapshots are ignored; call [`ModelDynamic::update`] afterwards to recompute them.
+  ///
+  /// ## Panics
+  /// - If either snapshot was taken from a model of a different moc.
+  pub fn blend_from_snapshots(&mut self, a: &ModelDynamicSnapshot, b: &ModelDynamicSnapshot, t: f32) {
+    self.blend_parameter_state(
+      (a.parameter_values(), a.part_opacities()),
+      (b.parameter_values(), b.part_opacities()),
+      t,
+    );
+  }
+  /// Blends parameter values and part opacities as with [`ModelDynamic::blend_from_snapshots`],
+  /// each state given as `(parameter_values, part_opacities)`.
+  pub(crate) fn blend_parameter_state(&mut self, a: (&[f32], &[f32]), b: (&[f32], &[f32]), t: f32) {
+    let t = t.clamp(0.0, 1.0);
+    let blend = |dst: &mut [f32], a: &[f32], b: &[f32]| {
+      assert!(dst.len() == a.len() && dst.len() == b.len(), "State does not match the model");
+      for (dst, (&a, &b)) in dst.iter_mut().zip(a.iter().zip(b.iter())) {
+        *dst = a + (b - a) * t;
+      }
+    };
+    blend(self.parameter_values_mut(), a.0, b.0);
+    blend(self.part_opacities_mut(), a.1, b.1);
+
+    let Self { inner, parameter_value_ranges, parameter_cyclics, .. } = self;
+    for (i, value) in inner.parameter_values_mut().iter_mut().enumerate().filter(|&(i, _)| parameter_cyclics[i]) {
+      let range = parameter_value_ranges[i];
+      let period = range.1 - range.0;
+      let (a, b) = (a.0[i], b.0[i]);
+      // The shorter difference, in [-period / 2, period / 2).
+      let delta = model_types::wrap_into_range(b - a, (-period * 0.5, period * 0.5));
+      *value = model_types::wrap_into_range(a + delta * t, range);
+    }
+  }
+
+  /// Computes a hash of the current state, stable across platforms and runs.
+  ///
+  /// Covers parameter values, part opacities, drawable opacities, draw and render orders, and vertex positions
+  /// quantized by [`state_hash::DEFAULT_VERTEX_POSITION_QUANTUM`], so that refactors of an animation pipeline can be
+  /// asserted to produce identical output.
+  pub fn state_hash(&self) -> u64 {
+    self.state_hash_with_quantum(state_hash::DEFAULT_VERTEX_POSITION_QUANTUM)
+  }
+  /// Like [`ModelDynamic::state_hash`], but quantizes vertex positions to multiples of `vertex_position_quantum` model units.
+  ///
+  /// ## Panics
+  /// - If `vertex_position_quantum` is not positive.
+  pub fn state_hash_with_quantum(&self, vertex_position_quantum: f32) -> u64 {
+    assert!(vertex_position_quantum > 0.0, "Quantum must be positive, given: {vertex_position_quantum}");
+    state_hash::state_hash(self, vertex_position_quantum)
+  }
+  /// Computes a hash of the inputs of an update only, i.e. parameter values and part opacities,
+  /// e.g. to tell in a bug report whether two reproductions start from the same state.
+  pub fn parameter_state_hash(&self) -> u64 {
+    state_hash::parameter_state_hash(self)
+  }
+
+  pub fn parameter_clamp_mode(&self) -> ParameterClampMode {
+    self.parameter_clamp_mode
+  }
+  pub fn set_parameter_clamp_mode(&mut self, mode: ParameterClampMode) {
+    self.parameter_clamp_mode = mode;
+  }
+  /// Whether the range of each parameter repeats, as set with [`ModelDynamic::set_parameter_cyclic`].
+  pub fn parameter_cyclics(&self) -> &[bool] {
+    &self.parameter_cyclics
+  }
+  /// Marks a parameter whose range repeats, e.g. a full rotation, so that it is wrapped rather than clamped
+  /// and blended along the shorter way around.
+  ///
+  /// ## Panics
+  /// - If `index` is out of range.
+  pub fn set_parameter_cyclic(&mut self, index: ParameterIndex, cyclic: bool) {
+    self.parameter_cyclics[index.as_usize()] = cyclic;
+  }
+
+  /// Whether [`ModelDynamic::update`] refreshes vertex positions and colors, as set with [`ModelDynamic::set_geometry_readback`].
+  pub fn geometry_readback(&self) -> bool {
+    self.geometry_readback
+  }
+  /// Sets whether [`ModelDynamic::update`] refreshes vertex positions and colors, e.g. to skip them while the model
+  /// is off-screen or too distant to be drawn, still advancing parameters. `true` by default.
+  ///
+  /// While disabled, vertex positions and colors may be left as of the last update with it enabled.
+  /// Opacities, orders and dynamic flags are always refreshed.
+  ///
+  /// ## Platform-specific
+  /// - **Native:** No effect, as they are read in place from the memory of _Live2D® Cubism SDK Core_.
+  /// - **Web:** Skips copying them out of the JavaScript Core on each update.
+  pub fn set_geometry_readback(&mut self, enabled: bool) {
+    self.geometry_readback = enabled;
+  }
+  /// Arrays refreshed by [`ModelDynamic::update`], as set with [`ModelDynamic::set_readback_mask`].
+  pub fn readback_mask(&self) -> DrawableReadbackFlagSet {
+    self.readback_mask
+  }
+  /// Sets which arrays [`ModelDynamic::update`] refreshes, e.g. to leave out screen colors and draw orders
+  /// if the renderer never reads them. All by default.
+  ///
+  /// Arrays left out may be left as of the last update including them.
+  /// While [`ModelDynamic::geometry_readback`] is `false`, vertex positions and colors are left out regardless.
+  ///
+  /// ## Platform-specific
+  /// - **Native:** No effect, as they are read in place from the memory of _Live2D® Cubism SDK Core_.
+  /// - **Web:** Skips copying the arrays left out of the JavaScript Core on each update.
+  pub fn set_readback_mask(&mut self, readback_mask: DrawableReadbackFlagSet) {
+    self.readback_mask = readback_mask;
+  }
+
+  /// Clamps all parameter values into their ranges, regardless of [`ModelDynamic::parameter_clamp_mode`].
+  ///
+  /// Cyclic parameters are wrapped instead.
+  pub fn clamp_parameter_values(&mut self) {
+    let Self { inner, parameter_value_ranges, parameter_cyclics, .. } = self;
+    for ((value, &range), &cyclic) in inner.parameter_values_mut().iter_mut().zip(parameter_value_ranges.iter()).zip(parameter_cyclics.iter()) {
+      *value = if cyclic { model_types::wrap_into_range(*value, range) } else { value.clamp(range.0, range.1) };
+    }
+  }
+
+  /// Updates drawables from the current parameter values and part opacities.
+  ///
+  /// With [`ParameterClampMode::OnUpdate`], parameter values are clamped into their ranges first.
   pub fn update(&mut self) {
-    self.inner.update()
+    if self.parameter_clamp_mode == ParameterClampMode::OnUpdate {
+      self.clamp_parameter_values();
+    }
+    let mut readback_mask = self.readback_mask;
+    if !self.geometry_readback {
+      readback_mask -= DrawableReadbackFlags::VertexPositions | DrawableReadbackFlags::MultiplyColors | DrawableReadbackFlags::ScreenColors;
+    }
+    if readback_mask.is_full() {
+      self.inner.update()
+    } else {
+      self.inner.update_with_readback_mask(readback_mask)
+    }
+  }
+  /// Resets the dynamic drawable flags, then updates, and summarizes what changed,
+  /// e.g. to skip re-sorting when no render order changed, or redrawing altogether.
+  pub fn update_with_summary(&mut self) -> UpdateSummary {
+    self.reset_drawable_dynamic_flags();
+    self.update();
+    UpdateSummary::from_flagsets(self.drawable_dynamic_flagsets())
   }
   pub fn reset_drawable_dynamic_flags(&mut self) {
     self.inner.reset_drawable_dynamic_flags()
   }
 }
 
+// Indexing with typed indices panics when out of range, just as slice indexing does.
+
+impl std::ops::Index<ParameterIndex> for ModelStatic {
+  type Output = Parameter;
+
+  fn index(&self, index: ParameterIndex) -> &Self::Output {
+    &self.parameters()[index.as_usize()]
+  }
+}
+impl std::ops::Index<PartIndex> for ModelStatic {
+  type Output = Part;
+
+  fn index(&self, index: PartIndex) -> &Self::Output {
+    &self.parts()[index.as_usize()]
+  }
+}
+impl std::ops::Index<DrawableIndex> for ModelStatic {
+  type Output = Drawable;
+
+  fn index(&self, index: DrawableIndex) -> &Self::Output {
+    &self.drawables()[index.as_usize()]
+  }
+}
+
+/// Parameter value.
+impl std::ops::Index<ParameterIndex> for ModelDynamic {
+  type Output = f32;
+
+  fn index(&self, index: ParameterIndex) -> &Self::Output {
+    &self.parameter_values()[index.as_usize()]
+  }
+}
+impl std::ops::IndexMut<ParameterIndex> for ModelDynamic {
+  fn index_mut(&mut self, index: ParameterIndex) -> &mut Self::Output {
+    &mut self.parameter_values_mut()[index.as_usize()]
+  }
+}
+/// Part opacity.
+impl std::ops::Index<PartIndex> for ModelDynamic {
+  type Output = f32;
+
+  fn index(&self, index: PartIndex) -> &Self::Output {
+    &self.part_opacities()[index.as_usize()]
+  }
+}
+impl std::ops::IndexMut<PartIndex> for ModelDynamic {
+  fn index_mut(&mut self, index: PartIndex) -> &mut Self::Output {
+    &mut self.part_opacities_mut()[index.as_usize()]
+  }
+}
+
 #[must_use]
 #[derive(Debug)]
 pub struct ModelDynamicReadLockGuard<'a> {
+  model_static: &'a ModelStatic,
   inner: RwLockReadGuard<'a, ModelDynamic>,
 }
+impl<'a> ModelDynamicReadLockGuard<'a> {
+  /// Gets the [`ModelStatic`] of the owning [`Model`].
+  pub fn model_static(&self) -> &'a ModelStatic {
+    self.model_static
+  }
+  /// Iterates parameters together with their current values.
+  pub fn iter_parameters(&self) -> impl Iterator<Item = (ParameterIndex, &Parameter, f32)> + '_ {
+    self.model_static.parameters().iter()
+      .zip(self.inner.parameter_values().iter())
+      .enumerate()
+      .map(|(index, (parameter, &value))| (ParameterIndex::from(index), parameter, value))
+  }
+}
 impl<'a> std::ops::Deref for ModelDynamicReadLockGuard<'a> {
   type Target = ModelDynamic;
 
@@ -188,8 +992,34 @@ impl<'a> std::ops::Deref for ModelDynamicReadLockGuard<'a> {
 #[must_use]
 #[derive(Debug)]
 pub struct ModelDynamicWriteLockGuard<'a> {
+  model_static: &'a ModelStatic,
+  post_update_hooks: &'a hooks::PostUpdateHooks,
   inner: RwLockWriteGuard<'a, ModelDynamic>,
 }
+impl<'a> ModelDynamicWriteLockGuard<'a> {
+  /// Gets the [`ModelStatic`] of the owning [`Model`].
+  pub fn model_static(&self) -> &'a ModelStatic {
+    self.model_static
+  }
+  /// [`ModelDynamic::update`], then calls the hooks added with [`Model::add_post_update_hook`].
+  pub fn update(&mut self) {
+    self.inner.update();
+    self.post_update_hooks.run(self.model_static, &self.inner);
+  }
+  /// [`ModelDynamic::update_with_summary`], then calls the hooks added with [`Model::add_post_update_hook`].
+  pub fn update_with_summary(&mut self) -> UpdateSummary {
+    let summary = self.inner.update_with_summary();
+    self.post_update_hooks.run(self.model_static, &self.inner);
+    summary
+  }
+  /// Iterates parameters together with mutable references to their current values.
+  pub fn iter_parameters_mut(&mut self) -> impl Iterator<Item = (ParameterIndex, &'a Parameter, &mut f32)> + '_ {
+    self.model_static.parameters().iter()
+      .zip(self.inner.parameter_values_mut().iter_mut())
+      .enumerate()
+      .map(|(index, (parameter, value))| (ParameterIndex::from(index), parameter, value))
+  }
+}
 impl<'a> std::ops::Deref for ModelDynamicWriteLockGuard<'a> {
   type Target = ModelDynamic;
 
@@ -203,13 +1033,13 @@ impl<'a> std::ops::DerefMut for ModelDynamicWriteLockGuard<'a> {
   }
 }
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "mock")))]
 macro_rules! if_native {
   ($($code:tt)*) => {
     $($code)*
   };
 }
-#[cfg(target_arch = "wasm32")]
+#[cfg(any(target_arch = "wasm32", feature = "mock"))]
 macro_rules! if_native {
   ($($code:tt)*) => {};
 }
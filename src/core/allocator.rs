@@ -0,0 +1,119 @@
+//! Allocation of the memory blocks holding revived mocs and model instances, e.g. to place them in an engine's tracked heap
+//! or a preallocated region.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::alloc::Layout;
+
+/// Allocates the memory blocks for [`super::Moc`]s and [`super::Model`]s created through a [`super::CubismCore`]
+/// from [`super::CubismCore::with_allocator`].
+///
+/// A model's block is allocated by the allocator of the [`super::CubismCore`] its moc was loaded with,
+/// and every block is deallocated by the allocator that allocated it.
+///
+/// ## Safety
+/// As with [`std::alloc::GlobalAlloc`], a non-null block returned by [`StorageAllocator::allocate`] MUST be valid
+/// for reads and writes of `layout`, and MUST NOT be otherwise used until passed to [`StorageAllocator::deallocate`].
+pub unsafe trait StorageAllocator: Send + Sync + std::fmt::Debug {
+  /// Returns null on failure, which is reported as [`super::OutOfMemoryError`].
+  ///
+  /// `layout` never has a size of `0`.
+  fn allocate(&self, layout: Layout) -> *mut u8;
+  /// Like [`StorageAllocator::allocate`], but with every byte set to `0`.
+  fn allocate_zeroed(&self, layout: Layout) -> *mut u8 {
+    let ptr = self.allocate(layout);
+    if !ptr.is_null() {
+      // SAFETY: `ptr` is valid for writes of `layout`.
+      unsafe { ptr.write_bytes(0, layout.size()) };
+    }
+    ptr
+  }
+  /// ## Safety
+  /// - `ptr` MUST have been returned by this allocator for the same `layout`, and not deallocated yet.
+  unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout);
+}
+
+/// Allocates through the global allocator. Used unless another allocator is given.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemStorageAllocator;
+unsafe impl StorageAllocator for SystemStorageAllocator {
+  fn allocate(&self, layout: Layout) -> *mut u8 {
+    unsafe { std::alloc::alloc(layout) }
+  }
+  fn allocate_zeroed(&self, layout: Layout) -> *mut u8 {
+    unsafe { std::alloc::alloc_zeroed(layout) }
+  }
+  unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+    std::alloc::dealloc(ptr, layout)
+  }
+}
+
+/// A cached block, only ever touched under the lock of [`PooledStorageAllocator`].
+#[derive(Debug)]
+struct CachedBlock {
+  ptr: *mut u8,
+  layout: Layout,
+}
+// SAFETY: The block is not in use while cached.
+unsafe impl Send for CachedBlock {}
+
+/// Keeps blocks deallocated through it for reuse by later allocations of the same size and alignment,
+/// for tools creating and dropping many models in quick succession, e.g. batch validators or thumbnailers.
+///
+/// Cached blocks are returned to the inner allocator by [`PooledStorageAllocator::trim`], or when dropped.
+#[derive(Debug)]
+pub struct PooledStorageAllocator<A: StorageAllocator = SystemStorageAllocator> {
+  inner: A,
+  max_cached_bytes: usize,
+  cached_blocks: parking_lot::Mutex<Vec<CachedBlock>>,
+}
+impl<A: StorageAllocator> PooledStorageAllocator<A> {
+  /// Caches at most `max_cached_bytes` bytes of blocks at a time, deallocating further blocks through `inner`.
+  pub fn new(inner: A, max_cached_bytes: usize) -> Self {
+    Self {
+      inner,
+      max_cached_bytes,
+      cached_blocks: parking_lot::Mutex::new(Vec::new()),
+    }
+  }
+
+  /// Total size of the cached blocks, in bytes.
+  pub fn cached_bytes(&self) -> usize {
+    self.cached_blocks.lock().iter().map(|block| block.layout.size()).sum()
+  }
+  /// Returns every cached block to the inner allocator.
+  pub fn trim(&self) {
+    for block in self.cached_blocks.lock().drain(..) {
+      // SAFETY: Cached blocks were allocated by `inner` for `layout`.
+      unsafe { self.inner.deallocate(block.ptr, block.layout) };
+    }
+  }
+}
+unsafe impl<A: StorageAllocator> StorageAllocator for PooledStorageAllocator<A> {
+  fn allocate(&self, layout: Layout) -> *mut u8 {
+    let mut cached_blocks = self.cached_blocks.lock();
+    match cached_blocks.iter().position(|block| block.layout == layout) {
+      Some(index) => cached_blocks.swap_remove(index).ptr,
+      None => {
+        drop(cached_blocks);
+        self.inner.allocate(layout)
+      },
+    }
+  }
+  unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+    let mut cached_blocks = self.cached_blocks.lock();
+    let cached_bytes: usize = cached_blocks.iter().map(|block| block.layout.size()).sum();
+    // `cached_bytes` never exceeds `max_cached_bytes`.
+    if layout.size() <= self.max_cached_bytes - cached_bytes {
+      cached_blocks.push(CachedBlock { ptr, layout });
+    } else {
+      drop(cached_blocks);
+      self.inner.deallocate(ptr, layout);
+    }
+  }
+}
+impl<A: StorageAllocator> Drop for PooledStorageAllocator<A> {
+  fn drop(&mut self) {
+    self.trim();
+  }
+}
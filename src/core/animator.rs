@@ -0,0 +1,300 @@
+//! Time-driven orchestration of the per-frame model update sequence.
+
+use std::borrow::Borrow;
+
+use super::{Model, ModelDynamic};
+use super::tween::ParamTween;
+
+/// Phase of the per-frame sequence in which an [`AnimationStage`] runs.
+///
+/// Phases run in declaration order, followed by [`ModelDynamic::update`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AnimationPhase {
+  /// Applies authored motions.
+  Motion,
+  /// Procedural controllers, tweens, tracking input etc.
+  Controller,
+  /// Physics and other simulation that reacts to the values written before it.
+  Physics,
+}
+
+/// A subsystem advanced by a [`ModelAnimator`] each frame.
+pub trait AnimationStage: Send {
+  fn phase(&self) -> AnimationPhase {
+    AnimationPhase::Controller
+  }
+  /// Advances the stage by `dt` seconds, writing into `dynamic`.
+  fn advance(&mut self, dt: f32, dynamic: &mut ModelDynamic);
+  /// Whether the stage is done, after which the animator drops it.
+  fn is_finished(&self) -> bool {
+    false
+  }
+}
+
+impl AnimationStage for ParamTween {
+  fn advance(&mut self, dt: f32, dynamic: &mut ModelDynamic) {
+    ParamTween::advance(self, dt, dynamic);
+  }
+  fn is_finished(&self) -> bool {
+    ParamTween::is_finished(self)
+  }
+}
+
+/// How a [`ModelAnimator`] advances its stages.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum StepMode {
+  /// Stages advance once per [`ModelAnimator::advance`], by the given delta time.
+  #[default]
+  Variable,
+  /// Stages advance in steps of `step` seconds, accumulating the given delta times.
+  ///
+  /// Parameter values and part opacities published for [`ModelDynamic::update`] are interpolated
+  /// between the last two steps, so rendering stays smooth at any frame rate.
+  /// Cyclic parameters are interpolated the shorter way around their range.
+  ///
+  /// Since the simulated values are restored before each step, values written into [`ModelDynamic`]
+  /// from outside of the stages do not persist in this mode.
+  Fixed {
+    step: f32,
+    /// Upper bound of steps per advance, after which remaining whole steps are dropped
+    /// so that a long frame can't stall the following ones.
+    max_steps_per_advance: u32,
+  },
+}
+
+/// Parameter values and part opacities of the last two fixed steps.
+#[derive(Debug, Clone)]
+struct FixedStepState {
+  accumulator: f32,
+  previous_parameter_values: Box<[f32]>,
+  previous_part_opacities: Box<[f32]>,
+  current_parameter_values: Box<[f32]>,
+  current_part_opacities: Box<[f32]>,
+}
+impl FixedStepState {
+  fn new(dynamic: &ModelDynamic) -> Self {
+    Self {
+      accumulator: 0.0,
+      previous_parameter_values: dynamic.parameter_values().into(),
+      previous_part_opacities: dynamic.part_opacities().into(),
+      current_parameter_values: dynamic.parameter_values().into(),
+      current_part_opacities: dynamic.part_opacities().into(),
+    }
+  }
+  fn interpolation_alpha(&self, step: f32) -> f32 {
+    (self.accumulator / step).clamp(0.0, 1.0)
+  }
+}
+
+/// Owns the per-frame sequence of a model: stages by [`AnimationPhase`], then [`ModelDynamic::update`].
+///
+/// `M` can be a [`Model`], a reference to one or a smart pointer such as [`std::sync::Arc<Model>`].
+pub struct ModelAnimator<M: Borrow<Model> = Model> {
+  model: M,
+  /// Sorted by phase; stages in the same phase run in insertion order.
+  stages: Vec<Box<dyn AnimationStage>>,
+  time: f64,
+  step_mode: StepMode,
+  /// Only used with [`StepMode::Fixed`], created on the first advance.
+  fixed_step_state: Option<FixedStepState>,
+}
+impl<M: Borrow<Model>> ModelAnimator<M> {
+  pub fn new(model: M) -> Self {
+    Self {
+      model,
+      stages: Vec::new(),
+      time: 0.0,
+      step_mode: StepMode::Variable,
+      fixed_step_state: None,
+    }
+  }
+
+  pub fn step_mode(&self) -> StepMode {
+    self.step_mode
+  }
+  /// Switches the step mode, discarding any accumulated time.
+  ///
+  /// ## Panics
+  /// - If `step_mode` is [`StepMode::Fixed`] with a non-positive `step`.
+  pub fn set_step_mode(&mut self, step_mode: StepMode) {
+    if let StepMode::Fixed { step, .. } = step_mode {
+      assert!(step > 0.0, "Fixed step must be positive, given: {step}");
+    }
+    self.step_mode = step_mode;
+    self.fixed_step_state = None;
+  }
+  /// Fraction of a fixed step accumulated but not yet simulated, used to interpolate published values.
+  ///
+  /// Always `1.0` with [`StepMode::Variable`].
+  pub fn interpolation_alpha(&self) -> f32 {
+    match (self.step_mode, self.fixed_step_state.as_ref()) {
+      (StepMode::Fixed { step, .. }, Some(state)) => state.interpolation_alpha(step),
+      _ => 1.0,
+    }
+  }
+
+  pub fn model(&self) -> &Model {
+    self.model.borrow()
+  }
+  pub fn into_model(self) -> M {
+    self.model
+  }
+  /// Total time advanced, in seconds.
+  pub fn time(&self) -> f64 {
+    self.time
+  }
+
+  /// Adds a stage, which runs after the existing stages of the same phase.
+  pub fn add_stage<S: AnimationStage + 'static>(&mut self, stage: S) {
+    let phase = stage.phase();
+    let position = self.stages.partition_point(|existing| existing.phase() <= phase);
+    self.stages.insert(position, Box::new(stage));
+  }
+  pub fn stage_count(&self) -> usize {
+    self.stages.len()
+  }
+  pub fn clear_stages(&mut self) {
+    self.stages.clear();
+  }
+
+  /// Advances all stages by `dt` seconds according to the [`StepMode`] and updates the model.
+  ///
+  /// Dynamic drawable flags are reset before the update, so they describe the changes made by this frame only.
+  pub fn advance(&mut self, dt: f32) {
+    let mut dynamic = self.model.borrow().write_dynamic();
+
+    match self.step_mode {
+      StepMode::Variable => {
+        for stage in self.stages.iter_mut() {
+          stage.advance(dt, &mut dynamic);
+        }
+      }
+      StepMode::Fixed { step, max_steps_per_advance } => {
+        let state = self.fixed_step_state.get_or_insert_with(|| FixedStepState::new(&dynamic));
+
+        // Restore the simulated values, replacing the interpolated ones published last time.
+        dynamic.parameter_values_mut().copy_from_slice(&state.current_parameter_values);
+        dynamic.part_opacities_mut().copy_from_slice(&state.current_part_opacities);
+
+        state.accumulator += dt.max(0.0);
+
+        let mut step_count = 0;
+        while state.accumulator >= step && step_count < max_steps_per_advance {
+          std::mem::swap(&mut state.previous_parameter_values, &mut state.current_parameter_values);
+          std::mem::swap(&mut state.previous_part_opacities, &mut state.current_part_opacities);
+
+          for stage in self.stages.iter_mut() {
+            stage.advance(step, &mut dynamic);
+          }
+
+          state.current_parameter_values.copy_from_slice(dynamic.parameter_values());
+          state.current_part_opacities.copy_from_slice(dynamic.part_opacities());
+
+          state.accumulator -= step;
+          step_count += 1;
+        }
+        if state.accumulator >= step {
+          log::trace!("ModelAnimator: dropping {} fixed steps", (state.accumulator / step) as u32);
+          state.accumulator %= step;
+        }
+
+        let alpha = state.interpolation_alpha(step);
+        dynamic.blend_parameter_state(
+          (&state.previous_parameter_values, &state.previous_part_opacities),
+          (&state.current_parameter_values, &state.current_part_opacities),
+          alpha,
+        );
+      }
+    }
+    self.stages.retain(|stage| !stage.is_finished());
+
+    dynamic.reset_drawable_dynamic_flags();
+    dynamic.update();
+
+    self.time += f64::from(dt);
+  }
+}
+
+impl<M: Borrow<Model> + std::fmt::Debug> std::fmt::Debug for ModelAnimator<M> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("ModelAnimator")
+      .field("model", &self.model)
+      .field("stage_count", &self.stages.len())
+      .field("time", &self.time)
+      .field("step_mode", &self.step_mode)
+      .finish()
+  }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+  use std::sync::{Arc, Mutex};
+
+  use super::*;
+  use crate::core::{CubismCore, MockMoc};
+
+  struct Recorder {
+    name: &'static str,
+    phase: AnimationPhase,
+    remaining: u32,
+    log: Arc<Mutex<Vec<&'static str>>>,
+  }
+  impl AnimationStage for Recorder {
+    fn phase(&self) -> AnimationPhase {
+      self.phase
+    }
+    fn advance(&mut self, _dt: f32, _dynamic: &mut ModelDynamic) {
+      self.remaining = self.remaining.saturating_sub(1);
+      self.log.lock().unwrap().push(self.name);
+    }
+    fn is_finished(&self) -> bool {
+      self.remaining == 0
+    }
+  }
+
+  fn animator_with_stages(log: &Arc<Mutex<Vec<&'static str>>>) -> ModelAnimator {
+    let moc = CubismCore::default().moc_from_bytes(&MockMoc::default().to_bytes()).unwrap();
+    let mut animator = ModelAnimator::new(Model::from_moc(&moc));
+    for (name, phase, remaining) in [
+      ("physics", AnimationPhase::Physics, u32::MAX),
+      ("motion", AnimationPhase::Motion, 1),
+      ("controller", AnimationPhase::Controller, u32::MAX),
+      ("motion2", AnimationPhase::Motion, u32::MAX),
+    ] {
+      animator.add_stage(Recorder { name, phase, remaining, log: log.clone() });
+    }
+    animator
+  }
+
+  #[test]
+  fn stage_order_and_finish() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut animator = animator_with_stages(&log);
+
+    animator.advance(0.5);
+    assert_eq!(*log.lock().unwrap(), ["motion", "motion2", "controller", "physics"]);
+    assert_eq!(animator.stage_count(), 3);
+
+    log.lock().unwrap().clear();
+    animator.advance(0.5);
+    assert_eq!(*log.lock().unwrap(), ["motion2", "controller", "physics"]);
+    assert_eq!(animator.time(), 1.0);
+  }
+
+  #[test]
+  fn fixed_steps() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut animator = animator_with_stages(&log);
+    animator.set_step_mode(StepMode::Fixed { step: 0.1, max_steps_per_advance: 3 });
+
+    animator.advance(0.25);
+    assert_eq!(log.lock().unwrap().iter().filter(|name| **name == "physics").count(), 2);
+    assert!((animator.interpolation_alpha() - 0.5).abs() < 1e-4);
+
+    // Steps beyond the limit are dropped rather than carried over.
+    log.lock().unwrap().clear();
+    animator.advance(1.0);
+    assert_eq!(log.lock().unwrap().iter().filter(|name| **name == "physics").count(), 3);
+    assert!(animator.interpolation_alpha() < 1.0);
+  }
+}
@@ -0,0 +1,157 @@
+//! Arbitration between several systems writing the same parameters, e.g. face tracking, motions and manual UI.
+
+use super::base_types::ParameterIndex;
+use super::{ModelStatic, ModelDynamic};
+
+/// How the writes of several writers to the same parameter are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ArbitrationMode {
+  /// Only the writer with the highest priority applies, blended over the current value by its weight.
+  #[default]
+  HighestPriority,
+  /// Every writer applies in order of increasing priority, each blended by its weight over the result of the previous ones,
+  /// so that higher priorities dominate.
+  Blend,
+}
+
+/// Identifies a writer added with [`ParameterArbiter::add_writer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParameterWriterId(usize);
+
+#[derive(Debug, Clone)]
+struct Writer {
+  name: String,
+  priority: i32,
+  /// Value and weight per parameter written since the last resolve.
+  writes: Box<[Option<(f32, f32)>]>,
+}
+
+/// Collects parameter writes from several writers with priorities, then resolves them into a model once per frame.
+#[derive(Debug, Clone)]
+pub struct ParameterArbiter {
+  parameter_count: usize,
+  writers: Vec<Writer>,
+  /// Indices into `writers` by increasing priority, then by insertion.
+  order: Vec<usize>,
+  pub mode: ArbitrationMode,
+}
+impl ParameterArbiter {
+  pub fn new(model_static: &ModelStatic, mode: ArbitrationMode) -> Self {
+    Self {
+      parameter_count: model_static.parameters().len(),
+      writers: Vec::new(),
+      order: Vec::new(),
+      mode,
+    }
+  }
+
+  /// Adds a writer. Among writers of equal priority, the one added last wins.
+  pub fn add_writer(&mut self, name: impl Into<String>, priority: i32) -> ParameterWriterId {
+    self.writers.push(Writer {
+      name: name.into(),
+      priority,
+      writes: vec![None; self.parameter_count].into_boxed_slice(),
+    });
+    self.sort();
+    ParameterWriterId(self.writers.len() - 1)
+  }
+  pub fn writer_name(&self, id: ParameterWriterId) -> &str {
+    &self.writers[id.0].name
+  }
+  pub fn priority(&self, id: ParameterWriterId) -> i32 {
+    self.writers[id.0].priority
+  }
+  pub fn set_priority(&mut self, id: ParameterWriterId, priority: i32) {
+    self.writers[id.0].priority = priority;
+    self.sort();
+  }
+
+  /// Writes `value` with full weight. Replaces any previous write of the same writer to the same parameter.
+  ///
+  /// ## Panics
+  /// - If `index` is out of range.
+  pub fn write(&mut self, id: ParameterWriterId, index: ParameterIndex, value: f32) {
+    self.write_weighted(id, index, value, 1.0);
+  }
+  /// Writes `value` blended by `weight`, clamped to `[0, 1]`.
+  ///
+  /// ## Panics
+  /// - If `index` is out of range.
+  pub fn write_weighted(&mut self, id: ParameterWriterId, index: ParameterIndex, value: f32, weight: f32) {
+    self.writers[id.0].writes[index.as_usize()] = Some((value, weight.clamp(0.0, 1.0)));
+  }
+  /// Discards the writes of a writer since the last resolve, e.g. when its tracking source is lost.
+  pub fn clear(&mut self, id: ParameterWriterId) {
+    self.writers[id.0].writes.fill(None);
+  }
+
+  /// Writes the combined values into `dynamic` according to [`ParameterArbiter::mode`], then discards all writes.
+  ///
+  /// Parameters no writer wrote keep their current values.
+  ///
+  /// ## Panics
+  /// - If `dynamic` belongs to a model of a different moc with fewer parameters.
+  pub fn resolve(&mut self, dynamic: &mut ModelDynamic) {
+    let parameter_values = &mut dynamic.parameter_values_mut()[..self.parameter_count];
+    for (i, value) in parameter_values.iter_mut().enumerate() {
+      let mut writes = self.order.iter().filter_map(|&writer| self.writers[writer].writes[i]);
+      match self.mode {
+        ArbitrationMode::HighestPriority => if let Some((target, weight)) = writes.next_back() {
+          *value += (target - *value) * weight;
+        },
+        ArbitrationMode::Blend => for (target, weight) in writes {
+          *value += (target - *value) * weight;
+        },
+      }
+    }
+
+    for writer in self.writers.iter_mut() {
+      writer.writes.fill(None);
+    }
+  }
+
+  fn sort(&mut self) {
+    self.order = (0..self.writers.len()).collect();
+    // Stable, so insertion order breaks ties.
+    self.order.sort_by_key(|&writer| self.writers[writer].priority);
+  }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+  use super::*;
+  use crate::core::{CubismCore, Model, MockMoc};
+
+  #[test]
+  fn resolve() {
+    let moc = CubismCore::default().moc_from_bytes(&MockMoc::default().to_bytes()).unwrap();
+    let model = Model::from_moc(&moc);
+    let mut arbiter = ParameterArbiter::new(model.get_static(), ArbitrationMode::HighestPriority);
+    let high = arbiter.add_writer("high", 10);
+    let low = arbiter.add_writer("low", 0);
+    let index = ParameterIndex::from(0);
+
+    let mut dynamic = model.write_dynamic();
+    let mut resolve = |arbiter: &mut ParameterArbiter, mode| {
+      dynamic.parameter_values_mut()[..2].copy_from_slice(&[0.0, 0.25]);
+      arbiter.mode = mode;
+      arbiter.write_weighted(high, index, 1.0, 0.5);
+      arbiter.write(low, index, -1.0);
+      arbiter.resolve(&mut dynamic);
+      [dynamic.parameter_values()[0], dynamic.parameter_values()[1]]
+    };
+
+    // The highest priority wins regardless of insertion order, and unwritten parameters are kept.
+    assert_eq!(resolve(&mut arbiter, ArbitrationMode::HighestPriority), [0.5, 0.25]);
+    // Low applies first, then high blends over it by half.
+    assert_eq!(resolve(&mut arbiter, ArbitrationMode::Blend), [0.0, 0.25]);
+
+    arbiter.set_priority(low, 20);
+    assert_eq!(resolve(&mut arbiter, ArbitrationMode::HighestPriority), [-1.0, 0.25]);
+    assert_eq!(resolve(&mut arbiter, ArbitrationMode::Blend), [-1.0, 0.25]);
+
+    // Writes are discarded by each resolve.
+    arbiter.resolve(&mut dynamic);
+    assert_eq!(dynamic.parameter_values()[0], -1.0);
+  }
+}
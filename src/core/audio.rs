@@ -0,0 +1,82 @@
+//! Decoding of WAV and Ogg Vorbis audio into an [`AmplitudeEnvelope`], e.g. for the sound of a motion.
+
+use std::path::Path;
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use thiserror::Error;
+
+use super::lip_sync::AmplitudeEnvelope;
+
+/// Errors generated when decoding audio.
+#[derive(Debug, Error)]
+pub enum AudioDecodeError {
+  #[error("Failed to read audio: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("Failed to decode audio: {0}")]
+  Decode(#[from] SymphoniaError),
+  #[error("No audio track found.")]
+  NoTrack,
+  #[error("Unknown sample rate or channel layout.")]
+  UnknownFormat,
+}
+
+impl AmplitudeEnvelope {
+  /// Decodes the WAV or Ogg Vorbis file at `path`, with `frame_rate` windows per second.
+  pub fn from_audio_file(path: impl AsRef<Path>, frame_rate: f32) -> Result<Self, AudioDecodeError> {
+    let path = path.as_ref();
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+      hint.with_extension(extension);
+    }
+    decode(Box::new(std::fs::File::open(path)?), hint, frame_rate)
+  }
+  /// Decodes WAV or Ogg Vorbis from `bytes`, with `frame_rate` windows per second.
+  pub fn from_audio_bytes(bytes: Vec<u8>, frame_rate: f32) -> Result<Self, AudioDecodeError> {
+    decode(Box::new(std::io::Cursor::new(bytes)), Hint::new(), frame_rate)
+  }
+}
+
+fn decode(source: Box<dyn MediaSource>, hint: Hint, frame_rate: f32) -> Result<AmplitudeEnvelope, AudioDecodeError> {
+  let stream = MediaSourceStream::new(source, Default::default());
+  let probed = symphonia::default::get_probe().format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())?;
+  let mut format = probed.format;
+
+  let track = format.default_track().ok_or(AudioDecodeError::NoTrack)?;
+  let track_id = track.id;
+  let sample_rate = track.codec_params.sample_rate.ok_or(AudioDecodeError::UnknownFormat)?;
+  let channel_count = track.codec_params.channels.ok_or(AudioDecodeError::UnknownFormat)?.count();
+  let mut decoder = symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+  let mut samples = Vec::new();
+  loop {
+    let packet = match format.next_packet() {
+      Ok(packet) => packet,
+      Err(SymphoniaError::IoError(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+      Err(err) => return Err(err.into()),
+    };
+    if packet.track_id() != track_id {
+      continue;
+    }
+
+    let decoded = match decoder.decode(&packet) {
+      Ok(decoded) => decoded,
+      // Corrupted packets are skipped, as players do.
+      Err(SymphoniaError::DecodeError(err)) => {
+        log::warn!("Skipping undecodable audio packet: {err}");
+        continue;
+      }
+      Err(err) => return Err(err.into()),
+    };
+    let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+    buffer.copy_interleaved_ref(decoded);
+    samples.extend_from_slice(buffer.samples());
+  }
+
+  Ok(AmplitudeEnvelope::from_samples(&samples, channel_count, sample_rate, frame_rate))
+}
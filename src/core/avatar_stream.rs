@@ -0,0 +1,230 @@
+//! Streaming of avatar state over WebSocket with the [`super::replication`] codec, e.g. from a native tracker to a
+//! browser renderer.
+//!
+//! The native side sends with an `AvatarStreamSender` over tokio-tungstenite, typically accepting the connection of the
+//! browser, which receives with a `WebAvatarStreamReceiver`. Since WebSocket is ordered and reliable, packets are only
+//! ever missed by a receiver joining mid-stream, which then waits for the next keyframe; see
+//! [`super::ReplicationEncoder::set_keyframe_interval`].
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+pub enum AvatarStreamError {
+  /// The WebSocket failed, with the underlying error formatted.
+  #[error("WebSocket error: {0}")]
+  WebSocket(String),
+  #[error(transparent)]
+  Replication(#[from] super::ReplicationError),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::AvatarStreamSender;
+#[cfg(target_arch = "wasm32")]
+pub use web::WebAvatarStreamReceiver;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+  use futures_util::SinkExt as _;
+  use tokio::io::{AsyncRead, AsyncWrite};
+  use tokio::net::TcpStream;
+  use tokio_tungstenite::tungstenite::Message;
+  use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+  use super::super::{ModelDynamic, ReplicationEncoder};
+  use super::AvatarStreamError;
+
+  impl From<tokio_tungstenite::tungstenite::Error> for AvatarStreamError {
+    fn from(err: tokio_tungstenite::tungstenite::Error) -> Self {
+      Self::WebSocket(err.to_string())
+    }
+  }
+
+  /// Sends the state of a model as binary WebSocket messages, one replication packet each.
+  ///
+  /// ## Platform-specific
+  /// - **Web:** Unavailable.
+  #[derive(Debug)]
+  pub struct AvatarStreamSender<S = MaybeTlsStream<TcpStream>> {
+    socket: WebSocketStream<S>,
+    encoder: ReplicationEncoder,
+  }
+  impl AvatarStreamSender {
+    /// Connects to a WebSocket server at `url`, e.g. a relay forwarding to browsers, to send the state of the model of `dynamic`.
+    pub async fn connect(url: &str, dynamic: &ModelDynamic) -> Result<Self, AvatarStreamError> {
+      let (socket, _) = tokio_tungstenite::connect_async(url).await?;
+      Ok(Self {
+        socket,
+        encoder: ReplicationEncoder::new(dynamic),
+      })
+    }
+  }
+  impl<S: AsyncRead + AsyncWrite + Unpin> AvatarStreamSender<S> {
+    /// Performs the server side of the WebSocket handshake on `stream`, e.g. accepted from a [`tokio::net::TcpListener`],
+    /// to send the state of the model of `dynamic`.
+    pub async fn accept(stream: S, dynamic: &ModelDynamic) -> Result<Self, AvatarStreamError> {
+      Ok(Self {
+        socket: tokio_tungstenite::accept_async(stream).await?,
+        encoder: ReplicationEncoder::new(dynamic),
+      })
+    }
+
+    pub fn encoder(&self) -> &ReplicationEncoder {
+      &self.encoder
+    }
+    /// E.g. to change the keyframe interval.
+    pub fn encoder_mut(&mut self) -> &mut ReplicationEncoder {
+      &mut self.encoder
+    }
+
+    /// Encodes and sends the current state of `dynamic`, typically once per tick.
+    ///
+    /// ## Panics
+    /// - If `dynamic` belongs to a model of a different moc.
+    pub async fn send(&mut self, dynamic: &ModelDynamic) -> Result<(), AvatarStreamError> {
+      let packet = self.encoder.encode(dynamic);
+      self.socket.send(Message::Binary(packet)).await?;
+      Ok(())
+    }
+    pub async fn close(mut self) -> Result<(), AvatarStreamError> {
+      self.socket.close(None).await?;
+      Ok(())
+    }
+  }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+  use std::cell::RefCell;
+  use std::collections::VecDeque;
+  use std::rc::Rc;
+
+  use wasm_bindgen::closure::Closure;
+  use wasm_bindgen::JsCast as _;
+
+  use super::super::{ModelDynamic, ReplicationDecoder, ReplicationError};
+  use super::AvatarStreamError;
+
+  impl From<wasm_bindgen::JsValue> for AvatarStreamError {
+    fn from(value: wasm_bindgen::JsValue) -> Self {
+      Self::WebSocket(format!("{value:?}"))
+    }
+  }
+
+  /// Receives the state of a model sent by a native `AvatarStreamSender` through a browser `WebSocket`.
+  ///
+  /// Packets are queued as they arrive and applied on [`WebAvatarStreamReceiver::poll`].
+  ///
+  /// ## Platform-specific
+  /// - **Native:** Unavailable.
+  pub struct WebAvatarStreamReceiver {
+    socket: web_sys::WebSocket,
+    packets: Rc<RefCell<VecDeque<Vec<u8>>>>,
+    _on_message: Closure<dyn FnMut(web_sys::MessageEvent)>,
+    decoder: ReplicationDecoder,
+  }
+  impl WebAvatarStreamReceiver {
+    /// Connects to the sender at `url` to receive the state of the model of `dynamic`.
+    pub fn connect(url: &str, dynamic: &ModelDynamic) -> Result<Self, AvatarStreamError> {
+      let socket = web_sys::WebSocket::new(url)?;
+      socket.set_binary_type(web_sys::BinaryType::Arraybuffer);
+
+      let packets = Rc::new(RefCell::new(VecDeque::new()));
+      let on_message = {
+        let packets = Rc::clone(&packets);
+        Closure::<dyn FnMut(web_sys::MessageEvent)>::new(move |event: web_sys::MessageEvent| {
+          if let Ok(buffer) = event.data().dyn_into::<js_sys::ArrayBuffer>() {
+            packets.borrow_mut().push_back(js_sys::Uint8Array::new(&buffer).to_vec());
+          }
+        })
+      };
+      socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+      Ok(Self {
+        socket,
+        packets,
+        _on_message: on_message,
+        decoder: ReplicationDecoder::new(dynamic),
+      })
+    }
+
+    pub fn decoder(&self) -> &ReplicationDecoder {
+      &self.decoder
+    }
+
+    /// Applies the packets received since the last poll to `dynamic`, returning the sequence number of the latest one
+    /// applied, if any. Call [`ModelDynamic::update`] afterwards.
+    ///
+    /// Packets received before the first keyframe are skipped.
+    ///
+    /// ## Panics
+    /// - If `dynamic` belongs to a model of a different moc than the one given on connection.
+    pub fn poll(&mut self, dynamic: &mut ModelDynamic) -> Result<Option<u16>, AvatarStreamError> {
+      let next_packet = || self.packets.borrow_mut().pop_front();
+
+      let mut latest_sequence = None;
+      while let Some(packet) = next_packet() {
+        match self.decoder.decode(&packet, dynamic) {
+          Ok(sequence) => latest_sequence = Some(sequence),
+          Err(ReplicationError::MissingBaseline { sequence, .. }) => {
+            log::trace!("WebAvatarStreamReceiver: skipping packet {sequence} until a keyframe");
+          },
+          Err(err) => return Err(err.into()),
+        }
+      }
+      Ok(latest_sequence)
+    }
+
+    pub fn close(&self) -> Result<(), AvatarStreamError> {
+      Ok(self.socket.close()?)
+    }
+  }
+  impl Drop for WebAvatarStreamReceiver {
+    fn drop(&mut self) {
+      self.socket.set_onmessage(None);
+      let _ = self.socket.close();
+    }
+  }
+  impl std::fmt::Debug for WebAvatarStreamReceiver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+      f.debug_struct("WebAvatarStreamReceiver")
+        .field("queued_packet_count", &self.packets.borrow().len())
+        .field("decoder", &self.decoder)
+        .finish_non_exhaustive()
+    }
+  }
+
+  #[cfg(all(test, feature = "mock"))]
+  mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use crate::core::{CubismCore, Model, MockMoc, ReplicationEncoder};
+
+    use super::*;
+
+    #[wasm_bindgen_test]
+    fn poll_skips_packets_until_a_keyframe() {
+      let moc = CubismCore::default().moc_from_bytes(&MockMoc::default().to_bytes()).unwrap();
+      let (sender, receiver) = (Model::from_moc(&moc), Model::from_moc(&moc));
+      let (mut sender_dynamic, mut receiver_dynamic) = (sender.write_dynamic(), receiver.write_dynamic());
+      let mut encoder = ReplicationEncoder::new(&sender_dynamic);
+
+      // Needs a global `WebSocket`, as in browsers or Node.js 22, but nothing listening: packets are queued directly.
+      let mut stream_receiver = WebAvatarStreamReceiver::connect("ws://127.0.0.1:9", &receiver_dynamic).unwrap();
+      assert_eq!(stream_receiver.poll(&mut receiver_dynamic).unwrap(), None);
+
+      let keyframe = encoder.encode(&sender_dynamic);
+      sender_dynamic.parameter_values_mut()[1] = 0.5;
+      let delta = encoder.encode(&sender_dynamic);
+
+      stream_receiver.packets.borrow_mut().push_back(delta.clone());
+      assert_eq!(stream_receiver.poll(&mut receiver_dynamic).unwrap(), None);
+
+      stream_receiver.packets.borrow_mut().extend([keyframe, delta]);
+      assert_eq!(stream_receiver.poll(&mut receiver_dynamic).unwrap(), Some(1));
+      assert!((receiver_dynamic.parameter_values()[1] - 0.5).abs() < 1e-4);
+
+      stream_receiver.packets.borrow_mut().push_back(b"not a packet".to_vec());
+      assert!(matches!(stream_receiver.poll(&mut receiver_dynamic), Err(AvatarStreamError::Replication(_))));
+    }
+  }
+}
@@ -4,7 +4,8 @@ use static_assertions::const_assert_eq;
 use thiserror::Error;
 use shrinkwraprs::Shrinkwrap;
 use derive_more::Display;
-use num_enum::TryFromPrimitive;
+
+use super::integrity::{Sha256Digest, to_hex};
 
 /// A 2-component `f32` vector with no padding.
 pub type Vector2 = mint::Vector2<f32>;
@@ -21,8 +22,26 @@ pub enum MocError {
   InvalidMoc,
   /// ## Platform-specific
   /// - **Web:** Unsupported.
-  #[error("Unsupported moc version. given: \"{given}\" latest supported:\"{latest_supported}\"")]
+  #[error("Unsupported moc version. given: \"{given}\" ({}) latest supported: \"{latest_supported}\" ({})", .given.raw(), .latest_supported.raw())]
   UnsupportedMocVersion { given: MocVersion, latest_supported: MocVersion },
+  /// ## Platform-specific
+  /// - **Web:** Unsupported.
+  #[error(transparent)]
+  OutOfMemory(#[from] OutOfMemoryError),
+  /// The bytes do not match [`MocLoadOptions::expected_sha256`].
+  #[error("Moc integrity check failed. expected SHA-256: {} actual: {}", to_hex(.expected), to_hex(.actual))]
+  IntegrityMismatch { expected: Sha256Digest, actual: Sha256Digest },
+  /// The [`super::decrypt::AssetDecryptor`] of the [`super::CubismCore`] failed.
+  #[error("Failed to decrypt the moc: {0}")]
+  Decryption(std::sync::Arc<dyn std::error::Error + Send + Sync>),
+}
+
+/// Failure to allocate memory for a moc or a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("Failed to allocate {size} bytes aligned to {alignment}.")]
+pub struct OutOfMemoryError {
+  pub size: usize,
+  pub alignment: usize,
 }
 
 /// Cubism version identifier.
@@ -48,21 +67,72 @@ impl std::fmt::Debug for CubismVersion {
 
 /// moc3 file format version.
 /// Note that there is no equivalent of `csmMocVersion_Unknown`.
-#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, TryFromPrimitive)]
-#[repr(u32)]
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum MocVersion {
   /// moc3 file version 3.0.00 - 3.2.07
   #[display(fmt = "30(3.0.00 - 3.2.07)")]
-  Moc3_30 = 1,
+  Moc3_30,
   /// moc3 file version 3.3.00 - 3.3.03
   #[display(fmt = "33(3.3.00 - 3.3.03)")]
-  Moc3_33 = 2,
+  Moc3_33,
   /// moc3 file version 4.0.00 - 4.1.05
   #[display(fmt = "33(4.0.00 - 4.1.05)")]
-  Moc3_40 = 3,
+  Moc3_40,
   /// moc3 file version 4.2.00 -
   #[display(fmt = "33(4.2.00 -)")]
-  Moc3_42 = 4,
+  Moc3_42,
+  /// A moc3 file version newer than any known to this crate, with its raw `csmMocVersion`,
+  /// reported by a _Live2D® Cubism SDK Core_ newer than this crate.
+  ///
+  /// Orders after the known versions, and by the raw value among newer ones.
+  #[display(fmt = "Newer than known ({})", _0)]
+  Newer(u32),
+}
+impl MocVersion {
+  /// Converts a raw `csmMocVersion`, mapping versions above the latest known one to [`MocVersion::Newer`].
+  ///
+  /// Returns `None` for `csmMocVersion_Unknown`.
+  #[cfg_attr(feature = "mock", allow(dead_code))]
+  pub(crate) fn from_raw(raw: u32) -> Option<Self> {
+    match raw {
+      0 => None,
+      1 => Some(Self::Moc3_30),
+      2 => Some(Self::Moc3_33),
+      3 => Some(Self::Moc3_40),
+      4 => Some(Self::Moc3_42),
+      _ => Some(Self::Newer(raw)),
+    }
+  }
+  /// The raw `csmMocVersion`.
+  pub fn raw(&self) -> u32 {
+    match self {
+      Self::Moc3_30 => 1,
+      Self::Moc3_33 => 2,
+      Self::Moc3_40 => 3,
+      Self::Moc3_42 => 4,
+      Self::Newer(raw) => *raw,
+    }
+  }
+}
+
+/// Options for [`crate::core::CubismCore::moc_from_bytes_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct MocLoadOptions {
+  /// Whether to attempt reviving mocs whose version exceeds the latest supported one, logging a warning,
+  /// instead of failing with [`MocError::UnsupportedMocVersion`].
+  ///
+  /// _Live2D® Cubism SDK Core_ may still reject such a moc, resulting in [`MocError::InvalidMoc`].
+  pub allow_newer: bool,
+  /// If set, the SHA-256 digest of the bytes is checked against it before reviving the moc,
+  /// failing with [`MocError::IntegrityMismatch`]. Compute it with [`super::integrity::sha256`].
+  pub expected_sha256: Option<Sha256Digest>,
+}
+impl MocLoadOptions {
+  /// Sets [`MocLoadOptions::expected_sha256`].
+  pub fn expected_sha256(mut self, digest: Sha256Digest) -> Self {
+    self.expected_sha256 = Some(digest);
+    self
+  }
 }
 
 /// Strong-typed index to a texture referenced from a Moc.
@@ -112,3 +182,71 @@ impl std::fmt::Display for DrawableIndex {
     write!(f, "{}", self.0)
   }
 }
+
+/// Strong-typed index to a parameter in a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Shrinkwrap)]
+#[repr(transparent)]
+pub struct ParameterIndex(pub u64);
+
+impl ParameterIndex {
+  #[inline]
+  pub fn as_usize(&self) -> usize {
+    self.0 as usize
+  }
+}
+
+impl From<usize> for ParameterIndex {
+  fn from(value: usize) -> Self {
+    Self(value as u64)
+  }
+}
+
+impl std::fmt::Display for ParameterIndex {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+/// Strong-typed index to a part in a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Shrinkwrap)]
+#[repr(transparent)]
+pub struct PartIndex(pub u64);
+
+impl PartIndex {
+  #[inline]
+  pub fn as_usize(&self) -> usize {
+    self.0 as usize
+  }
+}
+
+impl From<usize> for PartIndex {
+  fn from(value: usize) -> Self {
+    Self(value as u64)
+  }
+}
+
+impl std::fmt::Display for PartIndex {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn moc_version_raw_round_trip_and_order() {
+    assert_eq!(MocVersion::from_raw(0), None);
+    for raw in 1..=7 {
+      assert_eq!(MocVersion::from_raw(raw).unwrap().raw(), raw);
+    }
+    assert_eq!(MocVersion::from_raw(6), Some(MocVersion::Newer(6)));
+    assert!(MocVersion::Moc3_42 < MocVersion::Newer(5));
+    assert!(MocVersion::Newer(5) < MocVersion::Newer(6));
+
+    let err = MocError::UnsupportedMocVersion { given: MocVersion::Newer(6), latest_supported: MocVersion::Newer(5) };
+    let message = err.to_string();
+    assert!(message.contains("(6)") && message.contains("(5)"), "{message}");
+  }
+}
@@ -0,0 +1,186 @@
+//! Blend states for rendering drawables, following the premultiplied-alpha conventions of the official renderers.
+//!
+//! Textures and colors output by the fragment shader are expected to be premultiplied by alpha, as in _Live2D Cubism SDK_'s renderers.
+
+use super::base_types::{Vector4, DrawableIndex};
+use super::model_types::{ConstantDrawableFlags, ConstantDrawableFlagSet, Drawable};
+use super::ModelDynamic;
+
+/// How a drawable is composited onto the render target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BlendMode {
+  #[default]
+  Normal,
+  Additive,
+  Multiplicative,
+}
+impl BlendMode {
+  pub fn from_flagset(flagset: ConstantDrawableFlagSet) -> Self {
+    if flagset.contains(ConstantDrawableFlags::BlendAdditive) {
+      Self::Additive
+    } else if flagset.contains(ConstantDrawableFlags::BlendMultiplicative) {
+      Self::Multiplicative
+    } else {
+      Self::Normal
+    }
+  }
+
+  /// Blend factors, each combined with an add operation.
+  pub fn factors(&self) -> BlendFactors {
+    use BlendFactor::*;
+    match self {
+      Self::Normal => BlendFactors {
+        src_color: One,
+        dst_color: OneMinusSrcAlpha,
+        src_alpha: One,
+        dst_alpha: OneMinusSrcAlpha,
+      },
+      Self::Additive => BlendFactors {
+        src_color: One,
+        dst_color: One,
+        src_alpha: Zero,
+        dst_alpha: One,
+      },
+      Self::Multiplicative => BlendFactors {
+        src_color: DstColor,
+        dst_color: OneMinusSrcAlpha,
+        src_alpha: Zero,
+        dst_alpha: One,
+      },
+    }
+  }
+}
+
+impl Drawable {
+  pub fn blend_mode(&self) -> BlendMode {
+    BlendMode::from_flagset(self.constant_flagset)
+  }
+}
+
+/// Per-drawable shader constants with the multiply color, screen color and opacity folded in,
+/// so that a fragment shader only computes `color = texel * scale + texel.a * offset` with a premultiplied `texel`.
+///
+/// This is equivalent to the official renderers applying the multiply color, then the screen color, then the opacity.
+/// For a pipeline blending in linear, convert the colors with [`super::color_space::srgb_to_linear_rgb`] first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawableColorConstants {
+  pub scale: Vector4,
+  pub offset: Vector4,
+}
+impl DrawableColorConstants {
+  /// Colors as reported by _Live2D® Cubism SDK Core_, whose alpha components are ignored.
+  pub fn from_colors(multiply_color: Vector4, screen_color: Vector4, opacity: f32) -> Self {
+    // ((t * m + t.a * s) - t * m * s) * o = t * (m * (1 - s) * o) + t.a * (s * o)
+    let scale = |m: f32, s: f32| m * (1.0 - s) * opacity;
+    Self {
+      scale: Vector4 {
+        x: scale(multiply_color.x, screen_color.x),
+        y: scale(multiply_color.y, screen_color.y),
+        z: scale(multiply_color.z, screen_color.z),
+        w: opacity,
+      },
+      offset: Vector4 {
+        x: screen_color.x * opacity,
+        y: screen_color.y * opacity,
+        z: screen_color.z * opacity,
+        w: 0.0,
+      },
+    }
+  }
+}
+
+impl ModelDynamic {
+  /// The current [`DrawableColorConstants`] of a drawable, or `None` if `index` is out of range.
+  pub fn drawable_color_constants(&self, index: DrawableIndex) -> Option<DrawableColorConstants> {
+    let index = index.as_usize();
+    Some(DrawableColorConstants::from_colors(
+      *self.drawable_multiply_colors().get(index)?,
+      *self.drawable_screen_colors().get(index)?,
+      *self.drawable_opacities().get(index)?,
+    ))
+  }
+}
+
+/// A blend factor, named as in OpenGL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendFactor {
+  Zero,
+  One,
+  SrcAlpha,
+  OneMinusSrcAlpha,
+  DstColor,
+  OneMinusSrcColor,
+}
+impl BlendFactor {
+  /// The `GLenum` value, e.g. `GL_ONE_MINUS_SRC_ALPHA`.
+  pub fn gl_enum(&self) -> u32 {
+    match self {
+      Self::Zero => 0,
+      Self::One => 1,
+      Self::OneMinusSrcColor => 0x0301,
+      Self::SrcAlpha => 0x0302,
+      Self::OneMinusSrcAlpha => 0x0303,
+      Self::DstColor => 0x0306,
+    }
+  }
+}
+
+/// Separate color and alpha blend factors, as for `glBlendFuncSeparate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlendFactors {
+  pub src_color: BlendFactor,
+  pub dst_color: BlendFactor,
+  pub src_alpha: BlendFactor,
+  pub dst_alpha: BlendFactor,
+}
+impl BlendFactors {
+  /// The factors for rendering masks into a mask buffer, where masks are accumulated as `1 - coverage`.
+  pub const MASK: Self = Self {
+    src_color: BlendFactor::Zero,
+    dst_color: BlendFactor::OneMinusSrcColor,
+    src_alpha: BlendFactor::Zero,
+    dst_alpha: BlendFactor::OneMinusSrcAlpha,
+  };
+
+  /// The arguments of `glBlendFuncSeparate`, in order.
+  pub fn gl_enums(&self) -> [u32; 4] {
+    [self.src_color.gl_enum(), self.dst_color.gl_enum(), self.src_alpha.gl_enum(), self.dst_alpha.gl_enum()]
+  }
+}
+
+#[cfg(feature = "wgpu")]
+impl From<BlendFactor> for wgpu_types::BlendFactor {
+  fn from(factor: BlendFactor) -> Self {
+    match factor {
+      BlendFactor::Zero => Self::Zero,
+      BlendFactor::One => Self::One,
+      BlendFactor::SrcAlpha => Self::SrcAlpha,
+      BlendFactor::OneMinusSrcAlpha => Self::OneMinusSrcAlpha,
+      BlendFactor::DstColor => Self::Dst,
+      BlendFactor::OneMinusSrcColor => Self::OneMinusSrc,
+    }
+  }
+}
+#[cfg(feature = "wgpu")]
+impl From<BlendFactors> for wgpu_types::BlendState {
+  fn from(factors: BlendFactors) -> Self {
+    Self {
+      color: wgpu_types::BlendComponent {
+        src_factor: factors.src_color.into(),
+        dst_factor: factors.dst_color.into(),
+        operation: wgpu_types::BlendOperation::Add,
+      },
+      alpha: wgpu_types::BlendComponent {
+        src_factor: factors.src_alpha.into(),
+        dst_factor: factors.dst_alpha.into(),
+        operation: wgpu_types::BlendOperation::Add,
+      },
+    }
+  }
+}
+#[cfg(feature = "wgpu")]
+impl From<BlendMode> for wgpu_types::BlendState {
+  fn from(mode: BlendMode) -> Self {
+    mode.factors().into()
+  }
+}
@@ -0,0 +1,153 @@
+//! Creation of a [`Model`] with an initial dynamic state.
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use super::base_types::{ParameterIndex, PartIndex, OutOfMemoryError};
+use super::{Moc, Model, ModelStatic, ModelDynamic};
+
+/// Errors generated when building a [`Model`].
+#[derive(Debug, Clone, Error)]
+pub enum ModelBuildError {
+  #[error("Parameter \"{id}\" not found in the moc.")]
+  UnknownParameter { id: String },
+  #[error("Part \"{id}\" not found in the moc.")]
+  UnknownPart { id: String },
+  #[error(transparent)]
+  OutOfMemory(#[from] OutOfMemoryError),
+}
+
+/// How the dynamic state of a new model is initialized, before the values given to a [`ModelBuilder`] are applied.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum InitialState<'a> {
+  /// As initialized by _Live2D® Cubism SDK Core_.
+  #[default]
+  CoreValues,
+  /// Parameters at their declared default values, regardless of how _Live2D® Cubism SDK Core_ initialized them.
+  ///
+  /// Unlike parameters, parts have no declared default, so they keep their initial opacities from the moc
+  /// as in [`InitialState::CoreValues`].
+  DeclaredDefaults,
+  /// Parameter values and part opacities copied by ID from another model, e.g. to respawn a character mid-animation.
+  ///
+  /// Parameters and parts not found in the source model keep the values from _Live2D® Cubism SDK Core_.
+  CopiedFrom(&'a Model),
+}
+
+/// Builds a [`Model`] with an initial state. See [`Model::builder`].
+#[must_use]
+#[derive(Debug)]
+pub struct ModelBuilder<'a> {
+  moc: &'a Moc,
+  initial_state: InitialState<'a>,
+  parameter_values: Vec<(String, f32)>,
+  part_opacities: Vec<(String, f32)>,
+}
+impl<'a> ModelBuilder<'a> {
+  pub(crate) fn new(moc: &'a Moc) -> Self {
+    Self {
+      moc,
+      initial_state: InitialState::CoreValues,
+      parameter_values: Vec::new(),
+      part_opacities: Vec::new(),
+    }
+  }
+
+  /// Sets how the state is initialized before individual values are applied. Defaults to [`InitialState::CoreValues`].
+  pub fn initial_state(mut self, initial_state: InitialState<'a>) -> Self {
+    self.initial_state = initial_state;
+    self
+  }
+  /// Sets the initial value of the parameter with `id`.
+  pub fn parameter(mut self, id: impl Into<String>, value: f32) -> Self {
+    self.parameter_values.push((id.into(), value));
+    self
+  }
+  /// Sets the initial opacity of the part with `id`.
+  pub fn part_opacity(mut self, id: impl Into<String>, opacity: f32) -> Self {
+    self.part_opacities.push((id.into(), opacity));
+    self
+  }
+
+  /// Creates the model, applies the initial state and runs the first [`super::ModelDynamic::update`].
+  ///
+  /// Fails if any of the given IDs is not found, or if memory for the model cannot be allocated.
+  pub fn build(self) -> Result<Model, ModelBuildError> {
+    let model = Model::try_from_moc(self.moc)?;
+
+    let model_static = model.get_static();
+    let parameter_values = self.parameter_values.into_iter()
+      .map(|(id, value)| match model_static.parameter_index(&id) {
+        Some(index) => Ok((index, value)),
+        None => Err(ModelBuildError::UnknownParameter { id }),
+      })
+      .collect::<Result<Vec<(ParameterIndex, f32)>, _>>()?;
+    let part_opacities = self.part_opacities.into_iter()
+      .map(|(id, opacity)| match model_static.part_index(&id) {
+        Some(index) => Ok((index, opacity)),
+        None => Err(ModelBuildError::UnknownPart { id }),
+      })
+      .collect::<Result<Vec<(PartIndex, f32)>, _>>()?;
+
+    {
+      let mut dynamic = model.write_dynamic();
+      match self.initial_state {
+        InitialState::CoreValues => {}
+        InitialState::DeclaredDefaults => {
+          for (index, parameter) in model_static.parameters().iter().enumerate() {
+            dynamic[ParameterIndex::from(index)] = parameter.default_value();
+          }
+        }
+        InitialState::CopiedFrom(source) => {
+          copy_state_by_id(source.get_static(), &source.read_dynamic(), model_static, &mut dynamic);
+        }
+      }
+      for (index, value) in parameter_values {
+        dynamic[index] = value;
+      }
+      for (index, opacity) in part_opacities {
+        dynamic[index] = opacity;
+      }
+      dynamic.update();
+    }
+
+    Ok(model)
+  }
+}
+
+/// Copies parameter values and part opacities from `source` into `target`, matching them by ID.
+pub(crate) fn copy_state_by_id(source_static: &ModelStatic, source: &ModelDynamic, target_static: &ModelStatic, target: &mut ModelDynamic) {
+  copy_by_id(
+    source_static.parameters().iter().map(|parameter| parameter.id()),
+    source.parameter_values(),
+    target_static.parameters().iter().map(|parameter| parameter.id()),
+    target.parameter_values_mut(),
+  );
+  copy_by_id(
+    source_static.parts().iter().map(|part| part.id()),
+    source.part_opacities(),
+    target_static.parts().iter().map(|part| part.id()),
+    target.part_opacities_mut(),
+  );
+}
+
+pub(crate) fn copy_by_id<'a>(
+  source_ids: impl Iterator<Item = &'a str> + Clone,
+  source_values: &[f32],
+  target_ids: impl Iterator<Item = &'a str> + Clone,
+  target_values: &mut [f32],
+) {
+  // Fast path for models of the same moc.
+  if source_ids.clone().eq(target_ids.clone()) {
+    target_values.copy_from_slice(source_values);
+    return;
+  }
+
+  let source_values_by_id: HashMap<&str, f32> = source_ids.zip(source_values.iter().copied()).collect();
+  for (id, target_value) in target_ids.zip(target_values.iter_mut()) {
+    if let Some(&value) = source_values_by_id.get(id) {
+      *target_value = value;
+    }
+  }
+}
@@ -0,0 +1,44 @@
+//! Conversion of colors between sRGB and linear, e.g. for multiply and screen colors.
+//!
+//! Multiply and screen colors, like textures, are authored in sRGB, and the official renderers blend in sRGB without conversion.
+//! A pipeline that samples textures through an `*Srgb` format or renders into an `*Srgb` target blends in linear instead,
+//! so the colors passed to its shaders MUST be converted with [`srgb_to_linear_rgb`], or the model looks washed out.
+//! Screen colors and blending still differ slightly from the official renderers in linear, as they are not linear operations.
+
+use super::base_types::Vector4;
+
+/// Converts an sRGB-encoded component in `[0, 1]` to linear.
+pub fn srgb_to_linear(value: f32) -> f32 {
+  if value <= 0.04045 {
+    value / 12.92
+  } else {
+    ((value + 0.055) / 1.055).powf(2.4)
+  }
+}
+/// Converts a linear component in `[0, 1]` to sRGB encoding.
+pub fn linear_to_srgb(value: f32) -> f32 {
+  if value <= 0.0031308 {
+    value * 12.92
+  } else {
+    1.055 * value.powf(1.0 / 2.4) - 0.055
+  }
+}
+
+/// Converts the RGB components of an sRGB color to linear. Alpha is left as is.
+pub fn srgb_to_linear_rgb(color: Vector4) -> Vector4 {
+  Vector4 {
+    x: srgb_to_linear(color.x),
+    y: srgb_to_linear(color.y),
+    z: srgb_to_linear(color.z),
+    w: color.w,
+  }
+}
+/// Converts the RGB components of a linear color to sRGB. Alpha is left as is.
+pub fn linear_to_srgb_rgb(color: Vector4) -> Vector4 {
+  Vector4 {
+    x: linear_to_srgb(color.x),
+    y: linear_to_srgb(color.y),
+    z: linear_to_srgb(color.z),
+    w: color.w,
+  }
+}
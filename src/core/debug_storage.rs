@@ -0,0 +1,100 @@
+//! Tracking of the live memory blocks of mocs and models, with where they were created, to find models leaked
+//! through forgotten [`std::sync::Arc`]s in long-running applications.
+//!
+//! Capturing a backtrace on every creation is slow, so this is meant for debugging sessions only.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::backtrace::Backtrace;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+/// What a memory block holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StorageKind {
+  /// A revived moc, kept alive by its [`super::Moc`] and every [`super::Model`] created from it.
+  Moc,
+  /// A model instance, kept alive by its [`super::Model`].
+  Model,
+}
+
+/// A memory block still alive.
+#[derive(Debug, Clone)]
+pub struct LiveStorage {
+  pub kind: StorageKind,
+  /// In bytes.
+  pub size: usize,
+  /// Time since the block was created.
+  pub age: Duration,
+  /// Where the block was created.
+  pub backtrace: Arc<Backtrace>,
+}
+
+#[derive(Debug)]
+struct Entry {
+  kind: StorageKind,
+  size: usize,
+  created: Instant,
+  backtrace: Arc<Backtrace>,
+}
+
+fn entries() -> &'static Mutex<HashMap<u64, Entry>> {
+  static ENTRIES: OnceLock<Mutex<HashMap<u64, Entry>>> = OnceLock::new();
+  ENTRIES.get_or_init(Mutex::default)
+}
+
+/// Gets the blocks alive, oldest first.
+pub fn live_storages() -> Vec<LiveStorage> {
+  let mut entries: Vec<_> = entries().lock().iter()
+    .map(|(&id, entry)| (id, LiveStorage {
+      kind: entry.kind,
+      size: entry.size,
+      age: entry.created.elapsed(),
+      backtrace: Arc::clone(&entry.backtrace),
+    }))
+    .collect();
+  entries.sort_by_key(|(id, _)| *id);
+  entries.into_iter().map(|(_, storage)| storage).collect()
+}
+
+/// Formats [`live_storages`] for a log, e.g. when a streaming application expected every model to be dropped.
+pub fn report() -> String {
+  let storages = live_storages();
+  let mut report = format!(
+    "{} live storages, {} bytes\n",
+    storages.len(),
+    storages.iter().map(|storage| storage.size).sum::<usize>(),
+  );
+  for storage in &storages {
+    let _ = writeln!(report, "{:?}, {} bytes, alive for {:?}, created at:\n{}", storage.kind, storage.size, storage.age, storage.backtrace);
+  }
+  report
+}
+
+/// Registers a block in [`live_storages`] until dropped.
+#[derive(Debug)]
+pub(crate) struct StorageTracker(u64);
+impl StorageTracker {
+  pub(crate) fn new(kind: StorageKind, size: usize) -> Self {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+
+    entries().lock().insert(id, Entry {
+      kind,
+      size,
+      created: Instant::now(),
+      backtrace: Arc::new(Backtrace::force_capture()),
+    });
+    Self(id)
+  }
+}
+impl Drop for StorageTracker {
+  fn drop(&mut self) {
+    entries().lock().remove(&self.0);
+  }
+}
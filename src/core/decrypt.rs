@@ -0,0 +1,45 @@
+//! Decryption of protected model assets, for applications shipping encrypted moc3 and texture files.
+//!
+//! Register an [`AssetDecryptor`] with [`super::CubismCore::set_decryptor`], and every moc loaded through that
+//! [`super::CubismCore`] is decrypted first, including by [`super::MocCache`] and the file watcher.
+//! Textures are not loaded by this crate; decrypt them with the same [`AssetDecryptor::decrypt`] in the renderer.
+
+use std::sync::Arc;
+
+/// Error returned by a decryption callback.
+pub type DecryptError = Box<dyn std::error::Error + Send + Sync>;
+
+/// What is being decrypted, e.g. to pick a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AssetKind {
+  Moc,
+  Texture,
+  /// Anything else, e.g. a `.model3.json` or a motion.
+  Other,
+}
+
+type DecryptFn = dyn Fn(AssetKind, &[u8]) -> Result<Vec<u8>, DecryptError> + Send + Sync;
+
+/// A decryption callback taking encrypted bytes and returning the plain ones.
+///
+/// Cloning is cheap, as clones share the same callback.
+#[derive(Clone)]
+pub struct AssetDecryptor(Arc<DecryptFn>);
+impl AssetDecryptor {
+  pub fn new<F>(f: F) -> Self
+  where
+    F: Fn(AssetKind, &[u8]) -> Result<Vec<u8>, DecryptError> + Send + Sync + 'static,
+  {
+    Self(Arc::new(f))
+  }
+
+  pub fn decrypt(&self, kind: AssetKind, bytes: &[u8]) -> Result<Vec<u8>, DecryptError> {
+    (self.0)(kind, bytes)
+  }
+}
+impl std::fmt::Debug for AssetDecryptor {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("AssetDecryptor").finish_non_exhaustive()
+  }
+}
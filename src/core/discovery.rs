@@ -0,0 +1,210 @@
+//! Heuristic classification of the parameters of arbitrary models, so that generic tools can configure controllers
+//! without a hand-written mapping per model.
+//!
+//! Parameters are tagged by their IDs, split into words, and by the shape of their ranges. This is a best effort:
+//! physics outputs in particular are only recognized by name, since physics settings are not part of a moc.
+
+use super::base_types::ParameterIndex;
+use super::{standard_params, Parameter, ModelStatic};
+
+/// What a parameter is likely to drive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ParameterRole {
+  HeadAngleX,
+  HeadAngleY,
+  HeadAngleZ,
+  BodyAngleX,
+  BodyAngleY,
+  BodyAngleZ,
+  EyeLOpen,
+  EyeROpen,
+  EyeBallX,
+  EyeBallY,
+  MouthOpen,
+  MouthForm,
+  Breath,
+  /// Hair swaying, typically an output of physics.
+  HairPhysics,
+}
+impl ParameterRole {
+  pub const ALL: &'static [Self] = &[
+    Self::HeadAngleX, Self::HeadAngleY, Self::HeadAngleZ,
+    Self::BodyAngleX, Self::BodyAngleY, Self::BodyAngleZ,
+    Self::EyeLOpen, Self::EyeROpen, Self::EyeBallX, Self::EyeBallY,
+    Self::MouthOpen, Self::MouthForm, Self::Breath, Self::HairPhysics,
+  ];
+
+  /// IDs of the standard parameters of this role, see [`standard_params`].
+  pub fn standard_ids(&self) -> &'static [&'static str] {
+    match self {
+      Self::HeadAngleX => &[standard_params::ANGLE_X],
+      Self::HeadAngleY => &[standard_params::ANGLE_Y],
+      Self::HeadAngleZ => &[standard_params::ANGLE_Z],
+      Self::BodyAngleX => &[standard_params::BODY_ANGLE_X],
+      Self::BodyAngleY => &[standard_params::BODY_ANGLE_Y],
+      Self::BodyAngleZ => &[standard_params::BODY_ANGLE_Z],
+      Self::EyeLOpen => &[standard_params::EYE_L_OPEN],
+      Self::EyeROpen => &[standard_params::EYE_R_OPEN],
+      Self::EyeBallX => &[standard_params::EYE_BALL_X],
+      Self::EyeBallY => &[standard_params::EYE_BALL_Y],
+      Self::MouthOpen => &[standard_params::MOUTH_OPEN_Y],
+      Self::MouthForm => &[standard_params::MOUTH_FORM],
+      Self::Breath => &[standard_params::BREATH],
+      Self::HairPhysics => &[standard_params::HAIR_FRONT, standard_params::HAIR_SIDE, standard_params::HAIR_BACK],
+    }
+  }
+
+  /// Words of which an ID must contain one from each group, and words it must not contain.
+  fn keywords(&self) -> (&'static [&'static [&'static str]], &'static [&'static str]) {
+    const HEAD_EXCLUDED: &[&str] = &["body", "eye", "ball", "brow", "arm", "hand", "leg", "hair"];
+    match self {
+      Self::HeadAngleX => (&[&["angle", "head", "face"], &["x"]], HEAD_EXCLUDED),
+      Self::HeadAngleY => (&[&["angle", "head", "face"], &["y"]], HEAD_EXCLUDED),
+      Self::HeadAngleZ => (&[&["angle", "head", "face"], &["z"]], HEAD_EXCLUDED),
+      Self::BodyAngleX => (&[&["body"], &["x"]], &[]),
+      Self::BodyAngleY => (&[&["body"], &["y"]], &[]),
+      Self::BodyAngleZ => (&[&["body"], &["z"]], &[]),
+      Self::EyeLOpen => (&[&["eye"], &["open"], &["l", "left"]], &["smile", "ball"]),
+      Self::EyeROpen => (&[&["eye"], &["open"], &["r", "right"]], &["smile", "ball"]),
+      Self::EyeBallX => (&[&["ball", "eyeball", "pupil"], &["x"]], &[]),
+      Self::EyeBallY => (&[&["ball", "eyeball", "pupil"], &["y"]], &[]),
+      Self::MouthOpen => (&[&["mouth"], &["open"]], &["form", "smile"]),
+      Self::MouthForm => (&[&["mouth"], &["form", "shape", "smile"]], &["open"]),
+      Self::Breath => (&[&["breath", "breathing"]], &[]),
+      Self::HairPhysics => (&[&["hair"]], &[]),
+    }
+  }
+
+  fn range_shape(&self) -> RangeShape {
+    match self {
+      Self::EyeLOpen | Self::EyeROpen => RangeShape::FromZero { default_at_maximum: true },
+      Self::MouthOpen | Self::Breath => RangeShape::FromZero { default_at_maximum: false },
+      _ => RangeShape::Symmetric,
+    }
+  }
+}
+
+/// Typical range of the parameters of a role.
+#[derive(Debug, Clone, Copy)]
+enum RangeShape {
+  /// Around zero, e.g. `[-30, 30]`, defaulting to zero.
+  Symmetric,
+  /// From zero, e.g. `[0, 1]`.
+  FromZero { default_at_maximum: bool },
+}
+impl RangeShape {
+  /// How well `parameter` fits, in `[0, 1]`.
+  fn score(&self, parameter: &Parameter) -> f32 {
+    let (minimum, maximum) = parameter.value_range();
+    let default_value = parameter.default_value();
+    let tolerance = (maximum - minimum).abs() * 1e-3;
+
+    let (range_fits, default_fits) = match self {
+      Self::Symmetric => ((minimum + maximum).abs() <= tolerance && maximum > 0.0, default_value.abs() <= tolerance),
+      Self::FromZero { default_at_maximum } => {
+        let expected_default = if *default_at_maximum { maximum } else { minimum };
+        (minimum.abs() <= tolerance && maximum > 0.0, (default_value - expected_default).abs() <= tolerance)
+      },
+    };
+    if range_fits { 0.7 + if default_fits { 0.3 } else { 0.0 } } else { 0.0 }
+  }
+}
+
+/// A role tagged on a parameter by [`ParameterDiscovery`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParameterClassification {
+  pub parameter_index: ParameterIndex,
+  pub role: ParameterRole,
+  /// In `(0, 1]`: `1` for a standard ID, at most `0.9` otherwise.
+  pub confidence: f32,
+}
+
+/// Result of [`ModelStatic::discover_parameters`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParameterDiscovery {
+  /// By descending confidence, then by parameter index.
+  classifications: Vec<ParameterClassification>,
+}
+impl ParameterDiscovery {
+  pub fn new(model_static: &ModelStatic) -> Self {
+    let mut classifications: Vec<ParameterClassification> = model_static.parameters().iter().enumerate()
+      .flat_map(|(index, parameter)| {
+        let words = split_words(parameter.id());
+        ParameterRole::ALL.iter().filter_map(move |&role| {
+          let confidence = confidence(role, parameter, &words);
+          (confidence > 0.0).then_some(ParameterClassification { parameter_index: ParameterIndex::from(index), role, confidence })
+        })
+      })
+      .collect();
+    classifications.sort_by(|a, b| b.confidence.total_cmp(&a.confidence).then(a.parameter_index.cmp(&b.parameter_index)));
+    Self { classifications }
+  }
+
+  /// Every role tagged, by descending confidence, then by parameter index. A parameter may have several.
+  pub fn classifications(&self) -> &[ParameterClassification] {
+    &self.classifications
+  }
+  /// The parameter most confidently tagged with `role`.
+  pub fn best(&self, role: ParameterRole) -> Option<&ParameterClassification> {
+    self.classifications.iter().find(|classification| classification.role == role)
+  }
+  /// Roles tagged on a parameter, by descending confidence.
+  pub fn roles_of(&self, parameter_index: ParameterIndex) -> impl Iterator<Item = &ParameterClassification> + '_ {
+    self.classifications.iter().filter(move |classification| classification.parameter_index == parameter_index)
+  }
+}
+
+impl ModelStatic {
+  /// Shorthand for [`ParameterDiscovery::new`].
+  pub fn discover_parameters(&self) -> ParameterDiscovery {
+    ParameterDiscovery::new(self)
+  }
+}
+
+fn confidence(role: ParameterRole, parameter: &Parameter, words: &[String]) -> f32 {
+  let id = normalize(parameter.id());
+  if role.standard_ids().iter().any(|&standard_id| normalize(standard_id) == id) {
+    return 1.0;
+  }
+
+  let has_word = |candidates: &[&str]| words.iter().any(|word| candidates.contains(&word.as_str()));
+  let (required, excluded) = role.keywords();
+  if !required.iter().all(|&group| has_word(group)) || has_word(excluded) {
+    return 0.0;
+  }
+  0.6 + 0.3 * role.range_shape().score(parameter)
+}
+
+/// Splits an ID into lowercase words at non-alphanumeric characters, case changes and digits,
+/// e.g. `ParamEyeLOpen` into `param`, `eye`, `l` and `open`.
+fn split_words(id: &str) -> Vec<String> {
+  let chars: Vec<char> = id.chars().collect();
+  let mut words = Vec::new();
+  let mut word = String::new();
+  for (i, &c) in chars.iter().enumerate() {
+    if !c.is_ascii_alphanumeric() {
+      words.extend((!word.is_empty()).then(|| std::mem::take(&mut word)));
+      continue;
+    }
+    if let Some(&previous) = i.checked_sub(1).map(|i| &chars[i]) {
+      let next_is_lowercase = chars.get(i + 1).is_some_and(char::is_ascii_lowercase);
+      let is_boundary = (c.is_ascii_uppercase() && previous.is_ascii_lowercase())
+        || (c.is_ascii_uppercase() && previous.is_ascii_uppercase() && next_is_lowercase)
+        || c.is_ascii_digit() != previous.is_ascii_digit();
+      if is_boundary && !word.is_empty() {
+        words.push(std::mem::take(&mut word));
+      }
+    }
+    word.push(c.to_ascii_lowercase());
+  }
+  words.extend((!word.is_empty()).then_some(word));
+  words
+}
+
+fn normalize(id: &str) -> String {
+  id.chars()
+    .filter(char::is_ascii_alphanumeric)
+    .map(|c| c.to_ascii_lowercase())
+    .collect()
+}
@@ -0,0 +1,92 @@
+//! Plain data components for integrating models into an ECS such as `bevy_ecs` or `hecs`, without any rendering.
+//!
+//! Any of these can be used as a `hecs` component as is. With the `bevy_ecs` feature, they also implement `bevy_ecs::component::Component`.
+//!
+//! A typical frame: systems push into [`ParameterWrites`], one system calls [`update_model`] per entity,
+//! and renderers or other systems read [`FrameOutput`].
+
+use std::sync::Arc;
+
+use super::base_types::{DrawableIndex, ParameterIndex};
+use super::model_types::UpdateSummary;
+use super::{Model, ModelDynamic};
+
+/// The model of an entity.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "bevy_ecs", derive(bevy_ecs::component::Component))]
+pub struct ModelHandleComponent(pub Arc<Model>);
+impl std::ops::Deref for ModelHandleComponent {
+  type Target = Model;
+
+  fn deref(&self) -> &Self::Target {
+    &self.0
+  }
+}
+
+/// A pending write of a parameter value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterWrite {
+  Set(ParameterIndex, f32),
+  Add(ParameterIndex, f32),
+}
+
+/// Parameter writes queued by systems for the next [`update_model`], applied in order.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "bevy_ecs", derive(bevy_ecs::component::Component))]
+pub struct ParameterWrites {
+  writes: Vec<ParameterWrite>,
+}
+impl ParameterWrites {
+  pub fn set(&mut self, index: ParameterIndex, value: f32) {
+    self.writes.push(ParameterWrite::Set(index, value));
+  }
+  pub fn add(&mut self, index: ParameterIndex, delta: f32) {
+    self.writes.push(ParameterWrite::Add(index, delta));
+  }
+  pub fn writes(&self) -> &[ParameterWrite] {
+    &self.writes
+  }
+  pub fn is_empty(&self) -> bool {
+    self.writes.is_empty()
+  }
+
+  /// Applies and clears the queued writes. Writes to parameters out of range are dropped.
+  pub fn apply(&mut self, dynamic: &mut ModelDynamic) {
+    let parameter_values = dynamic.parameter_values_mut();
+    for write in self.writes.drain(..) {
+      match write {
+        ParameterWrite::Set(index, value) => if let Some(target) = parameter_values.get_mut(index.as_usize()) {
+          *target = value;
+        },
+        ParameterWrite::Add(index, delta) => if let Some(target) = parameter_values.get_mut(index.as_usize()) {
+          *target += delta;
+        },
+      }
+    }
+  }
+}
+
+/// What the last [`update_model`] produced, for systems that only read.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "bevy_ecs", derive(bevy_ecs::component::Component))]
+pub struct FrameOutput {
+  /// Incremented by each [`update_model`].
+  pub frame: u64,
+  pub summary: UpdateSummary,
+  /// Drawables to draw, in the order of [`ModelDynamic::drawing_sequence`]. See [`ModelDynamic::is_drawable_drawn`].
+  pub draw_list: Vec<DrawableIndex>,
+}
+
+/// Applies `writes`, updates the model and fills `output`.
+///
+/// Acquires a write lock for the [`ModelDynamic`] of the model, so post-update hooks are called.
+pub fn update_model(model: &ModelHandleComponent, writes: &mut ParameterWrites, output: &mut FrameOutput) {
+  let mut dynamic = model.write_dynamic();
+
+  writes.apply(&mut dynamic);
+  output.summary = dynamic.update_with_summary();
+  output.frame += 1;
+
+  dynamic.write_drawing_sequence(&mut output.draw_list);
+  output.draw_list.retain(|&index| dynamic.is_drawable_drawn(index));
+}
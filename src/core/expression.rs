@@ -0,0 +1,150 @@
+//! Expressions in the sense of `.exp3.json`: parameter offsets on top of the current pose, e.g. a smile or a blush,
+//! and their capture from a live state, to author them interactively in tools.
+//!
+//! Playing expressions back with fades is left to the application; this crate has no expression manager.
+
+use super::{ModelStatic, ModelDynamic, ModelDynamicSnapshot};
+
+/// How an [`ExpressionParameter`] combines with the value of its parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ExpressionBlend {
+  /// Adds the value.
+  #[default]
+  Add,
+  /// Multiplies by the value.
+  Multiply,
+  /// Replaces with the value.
+  Overwrite,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpressionParameter {
+  pub id: String,
+  pub value: f32,
+  pub blend: ExpressionBlend,
+}
+
+/// The contents of an `.exp3.json`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Expression {
+  /// In seconds, `None` for the default of 1 second.
+  pub fade_in_time: Option<f32>,
+  /// In seconds, `None` for the default of 1 second.
+  pub fade_out_time: Option<f32>,
+  pub parameters: Vec<ExpressionParameter>,
+}
+impl Expression {
+  /// Captures the parameters of `dynamic` that differ from the neutral `base`, e.g. after posing the face with sliders.
+  ///
+  /// With [`ExpressionBlend::Multiply`], parameters whose neutral value is zero are captured with
+  /// [`ExpressionBlend::Overwrite`] instead. Part opacities are not captured, since `.exp3.json` has no place for them.
+  ///
+  /// ## Panics
+  /// - If `dynamic` or `base` is of a model of a different moc than `model_static`.
+  pub fn capture(model_static: &ModelStatic, dynamic: &ModelDynamic, base: &ModelDynamicSnapshot, options: &ExpressionCaptureOptions) -> Self {
+    let parameter_values = dynamic.parameter_values();
+    let base_parameter_values = base.parameter_values();
+    assert!(
+      parameter_values.len() == model_static.parameters().len() && base_parameter_values.len() == model_static.parameters().len(),
+      "State does not match the model"
+    );
+
+    let parameters = model_static.parameters().iter().zip(parameter_values.iter().zip(base_parameter_values.iter()))
+      .filter(|(_, (value, base_value))| (*value - *base_value).abs() > options.tolerance)
+      .map(|(parameter, (&value, &base_value))| {
+        let (value, blend) = match options.blend {
+          ExpressionBlend::Add => (value - base_value, ExpressionBlend::Add),
+          ExpressionBlend::Multiply if base_value != 0.0 => (value / base_value, ExpressionBlend::Multiply),
+          ExpressionBlend::Multiply | ExpressionBlend::Overwrite => (value, ExpressionBlend::Overwrite),
+        };
+        ExpressionParameter {
+          id: parameter.id().to_owned(),
+          value,
+          blend,
+        }
+      })
+      .collect();
+
+    Self {
+      fade_in_time: options.fade_in_time,
+      fade_out_time: options.fade_out_time,
+      parameters,
+    }
+  }
+
+  /// Serializes as an `.exp3.json` document.
+  #[cfg(feature = "serde")]
+  pub fn to_exp3_json(&self) -> String {
+    serde_json::to_string_pretty(&self.to_exp3()).expect("Serializing an exp3.json should not fail")
+  }
+  /// Like [`Expression::to_exp3_json`], but writes into `writer`.
+  #[cfg(feature = "serde")]
+  pub fn write_exp3_json<W: std::io::Write>(&self, writer: W) -> Result<(), serde_json::Error> {
+    serde_json::to_writer_pretty(writer, &self.to_exp3())
+  }
+  #[cfg(feature = "serde")]
+  fn to_exp3(&self) -> exp3::Exp3Json<'_> {
+    exp3::Exp3Json {
+      ty: "Live2D Expression",
+      fade_in_time: self.fade_in_time,
+      fade_out_time: self.fade_out_time,
+      parameters: self.parameters.iter()
+        .map(|parameter| exp3::Parameter {
+          id: &parameter.id,
+          value: parameter.value,
+          blend: match parameter.blend {
+            ExpressionBlend::Add => "Add",
+            ExpressionBlend::Multiply => "Multiply",
+            ExpressionBlend::Overwrite => "Overwrite",
+          },
+        })
+        .collect(),
+    }
+  }
+}
+
+/// Options for [`Expression::capture`].
+#[derive(Debug, Clone)]
+pub struct ExpressionCaptureOptions {
+  pub blend: ExpressionBlend,
+  /// Parameters differing from the neutral state by at most this are omitted.
+  pub tolerance: f32,
+  pub fade_in_time: Option<f32>,
+  pub fade_out_time: Option<f32>,
+}
+impl Default for ExpressionCaptureOptions {
+  fn default() -> Self {
+    Self {
+      blend: ExpressionBlend::Add,
+      tolerance: 1e-3,
+      fade_in_time: None,
+      fade_out_time: None,
+    }
+  }
+}
+
+/// Serialized form of `.exp3.json`.
+#[cfg(feature = "serde")]
+mod exp3 {
+  use serde::Serialize;
+
+  #[derive(Debug, Serialize)]
+  #[serde(rename_all = "PascalCase")]
+  pub struct Exp3Json<'a> {
+    #[serde(rename = "Type")]
+    pub ty: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fade_in_time: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fade_out_time: Option<f32>,
+    pub parameters: Vec<Parameter<'a>>,
+  }
+
+  #[derive(Debug, Serialize)]
+  #[serde(rename_all = "PascalCase")]
+  pub struct Parameter<'a> {
+    pub id: &'a str,
+    pub value: f32,
+    pub blend: &'static str,
+  }
+}
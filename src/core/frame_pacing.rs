@@ -0,0 +1,114 @@
+//! Interpolation of states published by a simulation ticking slower than the display, e.g. at 30 Hz on its own thread
+//! while rendering at 144 Hz.
+
+use std::collections::VecDeque;
+
+use super::ModelDynamic;
+
+#[derive(Debug, Clone)]
+struct PublishedTick {
+  time: f64,
+  parameter_values: Box<[f32]>,
+  part_opacities: Box<[f32]>,
+}
+
+/// Keeps the parameter values and part opacities of the latest simulation ticks, up to a capacity,
+/// and interpolates them at display times.
+///
+/// Display times are typically one tick interval behind the latest tick, so that there is always a later tick to
+/// interpolate towards. Share it between threads with a [`std::sync::Mutex`].
+#[derive(Debug, Clone)]
+pub struct FrameInterpolator {
+  /// Oldest first.
+  ticks: VecDeque<PublishedTick>,
+  capacity: usize,
+}
+impl FrameInterpolator {
+  /// ## Panics
+  /// - If `capacity` is less than `2`.
+  pub fn new(capacity: usize) -> Self {
+    assert!(capacity >= 2, "Capacity must be at least 2, given: {capacity}");
+    Self {
+      ticks: VecDeque::with_capacity(capacity),
+      capacity,
+    }
+  }
+
+  /// Number of ticks kept at most.
+  pub fn capacity(&self) -> usize {
+    self.capacity
+  }
+  /// Number of ticks kept.
+  pub fn len(&self) -> usize {
+    self.ticks.len()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.ticks.is_empty()
+  }
+  pub fn clear(&mut self) {
+    self.ticks.clear();
+  }
+  /// Time of the latest tick published, in seconds.
+  pub fn latest_time(&self) -> Option<f64> {
+    self.ticks.back().map(|tick| tick.time)
+  }
+
+  /// Publishes the current parameter values and part opacities of `dynamic` as the tick at `time` seconds,
+  /// dropping the oldest tick if full.
+  ///
+  /// ## Panics
+  /// - If `time` is earlier than the latest tick.
+  pub fn publish(&mut self, time: f64, dynamic: &ModelDynamic) {
+    if let Some(latest_time) = self.latest_time() {
+      assert!(latest_time <= time, "Ticks must be published in chronological order. latest: {latest_time} given: {time}");
+    }
+
+    let tick = if self.ticks.len() == self.capacity {
+      // Reuse the allocations of the oldest tick when the shapes match.
+      let mut tick = self.ticks.pop_front().expect("capacity is positive");
+      tick.time = time;
+      if tick.parameter_values.len() == dynamic.parameter_values().len() && tick.part_opacities.len() == dynamic.part_opacities().len() {
+        tick.parameter_values.copy_from_slice(dynamic.parameter_values());
+        tick.part_opacities.copy_from_slice(dynamic.part_opacities());
+      } else {
+        tick.parameter_values = dynamic.parameter_values().into();
+        tick.part_opacities = dynamic.part_opacities().into();
+      }
+      tick
+    } else {
+      PublishedTick {
+        time,
+        parameter_values: dynamic.parameter_values().into(),
+        part_opacities: dynamic.part_opacities().into(),
+      }
+    };
+    self.ticks.push_back(tick);
+  }
+
+  /// Writes the parameter values and part opacities interpolated at `time` seconds into `dynamic`,
+  /// returning `false` without writing if no tick was published.
+  ///
+  /// Times outside of the ticks kept are clamped to the oldest or the latest one.
+  /// Cyclic parameters, see [`ModelDynamic::set_parameter_cyclic`], are interpolated the shorter way around their range.
+  /// Call [`ModelDynamic::update`] afterwards.
+  ///
+  /// ## Panics
+  /// - If the ticks were published from a model of a different moc.
+  pub fn apply(&self, time: f64, dynamic: &mut ModelDynamic) -> bool {
+    let Some(oldest) = self.ticks.front() else {
+      return false;
+    };
+
+    let later_index = self.ticks.partition_point(|tick| tick.time <= time);
+    let (a, b, t) = match later_index {
+      0 => (oldest, oldest, 0.0),
+      index if index == self.ticks.len() => (&self.ticks[index - 1], &self.ticks[index - 1], 0.0),
+      index => {
+        let (a, b) = (&self.ticks[index - 1], &self.ticks[index]);
+        (a, b, ((time - a.time) / (b.time - a.time)) as f32)
+      },
+    };
+    dynamic.blend_parameter_state((&a.parameter_values, &a.part_opacities), (&b.parameter_values, &b.part_opacities), t);
+    true
+  }
+}
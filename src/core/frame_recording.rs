@@ -0,0 +1,334 @@
+//! Recording of the per-frame output of a model to a compact binary stream, and reading it back,
+//! e.g. to render a captured performance offline.
+
+use std::io::{self, Read, Write};
+
+use flagset::{FlagSet, flags};
+use thiserror::Error;
+
+use super::base_types::Vector2;
+use super::ModelDynamic;
+
+/// Errors generated when reading a frame recording.
+#[derive(Debug, Error)]
+pub enum FrameRecordingError {
+  #[error("Not a frame recording.")]
+  InvalidMagic,
+  #[error("Unsupported frame recording version {0}. Latest supported is {latest}.", latest = FrameRecorder::<Vec<u8>>::VERSION)]
+  UnsupportedVersion(u32),
+  #[error("Frame recording is truncated.")]
+  UnexpectedEnd,
+  #[error(transparent)]
+  Io(io::Error),
+}
+impl From<io::Error> for FrameRecordingError {
+  fn from(err: io::Error) -> Self {
+    match err.kind() {
+      io::ErrorKind::UnexpectedEof => Self::UnexpectedEnd,
+      _ => Self::Io(err),
+    }
+  }
+}
+
+flags! {
+  /// What each frame of a recording contains.
+  pub enum FrameContentFlags: u8 {
+    ParameterValues = 1 << 0,
+    PartOpacities = 1 << 1,
+    DrawableOpacities = 1 << 2,
+    /// Deformed vertex positions of every drawable, by far the largest.
+    VertexPositions = 1 << 3,
+  }
+}
+pub type FrameContentFlagSet = FlagSet<FrameContentFlags>;
+
+/// Counts fixed for the whole recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Layout {
+  contents: FrameContentFlagSet,
+  parameter_count: usize,
+  part_count: usize,
+  drawable_vertex_counts: Vec<usize>,
+}
+
+/// Writes frames of a model's output to `W`.
+///
+/// Layout, little-endian: `"L2DF"`, version as `u32`, [`FrameContentFlagSet`] as `u8`, parameter, part and drawable counts as `u32`,
+/// and the vertex count of each drawable as `u32`. Then per frame: time as `f32`, followed by the recorded contents as `f32`s,
+/// in the order of [`FrameContentFlags`].
+#[derive(Debug)]
+pub struct FrameRecorder<W: Write> {
+  writer: W,
+  layout: Layout,
+  frame_count: usize,
+}
+impl<W: Write> FrameRecorder<W> {
+  /// Current version of the binary format.
+  pub const VERSION: u32 = 1;
+  const MAGIC: [u8; 4] = *b"L2DF";
+
+  /// Writes the header for frames of `contents` from the model of `dynamic`.
+  pub fn new(mut writer: W, contents: impl Into<FrameContentFlagSet>, dynamic: &ModelDynamic) -> io::Result<Self> {
+    let layout = Layout {
+      contents: contents.into(),
+      parameter_count: dynamic.parameter_values().len(),
+      part_count: dynamic.part_opacities().len(),
+      drawable_vertex_counts: dynamic.drawable_vertex_position_containers().iter().map(|positions| positions.len()).collect(),
+    };
+
+    writer.write_all(&Self::MAGIC)?;
+    writer.write_all(&Self::VERSION.to_le_bytes())?;
+    writer.write_all(&[layout.contents.bits()])?;
+    for count in [layout.parameter_count, layout.part_count, layout.drawable_vertex_counts.len()] {
+      writer.write_all(&(count as u32).to_le_bytes())?;
+    }
+    for &count in &layout.drawable_vertex_counts {
+      writer.write_all(&(count as u32).to_le_bytes())?;
+    }
+
+    Ok(Self {
+      writer,
+      layout,
+      frame_count: 0,
+    })
+  }
+
+  /// Appends the current output of `dynamic` at `time` seconds.
+  ///
+  /// ## Panics
+  /// - If `dynamic` belongs to a model of a different moc.
+  pub fn write_frame(&mut self, time: f32, dynamic: &ModelDynamic) -> io::Result<()> {
+    assert_eq!(dynamic.parameter_values().len(), self.layout.parameter_count, "Recorder does not match the model");
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&time.to_le_bytes());
+    let mut extend = |values: &[f32]| values.iter().for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+    let contents = self.layout.contents;
+    if contents.contains(FrameContentFlags::ParameterValues) {
+      extend(dynamic.parameter_values());
+    }
+    if contents.contains(FrameContentFlags::PartOpacities) {
+      extend(dynamic.part_opacities());
+    }
+    if contents.contains(FrameContentFlags::DrawableOpacities) {
+      extend(dynamic.drawable_opacities());
+    }
+    if contents.contains(FrameContentFlags::VertexPositions) {
+      for positions in dynamic.drawable_vertex_position_containers() {
+        for position in positions.iter() {
+          extend(&[position.x, position.y]);
+        }
+      }
+    }
+    self.writer.write_all(&bytes)?;
+
+    self.frame_count += 1;
+    Ok(())
+  }
+
+  pub fn frame_count(&self) -> usize {
+    self.frame_count
+  }
+  /// Flushes and returns the writer.
+  pub fn finish(mut self) -> io::Result<W> {
+    self.writer.flush()?;
+    Ok(self.writer)
+  }
+}
+impl FrameRecorder<io::BufWriter<std::fs::File>> {
+  /// Like [`FrameRecorder::new`], but into a file at `path`.
+  pub fn create<P: AsRef<std::path::Path>>(path: P, contents: impl Into<FrameContentFlagSet>, dynamic: &ModelDynamic) -> io::Result<Self> {
+    Self::new(io::BufWriter::new(std::fs::File::create(path)?), contents, dynamic)
+  }
+}
+
+/// A frame read by [`FrameReader`]. Contents not in the recording are empty.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordedFrame {
+  /// In seconds.
+  pub time: f32,
+  pub parameter_values: Vec<f32>,
+  pub part_opacities: Vec<f32>,
+  pub drawable_opacities: Vec<f32>,
+  pub drawable_vertex_positions: Vec<Vec<Vector2>>,
+}
+impl RecordedFrame {
+  /// Writes the recorded parameter values and part opacities into `dynamic`, e.g. to replay the performance on the model itself.
+  ///
+  /// ## Panics
+  /// - If the frame contains values and `dynamic` belongs to a model of a different moc.
+  pub fn apply(&self, dynamic: &mut ModelDynamic) {
+    if !self.parameter_values.is_empty() {
+      dynamic.parameter_values_mut().copy_from_slice(&self.parameter_values);
+    }
+    if !self.part_opacities.is_empty() {
+      dynamic.part_opacities_mut().copy_from_slice(&self.part_opacities);
+    }
+  }
+}
+
+/// Reads frames written by [`FrameRecorder`].
+#[derive(Debug)]
+pub struct FrameReader<R: Read> {
+  reader: R,
+  layout: Layout,
+}
+impl<R: Read> FrameReader<R> {
+  /// Reads the header.
+  pub fn new(mut reader: R) -> Result<Self, FrameRecordingError> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != FrameRecorder::<Vec<u8>>::MAGIC {
+      return Err(FrameRecordingError::InvalidMagic);
+    }
+    let version = read_u32(&mut reader)?;
+    if version == 0 || version > FrameRecorder::<Vec<u8>>::VERSION {
+      return Err(FrameRecordingError::UnsupportedVersion(version));
+    }
+
+    let mut contents = [0];
+    reader.read_exact(&mut contents)?;
+    let contents = FrameContentFlagSet::new_truncated(contents[0]);
+    let parameter_count = read_u32(&mut reader)? as usize;
+    let part_count = read_u32(&mut reader)? as usize;
+    let drawable_count = read_u32(&mut reader)? as usize;
+    // Not trusting `drawable_count` for the capacity, as the bytes may be corrupted.
+    let mut drawable_vertex_counts = Vec::new();
+    for _ in 0..drawable_count {
+      drawable_vertex_counts.push(read_u32(&mut reader)? as usize);
+    }
+
+    Ok(Self {
+      reader,
+      layout: Layout {
+        contents,
+        parameter_count,
+        part_count,
+        drawable_vertex_counts,
+      },
+    })
+  }
+
+  pub fn contents(&self) -> FrameContentFlagSet {
+    self.layout.contents
+  }
+  pub fn parameter_count(&self) -> usize {
+    self.layout.parameter_count
+  }
+  pub fn part_count(&self) -> usize {
+    self.layout.part_count
+  }
+  pub fn drawable_vertex_counts(&self) -> &[usize] {
+    &self.layout.drawable_vertex_counts
+  }
+
+  /// Reads the next frame, or returns `None` at the end of the recording.
+  pub fn read_frame(&mut self) -> Result<Option<RecordedFrame>, FrameRecordingError> {
+    let mut time = [0; 4];
+    let mut read = 0;
+    while read < time.len() {
+      match self.reader.read(&mut time[read..]) {
+        Ok(0) if read == 0 => return Ok(None),
+        Ok(0) => return Err(FrameRecordingError::UnexpectedEnd),
+        Ok(len) => read += len,
+        Err(err) if err.kind() == io::ErrorKind::Interrupted => {},
+        Err(err) => return Err(err.into()),
+      }
+    }
+
+    let mut frame = RecordedFrame {
+      time: f32::from_le_bytes(time),
+      ..Default::default()
+    };
+    let contents = self.layout.contents;
+    if contents.contains(FrameContentFlags::ParameterValues) {
+      frame.parameter_values = read_f32s(&mut self.reader, self.layout.parameter_count)?;
+    }
+    if contents.contains(FrameContentFlags::PartOpacities) {
+      frame.part_opacities = read_f32s(&mut self.reader, self.layout.part_count)?;
+    }
+    if contents.contains(FrameContentFlags::DrawableOpacities) {
+      frame.drawable_opacities = read_f32s(&mut self.reader, self.layout.drawable_vertex_counts.len())?;
+    }
+    if contents.contains(FrameContentFlags::VertexPositions) {
+      for &count in &self.layout.drawable_vertex_counts {
+        let values = read_f32s(&mut self.reader, count.checked_mul(2).ok_or(FrameRecordingError::UnexpectedEnd)?)?;
+        frame.drawable_vertex_positions.push(values.chunks_exact(2).map(|xy| Vector2 { x: xy[0], y: xy[1] }).collect());
+      }
+    }
+    Ok(Some(frame))
+  }
+}
+impl<R: Read> Iterator for FrameReader<R> {
+  type Item = Result<RecordedFrame, FrameRecordingError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.read_frame().transpose()
+  }
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+  let mut bytes = [0; 4];
+  reader.read_exact(&mut bytes)?;
+  Ok(u32::from_le_bytes(bytes))
+}
+/// Reads `count` values, failing with [`io::ErrorKind::UnexpectedEof`] rather than allocating for counts the reader
+/// can't back, as counts come from a possibly corrupted header.
+fn read_f32s<R: Read>(reader: &mut R, count: usize) -> io::Result<Vec<f32>> {
+  let len = count.checked_mul(4)
+    .and_then(|len| u64::try_from(len).ok())
+    .ok_or(io::ErrorKind::UnexpectedEof)?;
+  let mut bytes = Vec::new();
+  reader.take(len).read_to_end(&mut bytes)?;
+  if bytes.len() as u64 != len {
+    return Err(io::ErrorKind::UnexpectedEof.into());
+  }
+  Ok(bytes.chunks_exact(4).map(|value| f32::from_le_bytes(value.try_into().unwrap())).collect())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn header(parameter_count: u32, part_count: u32, drawable_vertex_counts: &[u32]) -> Vec<u8> {
+    let mut bytes = FrameRecorder::<Vec<u8>>::MAGIC.to_vec();
+    bytes.extend_from_slice(&FrameRecorder::<Vec<u8>>::VERSION.to_le_bytes());
+    bytes.push(FrameContentFlagSet::full().bits());
+    for count in [parameter_count, part_count, drawable_vertex_counts.len() as u32] {
+      bytes.extend_from_slice(&count.to_le_bytes());
+    }
+    for &count in drawable_vertex_counts {
+      bytes.extend_from_slice(&count.to_le_bytes());
+    }
+    bytes
+  }
+
+  #[test]
+  fn huge_counts_fail_without_allocating() {
+    let mut bytes = header(u32::MAX, u32::MAX, &[u32::MAX, u32::MAX]);
+    bytes.extend_from_slice(&0.0f32.to_le_bytes());
+    bytes.extend_from_slice(&[0; 16]);
+
+    let mut reader = FrameReader::new(bytes.as_slice()).expect("header should be read");
+    assert_eq!(reader.parameter_count(), u32::MAX as usize);
+    assert!(matches!(reader.read_frame(), Err(FrameRecordingError::UnexpectedEnd)));
+  }
+
+  #[test]
+  fn huge_drawable_count_fails_at_end_of_header() {
+    let mut bytes = header(0, 0, &[]);
+    let drawable_count_offset = bytes.len() - 4;
+    bytes[drawable_count_offset..].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(FrameReader::new(bytes.as_slice()), Err(FrameRecordingError::UnexpectedEnd)));
+  }
+
+  #[test]
+  fn truncated_frame() {
+    let mut bytes = header(2, 0, &[]);
+    bytes.extend_from_slice(&1.0f32.to_le_bytes());
+    bytes.extend_from_slice(&0.5f32.to_le_bytes());
+
+    let mut reader = FrameReader::new(bytes.as_slice()).expect("header should be read");
+    assert!(matches!(reader.read_frame(), Err(FrameRecordingError::UnexpectedEnd)));
+  }
+}
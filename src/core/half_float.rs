@@ -0,0 +1,41 @@
+//! Vertex data converted to half-precision floats, halving the size of uploads, e.g. on mobile and WebGL.
+//!
+//! ## Precision
+//! `f16` has an 11-bit significand, so the step between representable values is `2^-10` in `[1, 2)`,
+//! `2^-11` in `[0.5, 1)`, and so on. For reference:
+//! - **Positions** in model units are mostly within `[-2, 2]`. Near `±1`, positions are off by up to `2^-11` units,
+//!   i.e. about 1 pixel when drawn at 2048 pixels per unit. Converting positions to pixels first loses more, as the error grows with magnitude.
+//! - **UVs** in `[0, 1]` are off by at most `2^-12`, i.e. half a texel of a 2048-wide texture, which is usually acceptable.
+
+use half::f16;
+
+use super::base_types::{Vector2, DrawableIndex};
+use super::model_types::{Drawable, UvOrigin};
+use super::ModelDynamic;
+
+/// Converts `vectors` to pairs of `f16`, rounding to nearest. `out` is cleared first.
+pub fn write_f16_vectors(vectors: &[Vector2], out: &mut Vec<[f16; 2]>) {
+  out.clear();
+  out.extend(vectors.iter().map(|vector| [f16::from_f32(vector.x), f16::from_f32(vector.y)]));
+}
+
+impl ModelDynamic {
+  /// Like [`ModelDynamic::drawable_vertex_position_containers`] for a drawable, but converted to `f16`.
+  ///
+  /// `out` is cleared first. Returns the number of vertices written, or `None` if `index` is out of range.
+  /// See the [module documentation](self) for the precision.
+  pub fn write_drawable_vertex_positions_f16(&self, index: DrawableIndex, out: &mut Vec<[f16; 2]>) -> Option<usize> {
+    let positions = self.drawable_vertex_position_containers().get(index.as_usize())?;
+    write_f16_vectors(positions, out);
+    Some(positions.len())
+  }
+}
+
+impl Drawable {
+  /// Like [`Drawable::vertex_uvs_with_origin`], but converted to `f16`.
+  pub fn vertex_uvs_f16(&self, origin: UvOrigin) -> Vec<[f16; 2]> {
+    let mut uvs = Vec::new();
+    write_f16_vectors(&self.vertex_uvs_with_origin(origin), &mut uvs);
+    uvs
+  }
+}
@@ -0,0 +1,216 @@
+//! A rolling history of parameter values, for smoothing, velocity estimation and debugging graphs in tools.
+
+use super::base_types::ParameterIndex;
+use super::internal::platform_iface::PlatformModelDynamicInterface as _;
+use super::ModelDynamic;
+
+/// Keeps the parameter values of the last frames recorded, up to a capacity, overwriting the oldest ones.
+#[derive(Debug, Clone)]
+pub struct ParameterHistory {
+  parameter_count: usize,
+  /// Ring of frame times, in seconds.
+  times: Box<[f32]>,
+  /// Ring of frame-major parameter values, `parameter_count` per frame.
+  values: Box<[f32]>,
+  /// Ring index of the oldest frame.
+  start: usize,
+  len: usize,
+}
+impl ParameterHistory {
+  /// ## Panics
+  /// - If `capacity` is `0`.
+  pub fn new(parameter_count: usize, capacity: usize) -> Self {
+    assert!(capacity > 0, "Capacity must be positive");
+    Self {
+      parameter_count,
+      times: vec![0.0; capacity].into(),
+      values: vec![0.0; capacity * parameter_count].into(),
+      start: 0,
+      len: 0,
+    }
+  }
+
+  pub fn parameter_count(&self) -> usize {
+    self.parameter_count
+  }
+  /// Number of frames kept at most.
+  pub fn capacity(&self) -> usize {
+    self.times.len()
+  }
+  /// Number of frames kept.
+  pub fn len(&self) -> usize {
+    self.len
+  }
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+  pub fn clear(&mut self) {
+    self.start = 0;
+    self.len = 0;
+  }
+
+  /// Records the current parameter values of `dynamic` at `time` seconds, dropping the oldest frame if full.
+  ///
+  /// ## Panics
+  /// - If `time` is earlier than the latest frame.
+  /// - If `dynamic` does not have [`ParameterHistory::parameter_count`] parameters.
+  pub fn record(&mut self, time: f32, dynamic: &ModelDynamic) {
+    self.record_values(time, dynamic.parameter_values());
+  }
+  fn record_values(&mut self, time: f32, parameter_values: &[f32]) {
+    if let Some((latest_time, _)) = self.latest() {
+      assert!(latest_time <= time, "Frames must be recorded in chronological order. latest: {latest_time} given: {time}");
+    }
+    assert_eq!(parameter_values.len(), self.parameter_count, "Parameter count mismatch");
+
+    let slot = if self.len < self.capacity() {
+      self.len += 1;
+      (self.start + self.len - 1) % self.capacity()
+    } else {
+      let slot = self.start;
+      self.start = (self.start + 1) % self.capacity();
+      slot
+    };
+    self.times[slot] = time;
+    self.values[slot * self.parameter_count..(slot + 1) * self.parameter_count].copy_from_slice(parameter_values);
+  }
+
+  /// Gets the time and parameter values of a frame, `0` being the oldest kept.
+  pub fn frame(&self, index: usize) -> Option<(f32, &[f32])> {
+    if index >= self.len {
+      return None;
+    }
+    let slot = (self.start + index) % self.capacity();
+    Some((self.times[slot], &self.values[slot * self.parameter_count..(slot + 1) * self.parameter_count]))
+  }
+  /// Gets the most recently recorded frame.
+  pub fn latest(&self) -> Option<(f32, &[f32])> {
+    self.len.checked_sub(1).and_then(|index| self.frame(index))
+  }
+  /// Iterates the frames kept, oldest first.
+  pub fn frames(&self) -> impl Iterator<Item = (f32, &[f32])> + '_ {
+    (0..self.len).filter_map(|index| self.frame(index))
+  }
+
+  /// Iterates `(time, value)` of a parameter over the frames kept, oldest first.
+  ///
+  /// ## Panics
+  /// - If `parameter_index` is out of range.
+  pub fn track(&self, parameter_index: ParameterIndex) -> impl Iterator<Item = (f32, f32)> + '_ {
+    assert!(parameter_index.as_usize() < self.parameter_count, "Parameter index out of range: {parameter_index}");
+    self.frames().map(move |(time, values)| (time, values[parameter_index.as_usize()]))
+  }
+  /// Mean value of a parameter over the latest `frame_count` frames, or fewer if not as many are kept,
+  /// e.g. to smooth a jittery tracking input. `None` if empty.
+  ///
+  /// ## Panics
+  /// - If `parameter_index` is out of range.
+  pub fn mean(&self, parameter_index: ParameterIndex, frame_count: usize) -> Option<f32> {
+    let frame_count = frame_count.min(self.len);
+    if frame_count == 0 {
+      return None;
+    }
+    let sum: f32 = self.track(parameter_index).skip(self.len - frame_count).map(|(_, value)| value).sum();
+    Some(sum / frame_count as f32)
+  }
+
+  /// Estimates the velocity of every parameter, in units per second, from the latest two frames.
+  ///
+  /// `None` if fewer than two frames are kept, or if they have the same time.
+  pub fn velocities(&self) -> Option<Vec<f32>> {
+    self.velocities_with_spans(None)
+  }
+  /// Estimates the acceleration of every parameter, in units per second squared, from the latest three frames.
+  ///
+  /// `None` if fewer than three frames are kept, or if any two of them have the same time.
+  pub fn accelerations(&self) -> Option<Vec<f32>> {
+    self.accelerations_with_spans(None)
+  }
+
+  /// * `cyclic_spans` - Per parameter, the span of its range if cyclic, so that differences go the shorter way around.
+  pub(crate) fn velocities_with_spans(&self, cyclic_spans: Option<&[Option<f32>]>) -> Option<Vec<f32>> {
+    let latest = self.len.checked_sub(1)?;
+    self.velocities_between(latest.checked_sub(1)?, latest, cyclic_spans)
+  }
+  pub(crate) fn accelerations_with_spans(&self, cyclic_spans: Option<&[Option<f32>]>) -> Option<Vec<f32>> {
+    let latest = self.len.checked_sub(1)?;
+    let older_velocities = self.velocities_between(latest.checked_sub(2)?, latest - 1, cyclic_spans)?;
+    let newer_velocities = self.velocities_between(latest - 1, latest, cyclic_spans)?;
+
+    // Velocities are estimated at the midpoints between frames.
+    let (oldest_time, _) = self.frame(latest - 2)?;
+    let (newest_time, _) = self.frame(latest)?;
+    let dt = (newest_time - oldest_time) * 0.5;
+    Some(older_velocities.iter().zip(newer_velocities.iter()).map(|(older, newer)| (newer - older) / dt).collect())
+  }
+  fn velocities_between(&self, older: usize, newer: usize, cyclic_spans: Option<&[Option<f32>]>) -> Option<Vec<f32>> {
+    let (older_time, older_values) = self.frame(older)?;
+    let (newer_time, newer_values) = self.frame(newer)?;
+    let dt = newer_time - older_time;
+    if dt <= 0.0 {
+      return None;
+    }
+
+    Some(
+      older_values.iter().zip(newer_values.iter()).enumerate()
+        .map(|(index, (older_value, newer_value))| {
+          let mut difference = newer_value - older_value;
+          if let Some(span) = cyclic_spans.and_then(|cyclic_spans| cyclic_spans[index]) {
+            difference -= span * (difference / span).round();
+          }
+          difference / dt
+        })
+        .collect()
+    )
+  }
+}
+
+impl ModelDynamic {
+  /// Starts keeping the parameter values of the last `capacity` frames recorded with
+  /// [`ModelDynamic::record_parameter_history`], discarding any previous history.
+  ///
+  /// ## Panics
+  /// - If `capacity` is `0`.
+  pub fn enable_parameter_history(&mut self, capacity: usize) {
+    self.parameter_history = Some(ParameterHistory::new(self.parameter_values().len(), capacity));
+  }
+  pub fn disable_parameter_history(&mut self) {
+    self.parameter_history = None;
+  }
+  /// Gets the history, if enabled with [`ModelDynamic::enable_parameter_history`].
+  pub fn parameter_history(&self) -> Option<&ParameterHistory> {
+    self.parameter_history.as_ref()
+  }
+  /// Records the current parameter values at `time` seconds into the history, if enabled,
+  /// typically once per frame after parameters are driven.
+  ///
+  /// ## Panics
+  /// - If `time` is earlier than the latest frame.
+  pub fn record_parameter_history(&mut self, time: f32) {
+    let Self { inner, parameter_history, .. } = self;
+    if let Some(parameter_history) = parameter_history {
+      parameter_history.record_values(time, inner.parameter_values());
+    }
+  }
+
+  /// Estimates the velocity of every parameter, in units per second, from the latest two frames of the history,
+  /// e.g. for physics inputs or secondary motion.
+  ///
+  /// Cyclic parameters, see [`ModelDynamic::set_parameter_cyclic`], are assumed to have moved the shorter way around.
+  ///
+  /// `None` if the history is disabled, or as with [`ParameterHistory::velocities`].
+  pub fn parameter_velocities(&self) -> Option<Vec<f32>> {
+    self.parameter_history.as_ref()?.velocities_with_spans(Some(&self.parameter_cyclic_spans()))
+  }
+  /// Estimates the acceleration of every parameter, in units per second squared, from the latest three frames of the history.
+  ///
+  /// `None` if the history is disabled, or as with [`ParameterHistory::accelerations`].
+  pub fn parameter_accelerations(&self) -> Option<Vec<f32>> {
+    self.parameter_history.as_ref()?.accelerations_with_spans(Some(&self.parameter_cyclic_spans()))
+  }
+  fn parameter_cyclic_spans(&self) -> Vec<Option<f32>> {
+    self.parameter_cyclics.iter().zip(self.parameter_value_ranges.iter())
+      .map(|(&cyclic, &(minimum, maximum))| (cyclic && maximum > minimum).then_some(maximum - minimum))
+      .collect()
+  }
+}
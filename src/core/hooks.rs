@@ -0,0 +1,47 @@
+//! Callbacks run after each update of a model, for systems observing frames without owning the update loop.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use parking_lot::Mutex;
+
+use super::{ModelStatic, ModelDynamic};
+
+type PostUpdateHook = Box<dyn FnMut(&ModelStatic, &ModelDynamic) + Send>;
+
+/// Identifies a hook added with [`super::Model::add_post_update_hook`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PostUpdateHookId(u64);
+
+#[derive(Default)]
+pub(crate) struct PostUpdateHooks {
+  hooks: Mutex<Vec<(PostUpdateHookId, PostUpdateHook)>>,
+  next_id: AtomicU64,
+}
+impl PostUpdateHooks {
+  pub(crate) fn add(&self, hook: PostUpdateHook) -> PostUpdateHookId {
+    let id = PostUpdateHookId(self.next_id.fetch_add(1, Ordering::Relaxed));
+    self.hooks.lock().push((id, hook));
+    id
+  }
+  pub(crate) fn remove(&self, id: PostUpdateHookId) -> bool {
+    let mut hooks = self.hooks.lock();
+    let len = hooks.len();
+    hooks.retain(|(existing, _)| *existing != id);
+    hooks.len() != len
+  }
+
+  /// Calls the hooks in the order they were added.
+  pub(crate) fn run(&self, model_static: &ModelStatic, model_dynamic: &ModelDynamic) {
+    for (_, hook) in self.hooks.lock().iter_mut() {
+      hook(model_static, model_dynamic);
+    }
+  }
+}
+impl std::fmt::Debug for PostUpdateHooks {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("PostUpdateHooks")
+      // Not `lock`, as this may be formatted from within a hook.
+      .field("len", &self.hooks.try_lock().map(|hooks| hooks.len()))
+      .finish()
+  }
+}
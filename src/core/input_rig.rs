@@ -0,0 +1,252 @@
+//! Mapping of named inputs from tracking sources, e.g. `"head_yaw"` or `"smile"`, onto the parameters of a specific model.
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use super::base_types::ParameterIndex;
+use super::{ModelStatic, ModelDynamic};
+
+/// Errors generated by [`InputRigConfig`].
+#[derive(Debug, Error)]
+pub enum InputRigError {
+  #[error("Unsupported input rig version {0}. Latest supported is {latest}.", latest = InputRigConfig::VERSION)]
+  UnsupportedVersion(u32),
+  #[cfg(feature = "serde")]
+  #[error("Failed to parse input rig JSON: {0}")]
+  Json(#[from] serde_json::Error),
+}
+
+/// Shapes an input, typically normalized to `[-1, 1]`, into a parameter value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResponseCurve {
+  /// Inputs within `[-dead_zone, dead_zone]` map to `0`; the rest of the range is stretched to keep `±1` at `±1`.
+  pub dead_zone: f32,
+  /// Applied to the magnitude after the dead zone, e.g. above `1` for finer control around `0`.
+  pub exponent: f32,
+  pub scale: f32,
+  pub offset: f32,
+}
+impl Default for ResponseCurve {
+  fn default() -> Self {
+    Self {
+      dead_zone: 0.0,
+      exponent: 1.0,
+      scale: 1.0,
+      offset: 0.0,
+    }
+  }
+}
+impl ResponseCurve {
+  pub fn evaluate(&self, input: f32) -> f32 {
+    let magnitude = input.abs();
+    let magnitude = if magnitude <= self.dead_zone {
+      0.0
+    } else if self.dead_zone < 1.0 {
+      (magnitude - self.dead_zone) / (1.0 - self.dead_zone)
+    } else {
+      magnitude
+    };
+    magnitude.powf(self.exponent).copysign(input) * self.scale + self.offset
+  }
+}
+
+/// Drives the parameter with ID `parameter_id` from the input named `input` through `curve`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputBinding {
+  pub input: String,
+  pub parameter_id: String,
+  pub curve: ResponseCurve,
+}
+
+/// The bindings of an [`InputRig`], independent of any model.
+///
+/// Stored as JSON as e.g.:
+/// ```json
+/// {
+///   "Version": 1,
+///   "Bindings": [
+///     { "Input": "head_yaw", "Parameter": "ParamAngleX", "DeadZone": 0.05, "Scale": 30.0 },
+///     { "Input": "smile", "Parameter": "ParamMouthForm", "Exponent": 0.5 }
+///   ]
+/// }
+/// ```
+/// `DeadZone`, `Exponent`, `Scale` and `Offset` default to those of [`ResponseCurve::default`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputRigConfig {
+  pub bindings: Vec<InputBinding>,
+}
+impl InputRigConfig {
+  /// Current version of the JSON format.
+  pub const VERSION: u32 = 1;
+
+  #[cfg(feature = "serde")]
+  pub fn to_json(&self) -> String {
+    let json = json::InputRigJson {
+      version: Self::VERSION,
+      bindings: self.bindings.iter()
+        .map(|binding| json::Binding {
+          input: binding.input.clone(),
+          parameter: binding.parameter_id.clone(),
+          dead_zone: binding.curve.dead_zone,
+          exponent: binding.curve.exponent,
+          scale: binding.curve.scale,
+          offset: binding.curve.offset,
+        })
+        .collect(),
+    };
+    serde_json::to_string_pretty(&json).expect("Serializing an input rig should not fail")
+  }
+  #[cfg(feature = "serde")]
+  pub fn from_json(json: &str) -> Result<Self, InputRigError> {
+    let json: json::InputRigJson = serde_json::from_str(json)?;
+    if json.version == 0 || json.version > Self::VERSION {
+      return Err(InputRigError::UnsupportedVersion(json.version));
+    }
+
+    Ok(Self {
+      bindings: json.bindings.into_iter()
+        .map(|binding| InputBinding {
+          input: binding.input,
+          parameter_id: binding.parameter,
+          curve: ResponseCurve {
+            dead_zone: binding.dead_zone,
+            exponent: binding.exponent,
+            scale: binding.scale,
+            offset: binding.offset,
+          },
+        })
+        .collect(),
+    })
+  }
+}
+
+/// An [`InputRigConfig`] resolved against a model, holding the latest value of each input.
+#[derive(Debug, Clone)]
+pub struct InputRig {
+  /// Input index, parameter and curve per resolved binding.
+  bindings: Vec<(usize, ParameterIndex, ResponseCurve)>,
+  input_indices: HashMap<String, usize>,
+  inputs: Vec<Option<f32>>,
+  unmapped: Vec<String>,
+}
+impl InputRig {
+  /// Resolves the parameters of `config` in the model of `model_static`.
+  pub fn new(config: &InputRigConfig, model_static: &ModelStatic) -> Self {
+    let mut bindings = Vec::new();
+    let mut input_indices = HashMap::new();
+    let mut unmapped = Vec::new();
+
+    for binding in &config.bindings {
+      let next_input_index = input_indices.len();
+      let input_index = *input_indices.entry(binding.input.clone()).or_insert(next_input_index);
+      match model_static.parameter_index(&binding.parameter_id) {
+        Some(parameter_index) => bindings.push((input_index, parameter_index, binding.curve)),
+        None => unmapped.push(binding.parameter_id.clone()),
+      }
+    }
+
+    Self {
+      bindings,
+      inputs: vec![None; input_indices.len()],
+      input_indices,
+      unmapped,
+    }
+  }
+
+  /// IDs of the parameters in the config not found in the model, in config order.
+  pub fn unmapped(&self) -> &[String] {
+    &self.unmapped
+  }
+
+  /// Sets the latest value of an input. Returns `false` if no binding uses it.
+  pub fn set_input(&mut self, input: &str, value: f32) -> bool {
+    match self.input_indices.get(input) {
+      Some(&index) => {
+        self.inputs[index] = Some(value);
+        true
+      },
+      None => false,
+    }
+  }
+  /// Forgets the value of an input, e.g. when its tracking source is lost, so its parameters are left untouched.
+  pub fn clear_input(&mut self, input: &str) {
+    if let Some(&index) = self.input_indices.get(input) {
+      self.inputs[index] = None;
+    }
+  }
+
+  /// Writes the parameters of the bindings whose input has a value.
+  ///
+  /// Bindings onto the same parameter add up. Values are clamped into the ranges of the parameters.
+  pub fn apply(&self, model_static: &ModelStatic, dynamic: &mut ModelDynamic) {
+    let mut written: Vec<(ParameterIndex, f32)> = Vec::new();
+    for &(input_index, parameter_index, curve) in &self.bindings {
+      let Some(input) = self.inputs[input_index] else {
+        continue;
+      };
+      let value = curve.evaluate(input);
+      match written.iter_mut().find(|(index, _)| *index == parameter_index) {
+        Some((_, sum)) => *sum += value,
+        None => written.push((parameter_index, value)),
+      }
+    }
+    for (index, value) in written {
+      dynamic[index] = model_static[index].clamp_value(value);
+    }
+  }
+}
+
+/// Serialized form of the JSON input rig.
+#[cfg(feature = "serde")]
+mod json {
+  use serde::{Serialize, Deserialize};
+
+  #[derive(Debug, Serialize, Deserialize)]
+  #[serde(rename_all = "PascalCase")]
+  pub struct InputRigJson {
+    pub version: u32,
+    pub bindings: Vec<Binding>,
+  }
+
+  #[derive(Debug, Serialize, Deserialize)]
+  #[serde(rename_all = "PascalCase")]
+  pub struct Binding {
+    pub input: String,
+    pub parameter: String,
+    #[serde(default)]
+    pub dead_zone: f32,
+    #[serde(default = "one")]
+    pub exponent: f32,
+    #[serde(default = "one")]
+    pub scale: f32,
+    #[serde(default)]
+    pub offset: f32,
+  }
+
+  fn one() -> f32 {
+    1.0
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn response_curve() {
+    let curve = ResponseCurve { dead_zone: 0.2, exponent: 2.0, scale: 10.0, offset: 1.0 };
+    assert_eq!(curve.evaluate(0.1), 1.0);
+    assert_eq!(curve.evaluate(-0.2), 1.0);
+    assert_eq!(curve.evaluate(1.0), 11.0);
+    assert_eq!(curve.evaluate(-1.0), -9.0);
+    // Halfway through the stretched range, then squared, keeping the sign of the input.
+    assert!((curve.evaluate(0.6) - 3.5).abs() < 1e-5);
+    assert!((curve.evaluate(-0.6) + 1.5).abs() < 1e-5);
+
+    assert_eq!(ResponseCurve::default().evaluate(-0.3), -0.3);
+    // A dead zone of at least 1 leaves the magnitude unstretched past it.
+    let curve = ResponseCurve { dead_zone: 1.0, ..Default::default() };
+    assert_eq!((curve.evaluate(1.0), curve.evaluate(-1.5)), (0.0, -1.5));
+  }
+}
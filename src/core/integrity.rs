@@ -0,0 +1,123 @@
+//! Verification of moc bytes against a known SHA-256 digest, for models that are user-provided or downloaded.
+//!
+//! Set [`super::MocLoadOptions::expected_sha256`] to have a mismatch reported as [`super::MocError::IntegrityMismatch`]
+//! before the bytes ever reach _Live2D® Cubism SDK Core_, which does not handle corrupted mocs gracefully.
+
+/// A SHA-256 digest.
+pub type Sha256Digest = [u8; 32];
+
+/// Computes the SHA-256 digest of `bytes`, e.g. when publishing a model, to be checked on load later.
+pub fn sha256(bytes: &[u8]) -> Sha256Digest {
+  let mut state = H0;
+
+  let bit_len = (bytes.len() as u64).wrapping_mul(8);
+  let mut chunks = bytes.chunks_exact(64);
+  for chunk in &mut chunks {
+    compress(&mut state, chunk.try_into().unwrap());
+  }
+
+  // Padding: a single `1` bit, zeros, then the length in bits, to a multiple of 64 bytes.
+  let remainder = chunks.remainder();
+  let mut tail = [0u8; 128];
+  tail[..remainder.len()].copy_from_slice(remainder);
+  tail[remainder.len()] = 0x80;
+  let tail_len = if remainder.len() < 56 { 64 } else { 128 };
+  tail[tail_len - 8..tail_len].copy_from_slice(&bit_len.to_be_bytes());
+  for chunk in tail[..tail_len].chunks_exact(64) {
+    compress(&mut state, chunk.try_into().unwrap());
+  }
+
+  let mut digest = [0u8; 32];
+  for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+    bytes.copy_from_slice(&word.to_be_bytes());
+  }
+  digest
+}
+
+/// Formats `digest` as lowercase hexadecimal, as printed by `sha256sum`.
+pub fn to_hex(digest: &Sha256Digest) -> String {
+  digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Parses a digest from 64 hexadecimal digits, e.g. from a model manifest.
+pub fn from_hex(hex: &str) -> Option<Sha256Digest> {
+  if hex.len() != 64 || !hex.is_ascii() {
+    return None;
+  }
+  let mut digest = [0u8; 32];
+  for (byte, i) in digest.iter_mut().zip((0..64).step_by(2)) {
+    *byte = u8::from_str_radix(&hex[i..i + 2], 16).ok()?;
+  }
+  Some(digest)
+}
+
+const H0: [u32; 8] = [
+  0x6a09_e667, 0xbb67_ae85, 0x3c6e_f372, 0xa54f_f53a, 0x510e_527f, 0x9b05_688c, 0x1f83_d9ab, 0x5be0_cd19,
+];
+
+const K: [u32; 64] = [
+  0x428a_2f98, 0x7137_4491, 0xb5c0_fbcf, 0xe9b5_dba5, 0x3956_c25b, 0x59f1_11f1, 0x923f_82a4, 0xab1c_5ed5,
+  0xd807_aa98, 0x1283_5b01, 0x2431_85be, 0x550c_7dc3, 0x72be_5d74, 0x80de_b1fe, 0x9bdc_06a7, 0xc19b_f174,
+  0xe49b_69c1, 0xefbe_4786, 0x0fc1_9dc6, 0x240c_a1cc, 0x2de9_2c6f, 0x4a74_84aa, 0x5cb0_a9dc, 0x76f9_88da,
+  0x983e_5152, 0xa831_c66d, 0xb003_27c8, 0xbf59_7fc7, 0xc6e0_0bf3, 0xd5a7_9147, 0x06ca_6351, 0x1429_2967,
+  0x27b7_0a85, 0x2e1b_2138, 0x4d2c_6dfc, 0x5338_0d13, 0x650a_7354, 0x766a_0abb, 0x81c2_c92e, 0x9272_2c85,
+  0xa2bf_e8a1, 0xa81a_664b, 0xc24b_8b70, 0xc76c_51a3, 0xd192_e819, 0xd699_0624, 0xf40e_3585, 0x106a_a070,
+  0x19a4_c116, 0x1e37_6c08, 0x2748_774c, 0x34b0_bcb5, 0x391c_0cb3, 0x4ed8_aa4a, 0x5b9c_ca4f, 0x682e_6ff3,
+  0x748f_82ee, 0x78a5_636f, 0x84c8_7814, 0x8cc7_0208, 0x90be_fffa, 0xa450_6ceb, 0xbef9_a3f7, 0xc671_78f2,
+];
+
+fn compress(state: &mut [u32; 8], block: &[u8; 64]) {
+  let mut w = [0u32; 64];
+  for (word, bytes) in w.iter_mut().zip(block.chunks_exact(4)) {
+    *word = u32::from_be_bytes(bytes.try_into().unwrap());
+  }
+  for i in 16..64 {
+    let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+    let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+    w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+  }
+
+  let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+  for (&k, &w) in K.iter().zip(w.iter()) {
+    let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+    let ch = (e & f) ^ (!e & g);
+    let t1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(k).wrapping_add(w);
+    let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+    let maj = (a & b) ^ (a & c) ^ (b & c);
+    let t2 = s0.wrapping_add(maj);
+
+    h = g;
+    g = f;
+    f = e;
+    e = d.wrapping_add(t1);
+    d = c;
+    c = b;
+    b = a;
+    a = t1.wrapping_add(t2);
+  }
+
+  for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+    *word = word.wrapping_add(value);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn known_vectors() {
+    let vectors: &[(&[u8], &str)] = &[
+      (b"", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+      (b"abc", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+      // Padding fitting in the last block, just not fitting, and a full block.
+      (&[b'a'; 55], "9f4390f8d30c2dd92ec9f095b65e2b9ae9b0a925a5258e241c9f1e910f734318"),
+      (&[b'a'; 56], "b35439a4ac6f0948b6d6f9e3c6af0f5f590ce20f1bde7090ef7970686ec6738a"),
+      (&[b'a'; 64], "ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb"),
+    ];
+    for &(bytes, expected) in vectors {
+      assert_eq!(to_hex(&sha256(bytes)), expected, "length {}", bytes.len());
+      assert_eq!(from_hex(expected), Some(sha256(bytes)));
+    }
+  }
+}
@@ -1,10 +1,13 @@
-
 pub mod platform_iface;
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "mock")))]
 #[path = "internal/platform_impl_native.rs"]
 pub mod platform_impl;
 
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(target_arch = "wasm32", not(feature = "mock")))]
 #[path = "internal/platform_impl_web.rs"]
 pub mod platform_impl;
+
+#[cfg(feature = "mock")]
+#[path = "internal/platform_impl_mock.rs"]
+pub mod platform_impl;
@@ -1,16 +1,15 @@
 
 pub use crate::core::base_types::{Vector2, Vector4};
-pub use crate::core::base_types::{MocError, CubismVersion, MocVersion};
+pub use crate::core::base_types::{MocError, OutOfMemoryError, CubismVersion, MocVersion, MocLoadOptions};
 pub use crate::core::base_types::DrawableIndex;
 pub use crate::core::model_types::CanvasInfo;
 pub use crate::core::model_types::{ParameterType, Parameter};
 pub use crate::core::model_types::Part;
-pub use crate::core::model_types::{ConstantDrawableFlagSet, DynamicDrawableFlagSet, Drawable};
+pub use crate::core::model_types::{ConstantDrawableFlagSet, DynamicDrawableFlagSet, DrawableReadbackFlagSet, Drawable};
 
 pub trait PlatformCubismCoreInterface {
   type PlatformMoc;
 
-  #[cfg(not(target_arch = "wasm32"))]
   unsafe fn set_log_function<F>(f: F)
   where
     F: FnMut(&str) + Send + 'static;
@@ -18,14 +17,17 @@ pub trait PlatformCubismCoreInterface {
   fn version(&self) -> CubismVersion;
   fn latest_supported_moc_version(&self) -> MocVersion;
 
-  fn platform_moc_from_bytes(&self, bytes: &[u8]) -> Result<(MocVersion, Self::PlatformMoc), MocError>;
+  fn moc_version_from_bytes(&self, bytes: &[u8]) -> Result<MocVersion, MocError>;
+  fn platform_moc_from_bytes(&self, bytes: &[u8], options: &MocLoadOptions) -> Result<(MocVersion, Self::PlatformMoc), MocError>;
 }
 
 pub trait PlatformMocInterface {
   type PlatformModelStatic;
   type PlatformModelDynamic;
 
-  fn new_platform_model(&self) -> (Self::PlatformModelStatic, Self::PlatformModelDynamic);
+  fn new_platform_model(&self) -> Result<(Self::PlatformModelStatic, Self::PlatformModelDynamic), OutOfMemoryError>;
+  /// Resets the Core's state of a model created from this moc to its initial state, without reallocating.
+  fn reinitialize_platform_model(&self, platform_model_dynamic: &mut Self::PlatformModelDynamic);
 }
 
 pub trait PlatformModelStaticInterface {
@@ -51,6 +53,12 @@ pub trait PlatformModelDynamicInterface {
   fn drawable_screen_colors(&self) -> &[Vector4];
 
   fn update(&mut self);
+  /// Like [`PlatformModelDynamicInterface::update`], but may leave arrays not in `readback_mask` as they were,
+  /// where reading them back costs a copy.
+  fn update_with_readback_mask(&mut self, readback_mask: DrawableReadbackFlagSet) {
+    let _ = readback_mask;
+    self.update()
+  }
   fn reset_drawable_dynamic_flags(&mut self);
 }
 
@@ -0,0 +1,272 @@
+//! Synthetic implementation of the platform interface, for the `mock` feature. See [`crate::core::mock`].
+
+use parking_lot::Mutex;
+
+use super::platform_iface::{Vector2, Vector4};
+use super::platform_iface::{MocError, OutOfMemoryError, CubismVersion, MocVersion, MocLoadOptions};
+use super::platform_iface::{CanvasInfo, ParameterType, Parameter, Part, Drawable};
+use super::platform_iface::{ConstantDrawableFlagSet, DynamicDrawableFlagSet};
+use super::platform_iface::{PlatformCubismCoreInterface, PlatformMocInterface, PlatformModelStaticInterface, PlatformModelDynamicInterface};
+
+use crate::memory::RawSlices;
+
+use super::super::base_types::{TextureIndex, DrawableIndex};
+use super::super::model_types::DynamicDrawableFlags;
+use super::super::mock::MockMoc;
+
+/// Kept only so that [`PlatformCubismCoreInterface::set_log_function`] behaves as with the Core, which never logs here.
+static S_LOG_FUNCTION: Mutex<Option<LogFunction>> = Mutex::new(None);
+type LogFunction = Box<dyn FnMut(&str) + Send + 'static>;
+
+/// Corners of the unit quad of each drawable, relative to its center.
+const QUAD_CORNERS: [(f32, f32); 4] = [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)];
+const QUAD_TRIANGLE_INDICES: [u16; 6] = [0, 1, 2, 2, 3, 0];
+
+#[derive(Debug, Default)]
+pub struct PlatformCubismCore {
+  _private: (),
+}
+
+impl PlatformCubismCoreInterface for PlatformCubismCore {
+  type PlatformMoc = PlatformMoc;
+
+  unsafe fn set_log_function<F>(f: F)
+  where
+    F: FnMut(&str) + Send + 'static,
+  {
+    *S_LOG_FUNCTION.lock() = Some(Box::new(f));
+  }
+
+  fn version(&self) -> CubismVersion {
+    CubismVersion(0x0500_0000)
+  }
+  fn latest_supported_moc_version(&self) -> MocVersion {
+    MocVersion::Moc3_42
+  }
+
+  fn moc_version_from_bytes(&self, bytes: &[u8]) -> Result<MocVersion, MocError> {
+    MockMoc::from_bytes(bytes).map(|_| MocVersion::Moc3_42).ok_or(MocError::InvalidMoc)
+  }
+  fn platform_moc_from_bytes(&self, bytes: &[u8], _options: &MocLoadOptions) -> Result<(MocVersion, Self::PlatformMoc), MocError> {
+    let mock_moc = MockMoc::from_bytes(bytes).ok_or(MocError::InvalidMoc)?;
+    Ok((MocVersion::Moc3_42, PlatformMoc { mock_moc }))
+  }
+}
+
+#[derive(Debug)]
+pub struct PlatformMoc {
+  mock_moc: MockMoc,
+}
+
+impl PlatformMocInterface for PlatformMoc {
+  type PlatformModelStatic  = PlatformModelStatic;
+  type PlatformModelDynamic = PlatformModelDynamic;
+
+  fn new_platform_model(&self) -> Result<(Self::PlatformModelStatic, Self::PlatformModelDynamic), OutOfMemoryError> {
+    let MockMoc { parameter_count, part_count, drawable_count } = self.mock_moc;
+    let (parameter_count, part_count, drawable_count) = (parameter_count as usize, part_count as usize, drawable_count as usize);
+
+    let parameters = (0..parameter_count)
+      .map(|i| Parameter {
+        id: format!("MockParam{i}"),
+        ty: ParameterType::Normal,
+        value_range: (-1.0, 1.0),
+        default_value: 0.0,
+        keys: [-1.0, 0.0, 1.0].into(),
+      })
+      .collect();
+    let parts = (0..part_count)
+      .map(|i| Part {
+        id: format!("MockPart{i}"),
+        parent_part_index: None,
+      })
+      .collect();
+    let drawables = (0..drawable_count)
+      .map(|i| Drawable {
+        id: format!("MockDrawable{i}"),
+        index: DrawableIndex(i as u64),
+        constant_flagset: ConstantDrawableFlagSet::default(),
+        texture_index: TextureIndex(0),
+        masks: [].into(),
+        vertex_count: QUAD_CORNERS.len() as u32,
+        vertex_uvs: QUAD_CORNERS.iter().map(|&(x, y)| Vector2 { x: x + 0.5, y: y + 0.5 }).collect(),
+        triangle_indices: QUAD_TRIANGLE_INDICES.into(),
+        parent_part_index: i.checked_rem(part_count),
+      })
+      .collect();
+
+    let platform_model_static = PlatformModelStatic {
+      canvas_info: CanvasInfo {
+        size_in_pixels: (drawable_count.max(1) as f32 * 100.0, 100.0),
+        origin_in_pixels: (50.0, 50.0),
+        pixels_per_unit: 100.0,
+      },
+      parameters,
+      parts,
+      drawables,
+    };
+    let platform_model_dynamic = PlatformModelDynamic::new(&self.mock_moc);
+
+    Ok((platform_model_static, platform_model_dynamic))
+  }
+  fn reinitialize_platform_model(&self, platform_model_dynamic: &mut Self::PlatformModelDynamic) {
+    *platform_model_dynamic = PlatformModelDynamic::new(&self.mock_moc);
+  }
+}
+
+#[derive(Debug)]
+pub struct PlatformModelStatic {
+  canvas_info: CanvasInfo,
+  parameters: Box<[Parameter]>,
+  parts: Box<[Part]>,
+  drawables: Box<[Drawable]>,
+}
+
+impl PlatformModelStaticInterface for PlatformModelStatic {
+  fn canvas_info(&self) -> CanvasInfo {
+    self.canvas_info
+  }
+  fn parameters(&self) -> &[Parameter] {
+    &self.parameters
+  }
+  fn parts(&self) -> &[Part] {
+    &self.parts
+  }
+  fn drawables(&self) -> &[Drawable] {
+    &self.drawables
+  }
+  fn get_drawable(&self, index: DrawableIndex) -> Option<&Drawable> {
+    self.drawables.get(index.as_usize())
+  }
+}
+
+#[derive(Debug)]
+pub struct PlatformModelDynamic {
+  parameter_values: Box<[f32]>,
+  part_opacities: Box<[f32]>,
+  drawable_dynamic_flagsets: Box<[DynamicDrawableFlagSet]>,
+  drawable_draw_orders: Box<[i32]>,
+  drawable_render_orders: Box<[i32]>,
+  drawable_opacities: Box<[f32]>,
+  /// The vertex positions of every drawable in a row, from `Box::into_raw` and released on drop,
+  /// so that writing them does not invalidate `drawable_vertex_position_containers`.
+  drawable_vertex_positions: *mut [Vector2],
+  drawable_vertex_position_containers: RawSlices<Vector2>,
+  drawable_multiply_colors: Box<[Vector4]>,
+  drawable_screen_colors: Box<[Vector4]>,
+}
+
+// SAFETY: `drawable_vertex_positions` is owned, and only written through `&mut self`.
+unsafe impl Send for PlatformModelDynamic {}
+unsafe impl Sync for PlatformModelDynamic {}
+
+impl PlatformModelDynamic {
+  fn new(mock_moc: &MockMoc) -> Self {
+    let drawable_count = mock_moc.drawable_count as usize;
+
+    let drawable_vertex_positions: Box<[Vector2]> = (0..drawable_count)
+      .flat_map(|i| QUAD_CORNERS.iter().map(move |&(x, y)| Vector2 { x: x + i as f32, y }))
+      .collect();
+    let drawable_vertex_positions = Box::into_raw(drawable_vertex_positions);
+    // SAFETY: Each drawable has its own `QUAD_CORNERS.len()` vertices in the block, released only on drop.
+    let drawable_vertex_position_containers = unsafe {
+      let first = drawable_vertex_positions.cast::<Vector2>();
+      RawSlices::new((0..drawable_count).map(|i| (first.add(i * QUAD_CORNERS.len()).cast_const(), QUAD_CORNERS.len())))
+    };
+
+    Self {
+      parameter_values: vec![0.0; mock_moc.parameter_count as usize].into(),
+      part_opacities: vec![1.0; mock_moc.part_count as usize].into(),
+      drawable_dynamic_flagsets: vec![DynamicDrawableFlags::IsVisible.into(); drawable_count].into(),
+      drawable_draw_orders: (0..drawable_count as i32).collect(),
+      drawable_render_orders: (0..drawable_count as i32).collect(),
+      drawable_opacities: vec![1.0; drawable_count].into(),
+      drawable_vertex_positions,
+      drawable_vertex_position_containers,
+      drawable_multiply_colors: vec![Vector4 { x: 1.0, y: 1.0, z: 1.0, w: 1.0 }; drawable_count].into(),
+      drawable_screen_colors: vec![Vector4 { x: 0.0, y: 0.0, z: 0.0, w: 1.0 }; drawable_count].into(),
+    }
+  }
+}
+impl Drop for PlatformModelDynamic {
+  fn drop(&mut self) {
+    // SAFETY: From `Box::into_raw` in `PlatformModelDynamic::new`.
+    drop(unsafe { Box::from_raw(self.drawable_vertex_positions) });
+  }
+}
+
+impl PlatformModelDynamicInterface for PlatformModelDynamic {
+  fn parameter_values(&self) -> &[f32] {
+    &self.parameter_values
+  }
+  fn parameter_values_mut(&mut self) -> &mut [f32] {
+    &mut self.parameter_values
+  }
+  fn part_opacities(&self) -> &[f32] {
+    &self.part_opacities
+  }
+  fn part_opacities_mut(&mut self) -> &mut [f32] {
+    &mut self.part_opacities
+  }
+
+  fn drawable_dynamic_flagsets(&self) -> &[DynamicDrawableFlagSet] {
+    &self.drawable_dynamic_flagsets
+  }
+  fn drawable_draw_orders(&self) -> &[i32] {
+    &self.drawable_draw_orders
+  }
+  fn drawable_render_orders(&self) -> &[i32] {
+    &self.drawable_render_orders
+  }
+  fn drawable_opacities(&self) -> &[f32] {
+    &self.drawable_opacities
+  }
+  fn drawable_vertex_position_containers(&self) -> &[&[Vector2]] {
+    self.drawable_vertex_position_containers.as_slices()
+  }
+  fn drawable_multiply_colors(&self) -> &[Vector4] {
+    &self.drawable_multiply_colors
+  }
+  fn drawable_screen_colors(&self) -> &[Vector4] {
+    &self.drawable_screen_colors
+  }
+
+  fn update(&mut self) {
+    // SAFETY: Nothing borrowed from `drawable_vertex_position_containers` can be alive through `&mut self`.
+    let drawable_vertex_positions = unsafe { &mut *self.drawable_vertex_positions };
+    for (i, flagset) in self.drawable_dynamic_flagsets.iter_mut().enumerate() {
+      let offset = match self.parameter_values.len() {
+        0 => 0.0,
+        parameter_count => self.parameter_values[i % parameter_count],
+      };
+      let opacity = match self.part_opacities.len() {
+        0 => 1.0,
+        part_count => self.part_opacities[i % part_count],
+      };
+
+      let positions = &mut drawable_vertex_positions[i * QUAD_CORNERS.len()..(i + 1) * QUAD_CORNERS.len()];
+      if positions[0].y != QUAD_CORNERS[0].1 + offset {
+        for (position, &(_, y)) in positions.iter_mut().zip(QUAD_CORNERS.iter()) {
+          position.y = y + offset;
+        }
+        *flagset |= DynamicDrawableFlags::VertexPositionsDidChange;
+      }
+
+      if self.drawable_opacities[i] != opacity {
+        self.drawable_opacities[i] = opacity;
+        *flagset |= DynamicDrawableFlags::OpacityDidChange;
+      }
+      let visible = opacity > 0.0;
+      if flagset.contains(DynamicDrawableFlags::IsVisible) != visible {
+        *flagset ^= DynamicDrawableFlags::IsVisible;
+        *flagset |= DynamicDrawableFlags::VisibilityDidChange;
+      }
+    }
+  }
+  fn reset_drawable_dynamic_flags(&mut self) {
+    for flagset in self.drawable_dynamic_flagsets.iter_mut() {
+      *flagset &= DynamicDrawableFlags::IsVisible;
+    }
+  }
+}
+
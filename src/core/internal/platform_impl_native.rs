@@ -4,11 +4,14 @@ use std::sync::Arc;
 use static_assertions::{assert_eq_align, assert_eq_size};
 use parking_lot::Mutex;
 
-use crate::memory::AlignedStorage;
+use crate::memory::{AlignedStorage, RawSlice, RawSlices};
+use crate::core::allocator::{StorageAllocator, SystemStorageAllocator};
+#[cfg(feature = "debug-storage")]
+use crate::core::debug_storage::{StorageKind, StorageTracker};
 use crate::sys::*;
 
 use super::platform_iface::{Vector2, Vector4};
-use super::platform_iface::{MocError, CubismVersion, MocVersion};
+use super::platform_iface::{MocError, OutOfMemoryError, CubismVersion, MocVersion, MocLoadOptions};
 use super::platform_iface::{CanvasInfo, Parameter, Part, Drawable};
 use super::platform_iface::{ConstantDrawableFlagSet, DynamicDrawableFlagSet};
 use super::platform_iface::{PlatformCubismCoreInterface, PlatformMocInterface, PlatformModelStaticInterface, PlatformModelDynamicInterface};
@@ -54,9 +57,20 @@ impl Drop for LogFunctionTrampolineRaw {
 
 static mut S_LOG_FUNCTION_TRAMPOLINE_RAW: Mutex<Option<LogFunctionTrampolineRaw>> = Mutex::new(None);
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct PlatformCubismCore {
-  _private: (),
+  /// Allocates the memory blocks of mocs, and of the models created from them.
+  allocator: Arc<dyn StorageAllocator>,
+}
+impl Default for PlatformCubismCore {
+  fn default() -> Self {
+    Self::with_allocator(Arc::new(SystemStorageAllocator))
+  }
+}
+impl PlatformCubismCore {
+  pub fn with_allocator(allocator: Arc<dyn StorageAllocator>) -> Self {
+    Self { allocator }
+  }
 }
 
 impl PlatformCubismCoreInterface for PlatformCubismCore {
@@ -98,67 +112,117 @@ impl PlatformCubismCoreInterface for PlatformCubismCore {
     CubismVersion(unsafe { csmGetVersion() })
   }
   fn latest_supported_moc_version(&self) -> MocVersion {
-    unsafe { csmGetLatestMocVersion() }.try_into().unwrap()
+    MocVersion::from_raw(unsafe { csmGetLatestMocVersion() }).expect("csmGetLatestMocVersion should not return csmMocVersion_Unknown")
   }
 
-  fn platform_moc_from_bytes(&self, bytes: &[u8]) -> Result<(MocVersion, Self::PlatformMoc), MocError> {
-    const MOC_ALIGNMENT: usize = csmAlignofMoc as usize;
+  fn moc_version_from_bytes(&self, bytes: &[u8]) -> Result<MocVersion, MocError> {
+    let aligned_storage = new_moc_storage(bytes, &self.allocator)?;
 
-    let mut aligned_storage = AlignedStorage::new(bytes.len(), MOC_ALIGNMENT).unwrap();
-    aligned_storage.copy_from_slice(bytes);
+    get_moc_version(&aligned_storage)
+  }
+  fn platform_moc_from_bytes(&self, bytes: &[u8], options: &MocLoadOptions) -> Result<(MocVersion, Self::PlatformMoc), MocError> {
+    let mut aligned_storage = new_moc_storage(bytes, &self.allocator)?;
 
     let size_in_u32: u32 = bytes.len().try_into().expect("Size should fit in a u32");
 
-    let moc_version = unsafe {
-      csmGetMocVersion(aligned_storage.as_mut_ptr().cast(), size_in_u32)
-    };
-    let moc_version = MocVersion::try_from(moc_version).map_err(|_| MocError::InvalidMoc)?;
+    let moc_version = get_moc_version(&aligned_storage)?;
 
-    if self.latest_supported_moc_version() < moc_version {
-      return Err(MocError::UnsupportedMocVersion {
-        given: moc_version,
-        latest_supported: self.latest_supported_moc_version(),
-      });
+    let latest_supported_moc_version = self.latest_supported_moc_version();
+    if latest_supported_moc_version.raw() < moc_version.raw() {
+      if !options.allow_newer {
+        return Err(MocError::UnsupportedMocVersion {
+          given: moc_version,
+          latest_supported: latest_supported_moc_version,
+        });
+      }
+      log::warn!(
+        "Attempting to revive a moc newer than supported. given: {} ({}) latest supported: {} ({})",
+        moc_version, moc_version.raw(), latest_supported_moc_version, latest_supported_moc_version.raw()
+      );
     }
 
     let csm_moc = unsafe {
       csmReviveMocInPlace(aligned_storage.as_mut_ptr().cast(), size_in_u32)
     };
+    if csm_moc.is_null() {
+      return Err(MocError::InvalidMoc);
+    }
 
     Ok(
       (moc_version,
       PlatformMoc {
         csm_moc,
+        #[cfg(feature = "debug-storage")]
+        _tracker: StorageTracker::new(StorageKind::Moc, aligned_storage.len()),
         moc_storage: Arc::new(aligned_storage),
+        allocator: Arc::clone(&self.allocator),
       })
     )
   }
 }
 
+/// Copies `bytes` into a memory block aligned as required for a `csmMoc`.
+fn new_moc_storage(bytes: &[u8], allocator: &Arc<dyn StorageAllocator>) -> Result<AlignedStorage, MocError> {
+  const MOC_ALIGNMENT: usize = csmAlignofMoc as usize;
+
+  if bytes.is_empty() {
+    return Err(MocError::InvalidMoc);
+  }
+
+  let mut aligned_storage = AlignedStorage::new(bytes.len(), MOC_ALIGNMENT, allocator)?;
+  aligned_storage.copy_from_slice(bytes);
+  Ok(aligned_storage)
+}
+
+fn get_moc_version(aligned_storage: &AlignedStorage) -> Result<MocVersion, MocError> {
+  let size_in_u32: u32 = aligned_storage.len().try_into().expect("Size should fit in a u32");
+
+  let moc_version = unsafe {
+    csmGetMocVersion(aligned_storage.as_ptr().cast(), size_in_u32)
+  };
+  MocVersion::from_raw(moc_version).ok_or(MocError::InvalidMoc)
+}
+
 #[derive(Debug)]
 pub struct PlatformMoc {
   csm_moc: *mut csmMoc,
   /// This is an [`Arc`] because the memory block for a `csmMoc` needs to outlive
   /// the memory blocks for all `csmModel`s generated from it.
   moc_storage: Arc<AlignedStorage>,
+  /// Allocates the memory blocks of models created from this moc.
+  allocator: Arc<dyn StorageAllocator>,
+  /// Every model keeps its moc alive, so this tracks the moc storage for as long as it is reachable.
+  #[cfg(feature = "debug-storage")]
+  _tracker: StorageTracker,
 }
 
 // SAFETY: The underlying `csmMoc` is never mutated.
 unsafe impl Send for PlatformMoc {}
 unsafe impl Sync for PlatformMoc {}
 
+impl PlatformMoc {
+  pub fn csm_moc(&self) -> *mut csmMoc {
+    self.csm_moc
+  }
+  /// Size in bytes of the memory block for a `csmModel` of this moc.
+  pub fn model_storage_size(&self) -> u32 {
+    unsafe {
+      csmGetSizeofModel(self.csm_moc)
+    }
+  }
+}
+
 impl PlatformMocInterface for PlatformMoc {
   type PlatformModelStatic  = PlatformModelStatic;
   type PlatformModelDynamic = PlatformModelDynamic;
 
-  fn new_platform_model(&self) -> (Self::PlatformModelStatic, Self::PlatformModelDynamic) {
+  fn new_platform_model(&self) -> Result<(Self::PlatformModelStatic, Self::PlatformModelDynamic), OutOfMemoryError> {
     const MODEL_ALIGNMENT: usize = csmAlignofModel as usize;
 
-    let storage_size = unsafe {
-      csmGetSizeofModel(self.csm_moc)
-    };
+    let storage_size = self.model_storage_size();
 
-    let mut csm_model_storage = AlignedStorage::new(storage_size as _, MODEL_ALIGNMENT).unwrap();
+    // Zeroed, as the Core may leave padding uninitialized, which MUST NOT be read through `AlignedStorage`'s `Deref`.
+    let mut csm_model_storage = AlignedStorage::new_zeroed(storage_size as _, MODEL_ALIGNMENT, &self.allocator)?;
 
     let csm_model = unsafe {
       csmInitializeModelInPlace(self.csm_moc, csm_model_storage.as_mut_ptr().cast(), storage_size)
@@ -318,7 +382,9 @@ impl PlatformMocInterface for PlatformMoc {
     let drawable_count = drawables.len();
 
     let model_storage = Arc::new(ModelStorage {
-      _csm_model_storage: csm_model_storage,
+      #[cfg(feature = "debug-storage")]
+      _tracker: StorageTracker::new(StorageKind::Model, csm_model_storage.len()),
+      csm_model_storage,
       csm_model,
       _moc_storage: Arc::clone(&self.moc_storage),
     });
@@ -329,34 +395,52 @@ impl PlatformMocInterface for PlatformMoc {
       parts,
       drawables,
 
-      _model_storage: Arc::clone(&model_storage),
+      model_storage: Arc::clone(&model_storage),
     };
 
+    // SAFETY: `csm_model` is behind an `Arc` owned by `PlatformModelDynamic` along with the slices.
+    // The Core never writes the arrays read only through `RawSlice::as_slice`.
     let platform_model_dynamic = PlatformModelDynamic {
-       // SAFETY: `csm_model` is behind an `Arc` we own.
-      parameter_values: unsafe { std::slice::from_raw_parts_mut(csmGetParameterValues(csm_model), parameter_count) },
-      part_opactities: unsafe { std::slice::from_raw_parts_mut(csmGetPartOpacities(csm_model), part_count) },
-      drawable_dynamic_flagsets: unsafe { std::slice::from_raw_parts(csmGetDrawableDynamicFlags(csm_model).cast(), drawable_count) },
-      drawable_draw_orders: unsafe { std::slice::from_raw_parts(csmGetDrawableDrawOrders(csm_model), drawable_count) },
-      drawable_render_orders: unsafe { std::slice::from_raw_parts(csmGetDrawableRenderOrders(csm_model), drawable_count) },
-      drawable_opacities: unsafe { std::slice::from_raw_parts(csmGetDrawableOpacities(csm_model), drawable_count) },
-      vertex_position_containers: unsafe {
-        VertexPositionContainers::new(csm_model)
-      },
-      drawable_multiply_colors: unsafe { std::slice::from_raw_parts(csmGetDrawableMultiplyColors(csm_model).cast(), drawable_count) },
-      drawable_screen_colors: unsafe { std::slice::from_raw_parts(csmGetDrawableScreenColors(csm_model).cast(), drawable_count) },
+      parameter_values: unsafe { RawSlice::new(csmGetParameterValues(csm_model), parameter_count) },
+      part_opactities: unsafe { RawSlice::new(csmGetPartOpacities(csm_model), part_count) },
+      drawable_dynamic_flagsets: unsafe { RawSlice::new(csmGetDrawableDynamicFlags(csm_model).cast_mut().cast(), drawable_count) },
+      drawable_draw_orders: unsafe { RawSlice::new(csmGetDrawableDrawOrders(csm_model).cast_mut(), drawable_count) },
+      drawable_render_orders: unsafe { RawSlice::new(csmGetDrawableRenderOrders(csm_model).cast_mut(), drawable_count) },
+      drawable_opacities: unsafe { RawSlice::new(csmGetDrawableOpacities(csm_model).cast_mut(), drawable_count) },
+      vertex_position_containers: unsafe { vertex_position_containers(csm_model) },
+      drawable_multiply_colors: unsafe { RawSlice::new(csmGetDrawableMultiplyColors(csm_model).cast_mut().cast(), drawable_count) },
+      drawable_screen_colors: unsafe { RawSlice::new(csmGetDrawableScreenColors(csm_model).cast_mut().cast(), drawable_count) },
 
       platform_model: Arc::clone(&model_storage),
     };
 
-    (platform_model_static, platform_model_dynamic)
+    Ok((platform_model_static, platform_model_dynamic))
+  }
+  fn reinitialize_platform_model(&self, platform_model_dynamic: &mut Self::PlatformModelDynamic) {
+    let model_storage = &platform_model_dynamic.platform_model;
+    let storage_size: u32 = model_storage.csm_model_storage.len().try_into().expect("Size should fit in a u32");
+
+    // SAFETY: Only `PlatformModelDynamic` reads or writes the memory block after initialization, and we hold it mutably.
+    // `PlatformModelStatic` holds copies. Initializing into the same address lays the model out identically,
+    // so the slices in `platform_model_dynamic` remain valid.
+    let csm_model = unsafe {
+      csmInitializeModelInPlace(self.csm_moc, model_storage.csm_model_storage.as_raw_mut_ptr().cast(), storage_size)
+    };
+    assert_eq!(csm_model, model_storage.csm_model, "Model should be reinitialized at the same address");
+
+    // SAFETY: `csm_model` is behind an `Arc` we own.
+    unsafe {
+      platform_model_dynamic.vertex_position_containers = vertex_position_containers(csm_model);
+    }
   }
 }
 
 #[derive(Debug)]
 struct ModelStorage {
+  #[cfg(feature = "debug-storage")]
+  _tracker: StorageTracker,
   /// Where `csm_model` is instantiated. Needs to outlive any reference obtained through `csm_model`.
-  _csm_model_storage: AlignedStorage,
+  csm_model_storage: AlignedStorage,
   /// Points inside `csm_model_storage`.
   csm_model: *mut csmModel,
 
@@ -376,7 +460,12 @@ pub struct PlatformModelStatic {
   drawables: Box<[Drawable]>,
 
   /// Above members all reference the memory block inside this, which needs to outlive them.
-  _model_storage: Arc<ModelStorage>,
+  model_storage: Arc<ModelStorage>,
+}
+impl PlatformModelStatic {
+  pub fn csm_model(&self) -> *mut csmModel {
+    self.model_storage.csm_model
+  }
 }
 
 impl PlatformModelStaticInterface for PlatformModelStatic {
@@ -399,17 +488,18 @@ impl PlatformModelStaticInterface for PlatformModelStatic {
 
 #[derive(Debug)]
 pub struct PlatformModelDynamic {
-  parameter_values: &'static mut [f32],
-  part_opactities: &'static mut [f32],
-  drawable_dynamic_flagsets: &'static [DynamicDrawableFlagSet],
-  drawable_draw_orders: &'static [i32],
-  drawable_render_orders: &'static [i32],
-  drawable_opacities: &'static [f32],
-  vertex_position_containers: VertexPositionContainers<'static>,
-  drawable_multiply_colors: &'static [Vector4],
-  drawable_screen_colors: &'static [Vector4],
-
-  /// Above members all reference the memory block inside this, which needs to outlive them.
+  parameter_values: RawSlice<f32>,
+  part_opactities: RawSlice<f32>,
+  drawable_dynamic_flagsets: RawSlice<DynamicDrawableFlagSet>,
+  drawable_draw_orders: RawSlice<i32>,
+  drawable_render_orders: RawSlice<i32>,
+  drawable_opacities: RawSlice<f32>,
+  vertex_position_containers: RawSlices<Vector2>,
+  drawable_multiply_colors: RawSlice<Vector4>,
+  drawable_screen_colors: RawSlice<Vector4>,
+
+  /// Above members all point into the memory block inside this, which needs to outlive them.
+  /// They are only ever borrowed through `&self` or `&mut self`.
   platform_model: Arc<ModelStorage>,
 }
 
@@ -419,38 +509,38 @@ unsafe impl Sync for PlatformModelDynamic {}
 
 impl PlatformModelDynamicInterface for PlatformModelDynamic {
   fn parameter_values(&self) -> &[f32] {
-    self.parameter_values
+    self.parameter_values.as_slice()
   }
   fn parameter_values_mut(&mut self) -> &mut [f32] {
-    self.parameter_values
+    self.parameter_values.as_mut_slice()
   }
   fn part_opacities(&self) -> &[f32] {
-    self.part_opactities
+    self.part_opactities.as_slice()
   }
   fn part_opacities_mut(&mut self) -> &mut [f32] {
-    self.part_opactities
+    self.part_opactities.as_mut_slice()
   }
 
   fn drawable_dynamic_flagsets(&self) -> &[DynamicDrawableFlagSet] {
-    self.drawable_dynamic_flagsets
+    self.drawable_dynamic_flagsets.as_slice()
   }
   fn drawable_draw_orders(&self) -> &[i32] {
-    self.drawable_draw_orders
+    self.drawable_draw_orders.as_slice()
   }
   fn drawable_render_orders(&self) -> &[i32] {
-    self.drawable_render_orders
+    self.drawable_render_orders.as_slice()
   }
   fn drawable_opacities(&self) -> &[f32] {
-    self.drawable_opacities
+    self.drawable_opacities.as_slice()
   }
   fn drawable_vertex_position_containers(&self) -> &[&[Vector2]] {
-    &self.vertex_position_containers.inner
+    self.vertex_position_containers.as_slices()
   }
   fn drawable_multiply_colors(&self) -> &[Vector4] {
-    self.drawable_multiply_colors
+    self.drawable_multiply_colors.as_slice()
   }
   fn drawable_screen_colors(&self) -> &[Vector4] {
-    self.drawable_screen_colors
+    self.drawable_screen_colors.as_slice()
   }
 
   fn update(&mut self) {
@@ -460,7 +550,7 @@ impl PlatformModelDynamicInterface for PlatformModelDynamic {
 
     // SAFETY: `csm_model` is behind an `Arc` we own.
     unsafe {
-      self.vertex_position_containers = VertexPositionContainers::new(self.platform_model.csm_model);
+      self.vertex_position_containers = vertex_position_containers(self.platform_model.csm_model);
     }
   }
   fn reset_drawable_dynamic_flags(&mut self) {
@@ -470,30 +560,18 @@ impl PlatformModelDynamicInterface for PlatformModelDynamic {
   }
 }
 
-#[derive(Debug)]
-struct VertexPositionContainers<'a> {
-  inner: Box<[&'a [Vector2]]>,
-}
-impl<'a> VertexPositionContainers<'a> {
-  /// ## Safety
-  /// - `csm_model` MUST be valid for lifetime `'a`.
-  unsafe fn new(csm_model: *mut csmModel) -> Self {
-    Self {
-      inner: unsafe {
-        let drawable_count: usize = csmGetDrawableCount(csm_model).try_into().unwrap();
-
-        let vertex_counts = std::slice::from_raw_parts(csmGetDrawableVertexCounts(csm_model), drawable_count);
-        let vertex_position_ptrs = std::slice::from_raw_parts(csmGetDrawableVertexPositions(csm_model), drawable_count);
-
-        itertools::izip!(vertex_counts, vertex_position_ptrs)
-          .map(|(&vertex_count, &vertex_position_ptr)| {
-            let vertex_count: usize = vertex_count.try_into().unwrap();
-            std::slice::from_raw_parts(vertex_position_ptr.cast::<Vector2>(), vertex_count)
-          })
-          .collect()
-      }
-    }
-  }
+/// ## Safety
+/// - `csm_model` MUST be valid for as long as the result is used.
+unsafe fn vertex_position_containers(csm_model: *mut csmModel) -> RawSlices<Vector2> {
+  let drawable_count: usize = csmGetDrawableCount(csm_model).try_into().unwrap();
+
+  let vertex_counts = std::slice::from_raw_parts(csmGetDrawableVertexCounts(csm_model), drawable_count);
+  let vertex_position_ptrs = std::slice::from_raw_parts(csmGetDrawableVertexPositions(csm_model), drawable_count);
+
+  RawSlices::new(
+    itertools::izip!(vertex_counts, vertex_position_ptrs)
+      .map(|(&vertex_count, &vertex_position_ptr)| (vertex_position_ptr.cast::<Vector2>(), vertex_count.try_into().unwrap()))
+  )
 }
 
 unsafe fn to_string(c_str_ptr: *const std::os::raw::c_char) -> String {
@@ -1,13 +1,21 @@
 
 use std::sync::Arc;
 
+use flagset::FlagSet;
+use parking_lot::Mutex;
+
 use super::platform_iface::{Vector2, Vector4};
-use super::platform_iface::{MocError, CubismVersion, MocVersion};
+use super::platform_iface::{MocError, OutOfMemoryError, CubismVersion, MocVersion, MocLoadOptions};
 use super::platform_iface::DrawableIndex;
 use super::platform_iface::{CanvasInfo, Parameter, Part, Drawable};
-use super::platform_iface::DynamicDrawableFlagSet;
+use super::platform_iface::{DynamicDrawableFlagSet, DrawableReadbackFlagSet};
 use super::platform_iface::{PlatformCubismCoreInterface, PlatformMocInterface, PlatformModelStaticInterface, PlatformModelDynamicInterface};
 
+/// The user log function, shared by every `Live2DCubismCore` instance since each of them
+/// forwards its `Live2DCubismCore.Logging` messages here.
+static S_LOG_FUNCTION: Mutex<Option<LogFunction>> = Mutex::new(None);
+type LogFunction = Box<dyn FnMut(&str) + Send + 'static>;
+
 #[derive(Debug, Default)]
 pub struct PlatformCubismCore {
   js_cubism_core: Arc<JsLive2DCubismCore>,
@@ -16,6 +24,13 @@ pub struct PlatformCubismCore {
 impl PlatformCubismCoreInterface for PlatformCubismCore {
   type PlatformMoc = PlatformMoc;
 
+  unsafe fn set_log_function<F>(f: F)
+  where
+    F: FnMut(&str) + Send + 'static,
+  {
+    *S_LOG_FUNCTION.lock() = Some(Box::new(f));
+  }
+
   fn version(&self) -> CubismVersion {
     self.js_cubism_core.cubism_version
   }
@@ -23,7 +38,10 @@ impl PlatformCubismCoreInterface for PlatformCubismCore {
     self.js_cubism_core.latest_supported_moc_version
   }
 
-  fn platform_moc_from_bytes(&self, bytes: &[u8]) -> Result<(MocVersion, self::PlatformMoc), MocError> {
+  fn moc_version_from_bytes(&self, bytes: &[u8]) -> Result<MocVersion, MocError> {
+    self.js_cubism_core.moc_version_from_bytes(bytes).ok_or(MocError::InvalidMoc)
+  }
+  fn platform_moc_from_bytes(&self, bytes: &[u8], _options: &MocLoadOptions) -> Result<(MocVersion, self::PlatformMoc), MocError> {
     let array = js_sys::Uint8Array::new_with_length(bytes.len().try_into().unwrap());
     array.copy_from(bytes);
 
@@ -46,11 +64,18 @@ pub struct PlatformMoc {
   js_cubism_core: Arc<JsLive2DCubismCore>,
 }
 
+impl Drop for PlatformMoc {
+  fn drop(&mut self) {
+    // Every `Model` keeps its `Moc` alive, so no `Live2DCubismCore.Model` created from this remains.
+    self.js_cubism_core.release_moc(&self.js_moc);
+  }
+}
+
 impl PlatformMocInterface for PlatformMoc {
   type PlatformModelStatic  = PlatformModelStatic;
   type PlatformModelDynamic = PlatformModelDynamic;
 
-  fn new_platform_model(&self) -> (Self::PlatformModelStatic, Self::PlatformModelDynamic) {
+  fn new_platform_model(&self) -> Result<(Self::PlatformModelStatic, Self::PlatformModelDynamic), OutOfMemoryError> {
     let js_model = self.js_cubism_core.js_model_from_moc(&self.js_moc);
 
     let canvas_info = js_model.canvas_info;
@@ -69,7 +94,10 @@ impl PlatformMocInterface for PlatformMoc {
       js_model,
     };
 
-    (platform_model_static, platform_model_dynamic)
+    Ok((platform_model_static, platform_model_dynamic))
+  }
+  fn reinitialize_platform_model(&self, platform_model_dynamic: &mut Self::PlatformModelDynamic) {
+    self.js_cubism_core.reinitialize_js_model(&self.js_moc, &mut platform_model_dynamic.js_model);
   }
 }
 
@@ -141,7 +169,10 @@ impl PlatformModelDynamicInterface for PlatformModelDynamic {
   }
 
   fn update(&mut self) {
-    self.js_model.update()
+    self.js_model.update(FlagSet::full())
+  }
+  fn update_with_readback_mask(&mut self, readback_mask: DrawableReadbackFlagSet) {
+    self.js_model.update(readback_mask)
   }
   fn reset_drawable_dynamic_flags(&mut self) {
     self.js_model.reset_drawable_dynamic_flags()
@@ -154,7 +185,9 @@ use js::*;
 mod js {
   const LIVE2DCUBISMCORE_JS_STR: &str = include_str!(concat!(env!("LIVE2D_CUBISM_SDK_WEB_DIR"), "/Core/live2dcubismcore.min.js"));
 
+  use flagset::FlagSet;
   use wasm_bindgen::JsCast as _;
+  use wasm_bindgen::closure::Closure;
 
   use crate::core;
   use core::base_types::{TextureIndex, DrawableIndex};
@@ -174,6 +207,8 @@ mod js {
     moc_class: wasm_bindgen::JsValue,
     /// The `Live2DCubismCore.Moc.fromArrayBuffer` static method.
     from_array_buffer_method: js_sys::Function,
+    /// `Live2DCubismCore.Moc._release` method.
+    moc_release_method: js_sys::Function,
 
     /// The `Live2DCubismCore.Model` class object.
     model_class: wasm_bindgen::JsValue,
@@ -182,6 +217,9 @@ mod js {
 
     /// `Live2DCubismCore.Drawables.resetDynamicFlags` method.
     reset_dynamic_flags_method: js_sys::Function,
+
+    /// The handler passed to `Live2DCubismCore.Logging.csmSetLogFunction`, which needs to outlive its registration.
+    _log_function_closure: Closure<dyn FnMut(String)>,
   }
 
   #[derive(Debug)]
@@ -276,13 +314,14 @@ mod js {
       };
       let latest_supported_moc_version = {
         let csmGetLatestMocVersion = get_member_function(&version_class, "csmGetLatestMocVersion");
-        core::MocVersion::try_from(csmGetLatestMocVersion.call0(&version_class).unwrap().as_f64().unwrap() as u32).unwrap()
+        core::MocVersion::from_raw(csmGetLatestMocVersion.call0(&version_class).unwrap().as_f64().unwrap() as u32).unwrap()
       };
 
       let csmGetMocVersion = get_member_function(&version_class, "csmGetMocVersion");
 
       let moc_class = get_member_value(&live2d_cubism_core_namespace, "Moc");
       let from_array_buffer_method = get_member_function(&moc_class, "fromArrayBuffer");
+      let moc_release_method = get_member_function(&get_member_value(&moc_class, "prototype"), "_release");
 
       let model_class = get_member_value(&live2d_cubism_core_namespace, "Model");
       let from_moc_method = get_member_function(&model_class, "fromMoc");
@@ -291,6 +330,24 @@ mod js {
       let prototype = get_member_value(&drawables_class, "prototype");
       let reset_dynamic_flags_method = get_member_function(&prototype, "resetDynamicFlags");
 
+      let log_function_closure = {
+        let logging_class = get_member_value(&live2d_cubism_core_namespace, "Logging");
+        let csmSetLogFunction = get_member_function(&logging_class, "csmSetLogFunction");
+
+        let closure = Closure::wrap(Box::new(|message: String| {
+          // Taken out for the call, so that the lock is not held if it sets a log function or logs itself.
+          let log_function = super::S_LOG_FUNCTION.lock().take();
+          if let Some(mut log_function) = log_function {
+            log_function(&message);
+            // Put back, unless replaced during the call.
+            super::S_LOG_FUNCTION.lock().get_or_insert(log_function);
+          }
+        }) as Box<dyn FnMut(String)>);
+        csmSetLogFunction.call1(&logging_class, closure.as_ref()).unwrap();
+
+        closure
+      };
+
       Self {
         cubism_version,
         latest_supported_moc_version,
@@ -300,11 +357,14 @@ mod js {
 
         moc_class,
         from_array_buffer_method,
+        moc_release_method,
 
         model_class,
         from_moc_method,
 
         reset_dynamic_flags_method,
+
+        _log_function_closure: log_function_closure,
       }
     }
   }
@@ -318,13 +378,32 @@ mod js {
         return None;
       }
 
-      let version = self.get_moc_version(&moc_instance, &array_buffer);
+      let version = self.get_moc_version(&moc_instance, &array_buffer)?;
 
       Some(JsMoc {
         version,
         moc_instance,
       })
     }
+    /// Equivalent to `csmGetMocVersion` on bytes, through a temporary `Live2DCubismCore.Moc`.
+    pub fn moc_version_from_bytes(&self, bytes: &[u8]) -> Option<core::MocVersion> {
+      let array = js_sys::Uint8Array::new_with_length(bytes.len().try_into().unwrap());
+      array.copy_from(bytes);
+      let array_buffer = array.buffer();
+
+      let moc_instance = self.from_array_buffer_method.call1(&self.moc_class, array_buffer.as_ref()).unwrap();
+      if moc_instance.is_null() {
+        return None;
+      }
+
+      let version = self.get_moc_version(&moc_instance, &array_buffer);
+      self.moc_release_method.call0(&moc_instance).unwrap();
+      version
+    }
+    /// Calls `Live2DCubismCore.Moc.prototype._release` on the moc.
+    pub fn release_moc(&self, moc: &JsMoc) {
+      self.moc_release_method.call0(&moc.moc_instance).unwrap();
+    }
     #[allow(dead_code)]
     pub fn moc_from_bytes(&self, bytes: &[u8]) -> Option<JsMoc> {
       let array = js_sys::Uint8Array::new_with_length(bytes.len().try_into().unwrap());
@@ -334,15 +413,27 @@ mod js {
     }
 
     /// Equivalent to `csmGetMocVersion`.
-    pub fn get_moc_version(&self, js_moc_instance: &wasm_bindgen::JsValue, array_buffer: &js_sys::ArrayBuffer) -> core::MocVersion {
+    pub fn get_moc_version(&self, js_moc_instance: &wasm_bindgen::JsValue, array_buffer: &js_sys::ArrayBuffer) -> Option<core::MocVersion> {
       let moc_version = self.csmGetMocVersion.call2(
         &self.version_class, js_moc_instance, array_buffer.as_ref()
       )
       .unwrap().as_f64().unwrap() as u32;
-      core::MocVersion::try_from(moc_version).unwrap()
+      core::MocVersion::from_raw(moc_version)
     }
 
     pub fn js_model_from_moc(&self, moc: &JsMoc) -> JsModel {
+      self.js_model_from_moc_with_scratch(moc, None)
+    }
+    /// Replaces the `Live2DCubismCore.Model` of `model` with a new one from `moc`, reusing its scratch buffer.
+    ///
+    /// `moc` MUST be the one `model` was created from.
+    pub fn reinitialize_js_model(&self, moc: &JsMoc, model: &mut JsModel) {
+      let scratch = std::mem::take(&mut model.scratch);
+      // Drops the previous `JsModel`, which releases its `Live2DCubismCore.Model`.
+      *model = self.js_model_from_moc_with_scratch(moc, Some(scratch));
+    }
+    /// `scratch`, if any, MUST come from a model of `moc`.
+    fn js_model_from_moc_with_scratch(&self, moc: &JsMoc, scratch: Option<Scratch>) -> JsModel {
       let model_instance = self.from_moc_method.call1(&self.moc_class, moc.moc_instance.as_ref()).unwrap();
 
       let prototype = get_member_value(&self.model_class, "prototype");
@@ -371,7 +462,13 @@ mod js {
         get_member_value(&model_instance, "drawables")
       );
 
-      let scratch = Scratch::new(&parameters, &parts, &drawables);
+      let scratch = match scratch {
+        Some(mut scratch) => {
+          scratch.reload_from(&parameters, &parts, &drawables);
+          scratch
+        },
+        None => Scratch::new(&parameters, &parts, &drawables),
+      };
 
       JsModel {
         canvas_info,
@@ -389,14 +486,16 @@ mod js {
   }
 
   impl JsModel {
-    pub fn update(&mut self) {
+    /// * `readback_mask` - Arrays to copy back into the scratch buffer.
+    pub fn update(&mut self, readback_mask: core::DrawableReadbackFlagSet) {
       self.scratch.store_into(&self.parameters, &self.parts, &self.drawables);
       self.update_method.call0(&self.model_instance).unwrap();
-      self.scratch.load_from(&self.drawables);
+      self.scratch.load_from(&self.drawables, readback_mask);
     }
     pub fn reset_drawable_dynamic_flags(&mut self) {
       self.drawables.reset_dynamic_flags_method.call0(&self.drawables.drawables_instance).unwrap();
-      self.scratch.load_from(&self.drawables);
+      // Only the flags are changed by the Core.
+      self.scratch.load_dynamic_flags_from(&self.drawables);
     }
   }
   impl Drop for JsModel {
@@ -596,7 +695,7 @@ mod js {
   }
 
   /// Scratch buffer for dynamic values.
-  #[derive(Debug)]
+  #[derive(Debug, Default)]
   pub struct Scratch {
     parameter_values: Box<[f32]>,
     part_opacities: Box<[f32]>,
@@ -672,23 +771,41 @@ mod js {
         drawables.dynamic_flags.copy_from(src);
       }
     }
+    /// Like [`Scratch::new`], but overwriting the existing buffers, which MUST have the same sizes.
+    fn reload_from(&mut self, parameters: &JsParameters, parts: &JsParts, drawables: &JsDrawables) {
+      f32_array_overwrite_slice(&mut self.parameter_values, &parameters.values);
+      f32_array_overwrite_slice(&mut self.part_opacities, &parts.opacities);
+      self.load_from(drawables, FlagSet::full());
+    }
     fn load_dynamic_flags_from(&mut self, drawables: &JsDrawables) {
       uint8_array_overwrite_slice(&mut self.drawable_dynamic_flagsets, &drawables.dynamic_flags);
     }
-    fn load_from(&mut self, drawables: &JsDrawables) {
+    fn load_from(&mut self, drawables: &JsDrawables, readback_mask: core::DrawableReadbackFlagSet) {
       self.load_dynamic_flags_from(drawables);
 
-      int32_array_overwrite_slice(&mut self.drawable_draw_orders, &drawables.draw_orders);
-      int32_array_overwrite_slice(&mut self.drawable_render_orders, &drawables.render_orders);
-      f32_array_overwrite_slice(&mut self.drawable_opacities, &drawables.opacities);
+      if readback_mask.contains(core::DrawableReadbackFlags::DrawOrders) {
+        int32_array_overwrite_slice(&mut self.drawable_draw_orders, &drawables.draw_orders);
+      }
+      if readback_mask.contains(core::DrawableReadbackFlags::RenderOrders) {
+        int32_array_overwrite_slice(&mut self.drawable_render_orders, &drawables.render_orders);
+      }
+      if readback_mask.contains(core::DrawableReadbackFlags::Opacities) {
+        f32_array_overwrite_slice(&mut self.drawable_opacities, &drawables.opacities);
+      }
 
-      for (vertex_position_container, f32_array) in itertools::izip!(self.drawable_vertex_position_containers.iter_mut(), drawables.vertex_positions.iter()) {
-        let f32_array = f32_array.dyn_into::<js_sys::Float32Array>().unwrap();
-        f32_array_overwrite_slice(vertex_position_container, &f32_array);
+      if readback_mask.contains(core::DrawableReadbackFlags::VertexPositions) {
+        for (vertex_position_container, f32_array) in itertools::izip!(self.drawable_vertex_position_containers.iter_mut(), drawables.vertex_positions.iter()) {
+          let f32_array = f32_array.dyn_into::<js_sys::Float32Array>().unwrap();
+          f32_array_overwrite_slice(vertex_position_container, &f32_array);
+        }
       }
 
-      f32_array_overwrite_slice(&mut self.drawable_multiply_colors, &drawables.multiply_colors);
-      f32_array_overwrite_slice(&mut self.drawable_screen_colors, &drawables.screen_colors);
+      if readback_mask.contains(core::DrawableReadbackFlags::MultiplyColors) {
+        f32_array_overwrite_slice(&mut self.drawable_multiply_colors, &drawables.multiply_colors);
+      }
+      if readback_mask.contains(core::DrawableReadbackFlags::ScreenColors) {
+        f32_array_overwrite_slice(&mut self.drawable_screen_colors, &drawables.screen_colors);
+      }
     }
   }
 
@@ -0,0 +1,282 @@
+//! Opening the mouth of a model in sync with audio.
+
+use super::animator::AnimationStage;
+use super::base_types::ParameterIndex;
+use super::ModelDynamic;
+
+/// Loudness of audio over time, as the RMS of fixed-length windows of samples.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AmplitudeEnvelope {
+  /// Windows per second.
+  frame_rate: f32,
+  values: Vec<f32>,
+}
+impl AmplitudeEnvelope {
+  /// Computes the envelope of interleaved `samples` in `[-1, 1]`, with `frame_rate` windows per second.
+  ///
+  /// As in the official samples of _Live2D Cubism SDK_, the RMS covers the samples of all channels within each window.
+  ///
+  /// ## Panics
+  /// - If `channel_count`, `sample_rate` or `frame_rate` is zero or negative.
+  pub fn from_samples(samples: &[f32], channel_count: usize, sample_rate: u32, frame_rate: f32) -> Self {
+    assert!(channel_count > 0 && sample_rate > 0 && frame_rate > 0.0, "Channel count, sample rate and frame rate must be positive");
+
+    let window_len = (((sample_rate as f32 / frame_rate).round() as usize).max(1)) * channel_count;
+    let values = samples.chunks(window_len)
+      .map(|window| (window.iter().map(|sample| sample * sample).sum::<f32>() / window.len() as f32).sqrt())
+      .collect();
+    Self {
+      frame_rate,
+      values,
+    }
+  }
+
+  pub fn frame_rate(&self) -> f32 {
+    self.frame_rate
+  }
+  pub fn values(&self) -> &[f32] {
+    &self.values
+  }
+  /// In seconds.
+  pub fn duration(&self) -> f32 {
+    if self.frame_rate > 0.0 {
+      self.values.len() as f32 / self.frame_rate
+    } else {
+      0.0
+    }
+  }
+
+  /// Samples the envelope at `time` seconds, linearly interpolating between windows. `0` outside of the audio.
+  pub fn value_at(&self, time: f32) -> f32 {
+    if time < 0.0 || self.values.is_empty() {
+      return 0.0;
+    }
+    // Each value is attributed to the center of its window.
+    let position = (time * self.frame_rate - 0.5).max(0.0);
+    let index = position as usize;
+    match (self.values.get(index), self.values.get(index + 1)) {
+      (Some(a), Some(b)) => a + (b - a) * position.fract(),
+      (Some(a), None) if time <= self.duration() => *a,
+      _ => 0.0,
+    }
+  }
+}
+
+/// Drives the lip sync parameters of a model from an [`AmplitudeEnvelope`], as an [`AnimationStage`].
+///
+/// The parameters are typically those in the `LipSync` group of the `.model3.json`, usually just `ParamMouthOpenY`.
+#[derive(Debug, Clone)]
+pub struct LipSync {
+  envelope: AmplitudeEnvelope,
+  parameter_indices: Vec<ParameterIndex>,
+  time: f32,
+  /// Multiplies the amplitude before clamping to `[0, 1]`.
+  pub gain: f32,
+  /// Blends the mouth opening into the current value, `1` replacing it.
+  pub weight: f32,
+}
+impl LipSync {
+  pub fn new(envelope: AmplitudeEnvelope, parameter_indices: Vec<ParameterIndex>) -> Self {
+    Self {
+      envelope,
+      parameter_indices,
+      time: 0.0,
+      gain: 1.0,
+      weight: 1.0,
+    }
+  }
+
+  pub fn envelope(&self) -> &AmplitudeEnvelope {
+    &self.envelope
+  }
+  /// Position in the audio, in seconds.
+  pub fn time(&self) -> f32 {
+    self.time
+  }
+  /// Jumps to `time` seconds into the audio, e.g. to align it with the motion it accompanies.
+  ///
+  /// A negative time delays the start of the audio.
+  pub fn seek(&mut self, time: f32) {
+    self.time = time;
+  }
+
+  /// Mouth opening at the current position, in `[0, 1]`.
+  pub fn mouth_opening(&self) -> f32 {
+    (self.envelope.value_at(self.time) * self.gain).clamp(0.0, 1.0)
+  }
+}
+impl AnimationStage for LipSync {
+  fn advance(&mut self, dt: f32, dynamic: &mut ModelDynamic) {
+    self.time += dt;
+
+    let opening = self.mouth_opening();
+    for &index in &self.parameter_indices {
+      let current = dynamic[index];
+      dynamic[index] = current + (opening - current) * self.weight;
+    }
+  }
+  fn is_finished(&self) -> bool {
+    self.time > self.envelope.duration()
+  }
+}
+
+/// A viseme starting at some time, e.g. as reported by a text-to-speech engine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisemeEvent {
+  /// In seconds.
+  pub time: f32,
+  /// Name of the viseme, as used in the [`VisemeShapeTable`], e.g. `"a"` or `"sil"`.
+  pub viseme: String,
+}
+
+/// The mouth shape of each viseme, as values of parameters by ID, e.g. `ParamMouthOpenY` and `ParamMouthForm`.
+///
+/// Parameters used by some visemes but not by others are at `0` for the others.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VisemeShapeTable {
+  shapes: Vec<(String, Vec<(String, f32)>)>,
+}
+impl VisemeShapeTable {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Japanese vowels (`"a"`, `"i"`, `"u"`, `"e"`, `"o"`) and silence (`"sil"`) on `ParamMouthOpenY` and `ParamMouthForm`.
+  pub fn vowels() -> Self {
+    let mut table = Self::new();
+    for (viseme, open, form) in [("a", 1.0, 0.0), ("i", 0.3, 1.0), ("u", 0.3, -1.0), ("e", 0.6, 0.5), ("o", 0.8, -0.7), ("sil", 0.0, 0.0)] {
+      table.insert(viseme, [("ParamMouthOpenY".to_owned(), open), ("ParamMouthForm".to_owned(), form)]);
+    }
+    table
+  }
+
+  /// Sets the shape of `viseme`, replacing any existing one.
+  pub fn insert(&mut self, viseme: impl Into<String>, parameters: impl IntoIterator<Item = (String, f32)>) {
+    let viseme = viseme.into();
+    let parameters = parameters.into_iter().collect();
+    match self.shapes.iter_mut().find(|(existing, _)| *existing == viseme) {
+      Some((_, existing)) => *existing = parameters,
+      None => self.shapes.push((viseme, parameters)),
+    }
+  }
+  pub fn get(&self, viseme: &str) -> Option<&[(String, f32)]> {
+    self.shapes.iter()
+      .find(|(existing, _)| existing == viseme)
+      .map(|(_, parameters)| parameters.as_slice())
+  }
+}
+
+/// Drives mouth parameters from a timeline of visemes, crossfading between consecutive shapes, as an [`AnimationStage`].
+#[derive(Debug, Clone)]
+pub struct VisemeLipSync {
+  /// Sorted by time.
+  events: Vec<VisemeEvent>,
+  /// Parameters used by any shape of the table.
+  parameter_indices: Vec<ParameterIndex>,
+  /// Per viseme, a value per element of `parameter_indices`.
+  shapes: Vec<(String, Box<[f32]>)>,
+  time: f32,
+  /// Duration of the crossfade into each viseme, in seconds.
+  pub crossfade_time: f32,
+}
+impl VisemeLipSync {
+  /// Resolves the parameters of `table` in the model of `model_static`. Parameters not in the model are ignored.
+  pub fn new(mut events: Vec<VisemeEvent>, table: &VisemeShapeTable, model_static: &super::ModelStatic) -> Self {
+    events.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+    let mut parameter_indices = Vec::new();
+    for (_, parameters) in &table.shapes {
+      for (id, _) in parameters {
+        if let Some(index) = model_static.parameter_index(id) {
+          if !parameter_indices.contains(&index) {
+            parameter_indices.push(index);
+          }
+        }
+      }
+    }
+    let shapes = table.shapes.iter()
+      .map(|(viseme, parameters)| {
+        let values = parameter_indices.iter()
+          .map(|&index| {
+            parameters.iter()
+              .find(|(id, _)| model_static.parameter_index(id) == Some(index))
+              .map_or(0.0, |&(_, value)| value)
+          })
+          .collect();
+        (viseme.clone(), values)
+      })
+      .collect();
+
+    Self {
+      events,
+      parameter_indices,
+      shapes,
+      time: 0.0,
+      crossfade_time: 0.06,
+    }
+  }
+
+  /// Position in the timeline, in seconds.
+  pub fn time(&self) -> f32 {
+    self.time
+  }
+  pub fn seek(&mut self, time: f32) {
+    self.time = time;
+  }
+
+  fn shape(&self, viseme: &str) -> Option<&[f32]> {
+    self.shapes.iter()
+      .find(|(existing, _)| existing == viseme)
+      .map(|(_, values)| &values[..])
+  }
+}
+impl AnimationStage for VisemeLipSync {
+  fn advance(&mut self, dt: f32, dynamic: &mut ModelDynamic) {
+    self.time += dt;
+
+    // The latest started event, and the one before it to crossfade from.
+    let current = self.events.partition_point(|event| event.time <= self.time);
+    let Some(current_event) = current.checked_sub(1).map(|index| &self.events[index]) else {
+      return;
+    };
+    let previous_shape = current.checked_sub(2).and_then(|index| self.shape(&self.events[index].viseme));
+    let current_shape = self.shape(&current_event.viseme);
+
+    let weight = if self.crossfade_time > 0.0 {
+      ((self.time - current_event.time) / self.crossfade_time).clamp(0.0, 1.0)
+    } else {
+      1.0
+    };
+    for (i, &index) in self.parameter_indices.iter().enumerate() {
+      let from = previous_shape.map_or(0.0, |shape| shape[i]);
+      let to = current_shape.map_or(0.0, |shape| shape[i]);
+      dynamic[index] = from + (to - from) * weight;
+    }
+  }
+  fn is_finished(&self) -> bool {
+    self.events.last().is_none_or(|event| self.time > event.time + self.crossfade_time)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn envelope_value_at() {
+    // Two windows of two mono samples each.
+    let envelope = AmplitudeEnvelope::from_samples(&[1.0, -1.0, 0.5, 0.5], 1, 4, 2.0);
+    assert_eq!(envelope.values(), [1.0, 0.5]);
+    assert_eq!(envelope.duration(), 1.0);
+
+    assert_eq!(envelope.value_at(-0.01), 0.0);
+    assert_eq!(envelope.value_at(0.0), 1.0);
+    assert_eq!(envelope.value_at(0.25), 1.0);
+    assert_eq!(envelope.value_at(0.5), 0.75);
+    assert_eq!(envelope.value_at(0.75), 0.5);
+    assert_eq!(envelope.value_at(1.0), 0.5);
+    assert_eq!(envelope.value_at(1.01), 0.0);
+
+    assert_eq!(AmplitudeEnvelope::default().value_at(0.0), 0.0);
+  }
+}
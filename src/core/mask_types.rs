@@ -0,0 +1,236 @@
+//! Mask dependency graph of a _Live2D®_ model, as needed by a renderer's clipping pass.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use super::base_types::DrawableIndex;
+use super::model_types::{ConstantDrawableFlags, Drawable};
+
+/// A drawable masked by a [`MaskGroup`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaskConsumer {
+  pub drawable_index: DrawableIndex,
+  /// Whether the drawable is visible where the masks are _not_ drawn (i.e. `IsInvertedMask`).
+  pub is_inverted: bool,
+}
+
+/// A unique set of mask drawables, shared by one or more consumers.
+#[derive(Debug, Clone)]
+pub struct MaskGroup {
+  masks: Box<[DrawableIndex]>,
+  consumers: Box<[MaskConsumer]>,
+}
+impl MaskGroup {
+  /// Indices to the drawables making up the mask, sorted and deduplicated.
+  pub fn masks(&self) -> &[DrawableIndex] {
+    &self.masks
+  }
+  /// Drawables masked by this group, in ascending index order.
+  pub fn consumers(&self) -> &[MaskConsumer] {
+    &self.consumers
+  }
+}
+
+/// Mask relationships between the drawables of a model.
+#[derive(Debug, Clone)]
+pub struct MaskGroups {
+  groups: Box<[MaskGroup]>,
+  /// Index into `groups` for each drawable, if it is masked.
+  group_indices: Box<[Option<usize>]>,
+  mask_drawable_indices: Box<[DrawableIndex]>,
+  topological_order: Box<[DrawableIndex]>,
+}
+impl MaskGroups {
+  /// Builds the mask dependency graph of `drawables`.
+  ///
+  /// Mask lists are compared as sets, so lists differing only in order or duplicates share a [`MaskGroup`].
+  /// Mask indices out of the range of `drawables` are ignored.
+  pub fn from_drawables(drawables: &[Drawable]) -> Self {
+    let drawable_count = drawables.len();
+
+    let mut groups: Vec<(Box<[DrawableIndex]>, Vec<MaskConsumer>)> = Vec::new();
+    let mut group_index_by_masks: HashMap<Box<[DrawableIndex]>, usize> = HashMap::new();
+    let mut group_indices = vec![None; drawable_count].into_boxed_slice();
+
+    for drawable in drawables {
+      let mut masks: Vec<_> = drawable.masks().iter()
+        .copied()
+        .filter(|&mask| mask < drawable_count)
+        .map(DrawableIndex::from)
+        .collect();
+      if masks.is_empty() {
+        continue;
+      }
+      masks.sort();
+      masks.dedup();
+      let masks = masks.into_boxed_slice();
+
+      let group_index = *group_index_by_masks.entry(masks.clone()).or_insert_with(|| {
+        groups.push((masks, Vec::new()));
+        groups.len() - 1
+      });
+
+      groups[group_index].1.push(MaskConsumer {
+        drawable_index: drawable.index(),
+        is_inverted: drawable.constant_flagset().contains(ConstantDrawableFlags::IsInvertedMask),
+      });
+      group_indices[drawable.index().as_usize()] = Some(group_index);
+    }
+
+    let mut mask_drawable_indices: Vec<_> = groups.iter()
+      .flat_map(|(masks, _)| masks.iter().copied())
+      .collect();
+    mask_drawable_indices.sort();
+    mask_drawable_indices.dedup();
+
+    let topological_order = topological_order(drawable_count, &groups, &group_indices);
+
+    let groups = groups.into_iter()
+      .map(|(masks, consumers)| MaskGroup {
+        masks,
+        consumers: consumers.into_boxed_slice(),
+      })
+      .collect();
+
+    Self {
+      groups,
+      group_indices,
+      mask_drawable_indices: mask_drawable_indices.into_boxed_slice(),
+      topological_order,
+    }
+  }
+
+  /// Unique mask groups, in order of first use by ascending drawable index.
+  pub fn groups(&self) -> &[MaskGroup] {
+    &self.groups
+  }
+  /// The group masking the drawable at `drawable_index`, if any.
+  pub fn group_of(&self, drawable_index: DrawableIndex) -> Option<&MaskGroup> {
+    self.group_indices
+      .get(drawable_index.as_usize())
+      .copied()
+      .flatten()
+      .map(|group_index| &self.groups[group_index])
+  }
+  /// Indices to all drawables used as a mask by at least one other drawable, sorted.
+  pub fn mask_drawable_indices(&self) -> &[DrawableIndex] {
+    &self.mask_drawable_indices
+  }
+  /// Whether the drawable at `drawable_index` is used as a mask.
+  pub fn is_mask(&self, drawable_index: DrawableIndex) -> bool {
+    self.mask_drawable_indices.binary_search(&drawable_index).is_ok()
+  }
+  /// All drawables ordered so that masks come before the drawables they mask.
+  ///
+  /// Ties are broken by ascending drawable index.
+  /// Should the graph contain a cycle, the drawables involved are appended in ascending index order.
+  pub fn topological_order(&self) -> &[DrawableIndex] {
+    &self.topological_order
+  }
+}
+
+/// Kahn's algorithm over the edges "mask -> maskee".
+fn topological_order(
+  drawable_count: usize,
+  groups: &[(Box<[DrawableIndex]>, Vec<MaskConsumer>)],
+  group_indices: &[Option<usize>],
+) -> Box<[DrawableIndex]> {
+  let mut maskees: Vec<Vec<usize>> = vec![Vec::new(); drawable_count];
+  let mut in_degrees = vec![0usize; drawable_count];
+  for (maskee, group_index) in group_indices.iter().enumerate() {
+    if let Some(group_index) = group_index {
+      for mask in groups[*group_index].0.iter() {
+        maskees[mask.as_usize()].push(maskee);
+        in_degrees[maskee] += 1;
+      }
+    }
+  }
+
+  let mut ready: BinaryHeap<Reverse<usize>> = in_degrees.iter()
+    .enumerate()
+    .filter(|(_, &in_degree)| in_degree == 0)
+    .map(|(index, _)| Reverse(index))
+    .collect();
+
+  let mut order = Vec::with_capacity(drawable_count);
+  let mut visited = vec![false; drawable_count];
+  while let Some(Reverse(index)) = ready.pop() {
+    order.push(DrawableIndex::from(index));
+    visited[index] = true;
+
+    for &maskee in maskees[index].iter() {
+      in_degrees[maskee] -= 1;
+      if in_degrees[maskee] == 0 {
+        ready.push(Reverse(maskee));
+      }
+    }
+  }
+
+  if order.len() < drawable_count {
+    log::warn!("Mask dependency cycle detected among {} drawables", drawable_count - order.len());
+    order.extend((0..drawable_count).filter(|&index| !visited[index]).map(DrawableIndex::from));
+  }
+
+  order.into_boxed_slice()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::core::base_types::TextureIndex;
+
+  fn drawable(index: usize, masks: &[usize], is_inverted: bool) -> Drawable {
+    Drawable {
+      id: format!("Drawable{index}"),
+      index: DrawableIndex::from(index),
+      constant_flagset: if is_inverted { ConstantDrawableFlags::IsInvertedMask.into() } else { Default::default() },
+      texture_index: TextureIndex::from(0),
+      masks: masks.into(),
+      vertex_count: 0,
+      vertex_uvs: Box::new([]),
+      triangle_indices: Box::new([]),
+      parent_part_index: None,
+    }
+  }
+
+  #[test]
+  fn grouping() {
+    let drawables = [
+      drawable(0, &[], false),
+      drawable(1, &[], false),
+      drawable(2, &[1, 0], false),
+      drawable(3, &[0, 1, 1], true),
+      drawable(4, &[1, 99], false),
+      drawable(5, &[99], false),
+    ];
+    let mask_groups = MaskGroups::from_drawables(&drawables);
+
+    let indices = |indices: &[usize]| indices.iter().copied().map(DrawableIndex::from).collect::<Vec<_>>();
+    assert_eq!(mask_groups.groups().len(), 2);
+    assert_eq!(mask_groups.groups()[0].masks(), indices(&[0, 1]));
+    assert_eq!(mask_groups.groups()[0].consumers(), [
+      MaskConsumer { drawable_index: DrawableIndex::from(2), is_inverted: false },
+      MaskConsumer { drawable_index: DrawableIndex::from(3), is_inverted: true },
+    ]);
+    assert_eq!(mask_groups.groups()[1].masks(), indices(&[1]));
+
+    assert!(mask_groups.group_of(DrawableIndex::from(0)).is_none());
+    assert!(mask_groups.group_of(DrawableIndex::from(5)).is_none());
+    assert_eq!(mask_groups.mask_drawable_indices(), indices(&[0, 1]));
+    assert!(mask_groups.is_mask(DrawableIndex::from(1)) && !mask_groups.is_mask(DrawableIndex::from(2)));
+  }
+
+  #[test]
+  fn topological_order_with_cycle() {
+    let drawables = [
+      drawable(0, &[2], false),
+      drawable(1, &[], false),
+      drawable(2, &[1], false),
+      drawable(3, &[4], false),
+      drawable(4, &[3], false),
+    ];
+    let mask_groups = MaskGroups::from_drawables(&drawables);
+    let order: Vec<_> = mask_groups.topological_order().iter().map(|index| index.as_usize()).collect();
+    assert_eq!(order, [1, 2, 0, 3, 4]);
+  }
+}
@@ -0,0 +1,176 @@
+//! Driving a model from the output of MediaPipe `FaceLandmarker`, e.g. webcam tracking on the web.
+//!
+//! Only the data is consumed here; running MediaPipe itself is up to the application.
+
+use super::standard_params;
+use super::{ModelStatic, ModelDynamic};
+
+/// The parts of a MediaPipe `FaceLandmarkerResult` used for a single face.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FaceLandmarkerFrame<'a> {
+  /// `facialTransformationMatrixes[i].data`, a column-major 4x4 matrix. Enable `outputFacialTransformationMatrixes` to get it.
+  pub facial_transformation_matrix: Option<&'a [f32; 16]>,
+  /// `faceBlendshapes[i].categories` as `(categoryName, score)`. Enable `outputFaceBlendshapes` to get them.
+  pub blendshapes: &'a [(&'a str, f32)],
+}
+impl FaceLandmarkerFrame<'_> {
+  fn blendshape(&self, name: &str) -> f32 {
+    self.blendshapes.iter()
+      .find(|(category_name, _)| *category_name == name)
+      .map_or(0.0, |&(_, score)| score)
+  }
+}
+
+/// A standard parameter driven by [`MediaPipeFaceAdapter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+  AngleX,
+  AngleY,
+  AngleZ,
+  EyeLOpen,
+  EyeROpen,
+  EyeBallX,
+  EyeBallY,
+  BrowLY,
+  BrowRY,
+  MouthOpenY,
+  MouthForm,
+}
+impl Target {
+  const ALL: [Self; 11] = [
+    Self::AngleX, Self::AngleY, Self::AngleZ,
+    Self::EyeLOpen, Self::EyeROpen, Self::EyeBallX, Self::EyeBallY,
+    Self::BrowLY, Self::BrowRY,
+    Self::MouthOpenY, Self::MouthForm,
+  ];
+
+  fn parameter_id(self) -> &'static str {
+    match self {
+      Self::AngleX => standard_params::ANGLE_X,
+      Self::AngleY => standard_params::ANGLE_Y,
+      Self::AngleZ => standard_params::ANGLE_Z,
+      Self::EyeLOpen => standard_params::EYE_L_OPEN,
+      Self::EyeROpen => standard_params::EYE_R_OPEN,
+      Self::EyeBallX => standard_params::EYE_BALL_X,
+      Self::EyeBallY => standard_params::EYE_BALL_Y,
+      Self::BrowLY => standard_params::BROW_L_Y,
+      Self::BrowRY => standard_params::BROW_R_Y,
+      Self::MouthOpenY => standard_params::MOUTH_OPEN_Y,
+      Self::MouthForm => standard_params::MOUTH_FORM,
+    }
+  }
+}
+
+/// Converts [`FaceLandmarkerFrame`]s into the standard parameters of _Live2D Cubism Editor_, with exponential smoothing.
+#[derive(Debug, Clone)]
+pub struct MediaPipeFaceAdapter {
+  /// Time constant of the smoothing, in seconds. `0` disables smoothing.
+  pub smoothing_time: f32,
+  /// Whether to mirror left and right, as when the model faces the user like a mirror image of the webcam.
+  pub mirror: bool,
+  /// Smoothed value per [`Target::ALL`], `None` until first tracked.
+  values: [Option<f32>; Target::ALL.len()],
+}
+impl Default for MediaPipeFaceAdapter {
+  fn default() -> Self {
+    Self {
+      smoothing_time: 0.1,
+      mirror: true,
+      values: [None; Target::ALL.len()],
+    }
+  }
+}
+impl MediaPipeFaceAdapter {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Smooths `frame` into the current values, `delta_time` seconds after the previous frame.
+  pub fn update(&mut self, frame: &FaceLandmarkerFrame, delta_time: f32) {
+    let alpha = if self.smoothing_time > 0.0 {
+      1.0 - (-delta_time / self.smoothing_time).exp()
+    } else {
+      1.0
+    };
+
+    for (target, value) in Target::ALL.into_iter().zip(self.values.iter_mut()) {
+      let Some(raw) = raw_value(frame, target, self.mirror) else {
+        continue;
+      };
+      *value = Some(match *value {
+        Some(smoothed) => smoothed + (raw - smoothed) * alpha,
+        None => raw,
+      });
+    }
+  }
+
+  /// Forgets the smoothed values, e.g. when the face is lost.
+  pub fn reset(&mut self) {
+    self.values = [None; Target::ALL.len()];
+  }
+
+  /// Writes the current values into `dynamic`. Parameters not in the model, or not tracked yet, are skipped.
+  pub fn apply(&self, model_static: &ModelStatic, dynamic: &mut ModelDynamic) {
+    for (target, value) in Target::ALL.into_iter().zip(self.values.iter()) {
+      let (Some(value), Some(index)) = (value, model_static.parameter_index(target.parameter_id())) else {
+        continue;
+      };
+      dynamic[index] = *value;
+    }
+  }
+}
+
+/// The unsmoothed value of `target`, or `None` if it is not tracked in `frame`.
+fn raw_value(frame: &FaceLandmarkerFrame, target: Target, mirror: bool) -> Option<f32> {
+  // MediaPipe names sides from the subject's point of view.
+  let (left, right) = if mirror { ("Right", "Left") } else { ("Left", "Right") };
+  let side = if mirror { -1.0 } else { 1.0 };
+  let blendshape = |name: &str, suffix: &str| frame.blendshape(&format!("{name}{suffix}"));
+
+  if frame.blendshapes.is_empty() && !matches!(target, Target::AngleX | Target::AngleY | Target::AngleZ) {
+    return None;
+  }
+
+  let value = match target {
+    Target::AngleX | Target::AngleY | Target::AngleZ => {
+      let [pitch, yaw, roll] = head_rotation_degrees(frame.facial_transformation_matrix?);
+      match target {
+        Target::AngleX => yaw * side,
+        Target::AngleY => pitch,
+        _ => roll * side,
+      }
+    }
+    Target::EyeLOpen => 1.0 - blendshape("eyeBlink", left),
+    Target::EyeROpen => 1.0 - blendshape("eyeBlink", right),
+    Target::EyeBallX => {
+      let towards_subject_left = blendshape("eyeLookOut", "Left") + blendshape("eyeLookIn", "Right");
+      let towards_subject_right = blendshape("eyeLookIn", "Left") + blendshape("eyeLookOut", "Right");
+      (towards_subject_left - towards_subject_right) * 0.5 * side
+    }
+    Target::EyeBallY => {
+      let up = blendshape("eyeLookUp", "Left") + blendshape("eyeLookUp", "Right");
+      let down = blendshape("eyeLookDown", "Left") + blendshape("eyeLookDown", "Right");
+      (up - down) * 0.5
+    }
+    Target::BrowLY => blendshape("browOuterUp", left) - blendshape("browDown", left),
+    Target::BrowRY => blendshape("browOuterUp", right) - blendshape("browDown", right),
+    Target::MouthOpenY => frame.blendshape("jawOpen"),
+    Target::MouthForm => {
+      let smile = blendshape("mouthSmile", "Left") + blendshape("mouthSmile", "Right");
+      let frown = blendshape("mouthFrown", "Left") + blendshape("mouthFrown", "Right");
+      ((smile - frown) * 0.5).clamp(-1.0, 1.0)
+    }
+  };
+  Some(value)
+}
+
+/// Decomposes the rotation of a column-major transformation matrix as yaw, then pitch, then roll, into `[pitch, yaw, roll]` in degrees.
+fn head_rotation_degrees(matrix: &[f32; 16]) -> [f32; 3] {
+  // Row-major element of the upper-left 3x3.
+  let r = |row: usize, column: usize| matrix[column * 4 + row];
+
+  let pitch = (-r(1, 2)).clamp(-1.0, 1.0).asin();
+  let yaw = r(0, 2).atan2(r(2, 2));
+  let roll = r(1, 0).atan2(r(1, 1));
+  [pitch.to_degrees(), yaw.to_degrees(), roll.to_degrees()]
+}
@@ -0,0 +1,238 @@
+//! Debug export of the current deformed meshes, for inspection in DCC tools such as Blender.
+
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::io;
+
+use super::base_types::Vector2;
+use super::model_types::UvOrigin;
+use super::{ModelStatic, ModelDynamic};
+
+/// File format for [`super::Model::export_mesh`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MeshExportFormat {
+  /// Wavefront OBJ, one object per drawable.
+  Obj,
+  /// glTF 2.0 with an embedded buffer, one mesh and node per drawable.
+  ///
+  /// UVs are V-flipped to glTF's top-left origin.
+  Gltf,
+}
+impl MeshExportFormat {
+  fn uv_origin(&self) -> UvOrigin {
+    match self {
+      Self::Obj => UvOrigin::BottomLeft,
+      Self::Gltf => UvOrigin::TopLeft,
+    }
+  }
+}
+
+/// Writes the meshes of all drawables with their current vertex positions.
+///
+/// Positions are in model units, with Z = 0.
+pub(crate) fn write_mesh<W: io::Write>(writer: W, model_static: &ModelStatic, dynamic: &ModelDynamic, format: MeshExportFormat) -> io::Result<()> {
+  let meshes: Vec<_> = model_static.drawables().iter()
+    .zip(dynamic.drawable_vertex_position_containers().iter())
+    .map(|(drawable, &positions)| DrawableMesh {
+      id: drawable.id(),
+      positions,
+      uvs: drawable.vertex_uvs_with_origin(format.uv_origin()),
+      triangle_indices: drawable.triangle_indices(),
+    })
+    .collect();
+
+  match format {
+    MeshExportFormat::Obj => write_obj(writer, &meshes),
+    MeshExportFormat::Gltf => write_gltf(writer, &meshes),
+  }
+}
+
+struct DrawableMesh<'a> {
+  id: &'a str,
+  positions: &'a [Vector2],
+  uvs: Cow<'a, [Vector2]>,
+  triangle_indices: &'a [u16],
+}
+
+fn write_obj<W: io::Write>(mut writer: W, meshes: &[DrawableMesh]) -> io::Result<()> {
+  writeln!(writer, "# Exported by live2d-cubism-core-sys")?;
+
+  // OBJ indices are 1-based and global across objects.
+  let mut index_base = 1;
+  for mesh in meshes {
+    writeln!(writer, "o {}", mesh.id)?;
+    for position in mesh.positions {
+      writeln!(writer, "v {} {} 0", position.x, position.y)?;
+    }
+    for uv in mesh.uvs.iter() {
+      writeln!(writer, "vt {} {}", uv.x, uv.y)?;
+    }
+    for triangle in mesh.triangle_indices.chunks_exact(3) {
+      let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|index| index_base + usize::from(index));
+      writeln!(writer, "f {a}/{a} {b}/{b} {c}/{c}")?;
+    }
+    index_base += mesh.positions.len();
+  }
+
+  writer.flush()
+}
+
+fn write_gltf<W: io::Write>(mut writer: W, meshes: &[DrawableMesh]) -> io::Result<()> {
+  const FLOAT: u32 = 5126;
+  const UNSIGNED_SHORT: u32 = 5123;
+  const ARRAY_BUFFER: u32 = 34962;
+  const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+  let mut buffer: Vec<u8> = Vec::new();
+  let mut buffer_views = Vec::new();
+  let mut accessors = Vec::new();
+  let mut gltf_meshes = Vec::new();
+  let mut nodes = Vec::new();
+
+  let mut push_buffer_view = |buffer: &mut Vec<u8>, bytes: &[u8], target: u32| -> usize {
+    // Every accessor component type used here is at most 4 bytes.
+    buffer.resize(buffer.len().next_multiple_of(4), 0);
+    buffer_views.push(format!(r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#, buffer.len(), bytes.len(), target));
+    buffer.extend_from_slice(bytes);
+    buffer_views.len() - 1
+  };
+
+  for mesh in meshes.iter().filter(|mesh| !mesh.positions.is_empty() && !mesh.triangle_indices.is_empty()) {
+    // glTF requires finite bounds.
+    if mesh.positions.iter().any(|position| !position.x.is_finite() || !position.y.is_finite()) {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Drawable \"{}\" has non-finite vertex positions", mesh.id)));
+    }
+    let (min, max) = mesh.positions.iter().fold(
+      ([f32::MAX, f32::MAX], [f32::MIN, f32::MIN]),
+      |(min, max), position| {
+        ([min[0].min(position.x), min[1].min(position.y)], [max[0].max(position.x), max[1].max(position.y)])
+      },
+    );
+
+    let position_bytes: Vec<u8> = mesh.positions.iter()
+      .flat_map(|position| [position.x, position.y, 0.0])
+      .flat_map(f32::to_le_bytes)
+      .collect();
+    let uv_bytes: Vec<u8> = mesh.uvs.iter()
+      .flat_map(|uv| [uv.x, uv.y])
+      .flat_map(f32::to_le_bytes)
+      .collect();
+    let index_bytes: Vec<u8> = mesh.triangle_indices.iter()
+      .flat_map(|index| index.to_le_bytes())
+      .collect();
+
+    let position_view = push_buffer_view(&mut buffer, &position_bytes, ARRAY_BUFFER);
+    let uv_view = push_buffer_view(&mut buffer, &uv_bytes, ARRAY_BUFFER);
+    let index_view = push_buffer_view(&mut buffer, &index_bytes, ELEMENT_ARRAY_BUFFER);
+
+    let position_accessor = accessors.len();
+    accessors.push(format!(
+      r#"{{"bufferView":{},"componentType":{},"count":{},"type":"VEC3","min":[{},{},0],"max":[{},{},0]}}"#,
+      position_view, FLOAT, mesh.positions.len(), min[0], min[1], max[0], max[1],
+    ));
+    let uv_accessor = accessors.len();
+    accessors.push(format!(r#"{{"bufferView":{},"componentType":{},"count":{},"type":"VEC2"}}"#, uv_view, FLOAT, mesh.uvs.len()));
+    let index_accessor = accessors.len();
+    accessors.push(format!(r#"{{"bufferView":{},"componentType":{},"count":{},"type":"SCALAR"}}"#, index_view, UNSIGNED_SHORT, mesh.triangle_indices.len()));
+
+    let name = json_string(mesh.id);
+    gltf_meshes.push(format!(
+      r#"{{"name":{},"primitives":[{{"attributes":{{"POSITION":{},"TEXCOORD_0":{}}},"indices":{}}}]}}"#,
+      name, position_accessor, uv_accessor, index_accessor,
+    ));
+    nodes.push(format!(r#"{{"name":{},"mesh":{}}}"#, name, gltf_meshes.len() - 1));
+  }
+
+  let mut json = String::from(r#"{"asset":{"version":"2.0","generator":"live2d-cubism-core-sys"}"#);
+  // glTF forbids empty arrays and buffers, so a scene without meshes has none of them.
+  if nodes.is_empty() {
+    json.push_str(r#","scene":0,"scenes":[{}]"#);
+  } else {
+    let node_indices: Vec<_> = (0..nodes.len()).map(|index| index.to_string()).collect();
+    write!(
+      json,
+      r#","scene":0,"scenes":[{{"nodes":[{}]}}],"nodes":[{}],"meshes":[{}],"accessors":[{}],"bufferViews":[{}],"buffers":[{{"byteLength":{},"uri":"data:application/octet-stream;base64,{}"}}]"#,
+      node_indices.join(","),
+      nodes.join(","),
+      gltf_meshes.join(","),
+      accessors.join(","),
+      buffer_views.join(","),
+      buffer.len(),
+      base64_encode(&buffer),
+    ).unwrap();
+  }
+  json.push('}');
+
+  writer.write_all(json.as_bytes())?;
+  writer.flush()
+}
+
+fn json_string(value: &str) -> String {
+  let mut escaped = String::with_capacity(value.len() + 2);
+  escaped.push('"');
+  for c in value.chars() {
+    match c {
+      '"' => escaped.push_str("\\\""),
+      '\\' => escaped.push_str("\\\\"),
+      c if (c as u32) < 0x20 => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+      c => escaped.push(c),
+    }
+  }
+  escaped.push('"');
+  escaped
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+  const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+  let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+  for chunk in bytes.chunks(3) {
+    let b = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+    let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+
+    encoded.push(ALPHABET[(n >> 18) as usize & 63] as char);
+    encoded.push(ALPHABET[(n >> 12) as usize & 63] as char);
+    encoded.push(if chunk.len() > 1 { ALPHABET[(n >> 6) as usize & 63] as char } else { '=' });
+    encoded.push(if chunk.len() > 2 { ALPHABET[n as usize & 63] as char } else { '=' });
+  }
+  encoded
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+  use crate::core::{CubismCore, Model, MockMoc};
+
+  use super::*;
+
+  fn write_gltf_of(mock_moc: MockMoc, parameter_value: f32) -> io::Result<String> {
+    let moc = CubismCore::default().moc_from_bytes(&mock_moc.to_bytes()).unwrap();
+    let model = Model::from_moc(&moc);
+    {
+      let mut dynamic = model.write_dynamic();
+      dynamic.parameter_values_mut().fill(parameter_value);
+      dynamic.update();
+    }
+    let mut bytes = Vec::new();
+    model.write_mesh(&mut bytes, MeshExportFormat::Gltf)?;
+    Ok(String::from_utf8(bytes).unwrap())
+  }
+
+  #[test]
+  fn gltf_meshes() {
+    let json = write_gltf_of(MockMoc::default(), 0.5).unwrap();
+    assert!(json.contains(r#""name":"MockDrawable3""#));
+    assert!(json.contains(r#""buffers":[{"byteLength":"#));
+  }
+
+  #[test]
+  fn gltf_without_meshes_has_no_empty_arrays() {
+    let json = write_gltf_of(MockMoc { drawable_count: 0, ..Default::default() }, 0.0).unwrap();
+    assert_eq!(json, r#"{"asset":{"version":"2.0","generator":"live2d-cubism-core-sys"},"scene":0,"scenes":[{}]}"#);
+  }
+
+  #[test]
+  fn gltf_rejects_non_finite_positions() {
+    let err = write_gltf_of(MockMoc::default(), f32::NAN).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+  }
+}
@@ -0,0 +1,149 @@
+//! Lip sync from the microphone in real time.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use cpal::traits::{DeviceTrait as _, HostTrait as _, StreamTrait as _};
+use cpal::{FromSample, Sample as _, SampleFormat, SizedSample};
+use thiserror::Error;
+
+use super::animator::AnimationStage;
+use super::base_types::ParameterIndex;
+use super::ModelDynamic;
+
+/// Errors generated when opening the microphone.
+#[derive(Debug, Error)]
+pub enum MicLipSyncError {
+  #[error("No input device available.")]
+  NoInputDevice,
+  #[error("Failed to get the input configuration: {0}")]
+  DefaultConfig(#[from] cpal::DefaultStreamConfigError),
+  #[error("Failed to open the input stream: {0}")]
+  BuildStream(#[from] cpal::BuildStreamError),
+  #[error("Failed to start the input stream: {0}")]
+  PlayStream(#[from] cpal::PlayStreamError),
+  #[error("Unsupported sample format {0}.")]
+  UnsupportedSampleFormat(SampleFormat),
+}
+
+/// Captures the default input device, computing a smoothed RMS of the audio on the audio thread.
+///
+/// The capture stops when dropped. Since the underlying stream can't be sent to other threads on every platform,
+/// use [`MicLipSync::stage`] to drive a model from a [`super::ModelAnimator`].
+pub struct MicLipSync {
+  /// Only `None` while dropping.
+  stream: Option<cpal::Stream>,
+  level: MicLevel,
+}
+impl MicLipSync {
+  /// Opens the default input device of the default host.
+  ///
+  /// * `smoothing_time` - Time constant of the smoothing, in seconds. `0` disables smoothing.
+  pub fn open_default(smoothing_time: f32) -> Result<Self, MicLipSyncError> {
+    let device = cpal::default_host().default_input_device().ok_or(MicLipSyncError::NoInputDevice)?;
+    Self::open(&device, smoothing_time)
+  }
+  pub fn open(device: &cpal::Device, smoothing_time: f32) -> Result<Self, MicLipSyncError> {
+    let supported_config = device.default_input_config()?;
+    let sample_format = supported_config.sample_format();
+    let config: cpal::StreamConfig = supported_config.into();
+
+    let level = MicLevel::default();
+    let stream = match sample_format {
+      SampleFormat::F32 => build_stream::<f32>(device, &config, level.clone(), smoothing_time)?,
+      SampleFormat::I16 => build_stream::<i16>(device, &config, level.clone(), smoothing_time)?,
+      SampleFormat::U16 => build_stream::<u16>(device, &config, level.clone(), smoothing_time)?,
+      sample_format => return Err(MicLipSyncError::UnsupportedSampleFormat(sample_format)),
+    };
+    stream.play()?;
+
+    Ok(Self {
+      stream: Some(stream),
+      level,
+    })
+  }
+
+  /// The latest smoothed RMS of the captured audio.
+  pub fn level(&self) -> f32 {
+    self.level.get()
+  }
+  /// Creates a stage that writes the latest level into `parameter_indices`, such as `ParamMouthOpenY`.
+  pub fn stage(&self, parameter_indices: Vec<ParameterIndex>) -> MicLipSyncStage {
+    MicLipSyncStage {
+      level: self.level.clone(),
+      parameter_indices,
+      gain: 1.0,
+    }
+  }
+}
+impl std::fmt::Debug for MicLipSync {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("MicLipSync")
+      .field("level", &self.level())
+      .finish_non_exhaustive()
+  }
+}
+impl Drop for MicLipSync {
+  fn drop(&mut self) {
+    // Stop the callback before resetting the level, so that it is not overwritten.
+    drop(self.stream.take());
+    self.level.set(0.0);
+  }
+}
+
+/// Drives lip sync parameters from a [`MicLipSync`], as an [`AnimationStage`]. Keeps working after the [`MicLipSync`] is dropped, at a level of `0`.
+#[derive(Debug, Clone)]
+pub struct MicLipSyncStage {
+  level: MicLevel,
+  parameter_indices: Vec<ParameterIndex>,
+  /// Multiplies the level before clamping to `[0, 1]`.
+  pub gain: f32,
+}
+impl AnimationStage for MicLipSyncStage {
+  fn advance(&mut self, _dt: f32, dynamic: &mut ModelDynamic) {
+    let opening = (self.level.get() * self.gain).clamp(0.0, 1.0);
+    for &index in &self.parameter_indices {
+      dynamic[index] = opening;
+    }
+  }
+}
+
+/// The smoothed level shared with the audio thread, as the bits of an `f32`.
+#[derive(Debug, Clone, Default)]
+struct MicLevel(Arc<AtomicU32>);
+impl MicLevel {
+  fn get(&self) -> f32 {
+    f32::from_bits(self.0.load(Ordering::Relaxed))
+  }
+  fn set(&self, level: f32) {
+    self.0.store(level.to_bits(), Ordering::Relaxed);
+  }
+}
+
+fn build_stream<T>(device: &cpal::Device, config: &cpal::StreamConfig, level: MicLevel, smoothing_time: f32) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+  T: SizedSample,
+  f32: FromSample<T>,
+{
+  let samples_per_second = config.sample_rate.0 as f32 * config.channels as f32;
+  let mut smoothed = 0.0f32;
+
+  device.build_input_stream(
+    config,
+    move |data: &[T], _: &cpal::InputCallbackInfo| {
+      if data.is_empty() {
+        return;
+      }
+      let rms = (data.iter().map(|&sample| f32::from_sample(sample).powi(2)).sum::<f32>() / data.len() as f32).sqrt();
+
+      let duration = data.len() as f32 / samples_per_second;
+      let alpha = if smoothing_time > 0.0 { 1.0 - (-duration / smoothing_time).exp() } else { 1.0 };
+      smoothed += (rms - smoothed) * alpha;
+      level.set(smoothed);
+    },
+    |err| log::error!("Microphone input stream error: {err}"),
+    None,
+  )
+}
@@ -0,0 +1,102 @@
+//! Deduplication of mocs deserialized from identical bytes.
+
+use std::collections::HashMap;
+
+use parking_lot::Mutex;
+
+use super::base_types::MocError;
+use super::integrity::{self, Sha256Digest};
+use super::{CubismCore, Moc};
+
+/// Identifies moc bytes by their length and SHA-256 digest.
+///
+/// The bytes themselves are not kept, as a revived moc does not retain them unmodified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct MocKey {
+  len: usize,
+  sha256: Sha256Digest,
+}
+impl MocKey {
+  fn new(bytes: &[u8]) -> Self {
+    Self {
+      len: bytes.len(),
+      sha256: integrity::sha256(bytes),
+    }
+  }
+}
+
+/// A cache of [`Moc`]s keyed by the content of their bytes, so that loading the same moc again shares its storage.
+///
+/// See [`CubismCore::moc_cached`].
+#[derive(Debug, Default)]
+pub struct MocCache {
+  mocs: Mutex<HashMap<MocKey, Moc>>,
+}
+impl MocCache {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Gets the cached moc with the same bytes, or deserializes and caches a new one with `cubism_core`.
+  ///
+  /// Deserialization happens without holding the cache locked, so unrelated loads run concurrently.
+  /// If the same bytes are loaded concurrently, all callers get whichever moc was cached first.
+  pub fn get_or_load(&self, cubism_core: &CubismCore, bytes: &[u8]) -> Result<Moc, MocError> {
+    let key = MocKey::new(bytes);
+
+    if let Some(moc) = self.mocs.lock().get(&key) {
+      return Ok(moc.clone());
+    }
+
+    let moc = cubism_core.moc_from_bytes(bytes)?;
+    Ok(self.mocs.lock().entry(key).or_insert(moc).clone())
+  }
+
+  /// Number of cached mocs.
+  pub fn len(&self) -> usize {
+    self.mocs.lock().len()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.mocs.lock().is_empty()
+  }
+  /// Removes the cached moc with the same bytes, returning it.
+  pub fn remove(&self, bytes: &[u8]) -> Option<Moc> {
+    self.mocs.lock().remove(&MocKey::new(bytes))
+  }
+  /// Removes the cached mocs without any live [`super::Model`], as counted by [`Moc::model_count`], returning how many were removed.
+  ///
+  /// Removed mocs still referenced elsewhere stay alive.
+  pub fn evict_unused(&self) -> usize {
+    let mut mocs = self.mocs.lock();
+    let len_before = mocs.len();
+    mocs.retain(|_, moc| moc.model_count() > 0);
+    len_before - mocs.len()
+  }
+  /// Removes all cached mocs. Mocs still referenced elsewhere stay alive.
+  pub fn clear(&self) {
+    self.mocs.lock().clear();
+  }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+  use crate::core::MockMoc;
+
+  use super::*;
+
+  #[test]
+  fn shares_identical_bytes_only() {
+    let cubism_core = CubismCore::default();
+    let cache = MocCache::new();
+    let bytes = MockMoc::default().to_bytes();
+    let other_bytes = MockMoc { parameter_count: 5, ..Default::default() }.to_bytes();
+
+    let moc = cache.get_or_load(&cubism_core, &bytes).unwrap();
+    assert!(Moc::ptr_eq(&moc, &cache.get_or_load(&cubism_core, &bytes).unwrap()));
+    assert!(!Moc::ptr_eq(&moc, &cache.get_or_load(&cubism_core, &other_bytes).unwrap()));
+    assert_eq!(cache.len(), 2);
+
+    assert!(cache.remove(&bytes).is_some());
+    assert!(!Moc::ptr_eq(&moc, &cache.get_or_load(&cubism_core, &bytes).unwrap()));
+  }
+}
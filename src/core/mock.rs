@@ -0,0 +1,83 @@
+//! A synthetic moc for testing code built on this crate in CI without _Live2D® Cubism SDK Core_.
+//!
+//! With the `mock` feature, this crate does not link the Core, and [`super::CubismCore::moc_from_bytes`] only accepts
+//! the bytes of a [`MockMoc`]. Items needing the actual Core, e.g. `crate::sys`, are unavailable.
+//!
+//! The feature is not additive: enabled anywhere in a build, it replaces the Core for every user of this crate there.
+//! Enable it only for tests of a final crate, never from the dependencies of a library.
+//!
+//! The models created from it behave as follows:
+//! - Parameter `i` has the ID `MockParam{i}`, a range of `[-1, 1]` and a default value of `0`.
+//! - Part `i` has the ID `MockPart{i}`.
+//! - Drawable `i` has the ID `MockDrawable{i}`, and is a unit quad of 4 vertices and 2 triangles centered at `(i, 0)`,
+//!   using texture `0`, in part `i % part_count`.
+//! - On update, drawable `i` moves up by the value of parameter `i % parameter_count`,
+//!   and takes the opacity of its part, being visible while it is above `0`.
+//!   Dynamic flags report the resulting changes.
+
+#![cfg(feature = "mock")]
+
+/// Describes a synthetic moc. Load it with [`super::CubismCore::moc_from_bytes`] on the bytes from [`MockMoc::to_bytes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MockMoc {
+  pub parameter_count: u32,
+  pub part_count: u32,
+  pub drawable_count: u32,
+}
+impl Default for MockMoc {
+  fn default() -> Self {
+    Self {
+      parameter_count: 4,
+      part_count: 2,
+      drawable_count: 4,
+    }
+  }
+}
+impl MockMoc {
+  const MAGIC: [u8; 4] = *b"MOCK";
+
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = Self::MAGIC.to_vec();
+    for count in [self.parameter_count, self.part_count, self.drawable_count] {
+      bytes.extend_from_slice(&count.to_le_bytes());
+    }
+    bytes
+  }
+  pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+    let counts = bytes.strip_prefix(&Self::MAGIC)?;
+    if counts.len() != 12 {
+      return None;
+    }
+    let count = |i: usize| u32::from_le_bytes(counts[i * 4..(i + 1) * 4].try_into().unwrap());
+    Some(Self {
+      parameter_count: count(0),
+      part_count: count(1),
+      drawable_count: count(2),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::core::{CubismCore, DynamicDrawableFlags, Model};
+
+  use super::MockMoc;
+
+  #[test]
+  fn update() {
+    let moc = CubismCore::default().moc_from_bytes(&MockMoc::default().to_bytes()).unwrap();
+    let model = Model::from_moc(&moc);
+    let mut model_dynamic = model.write_dynamic();
+    assert_eq!(model_dynamic.model_static().parameters()[1].id(), "MockParam1");
+    assert_eq!(model_dynamic.model_static().drawables().len(), 4);
+
+    model_dynamic.parameter_values_mut()[1] = 0.5;
+    model_dynamic.part_opacities_mut()[0] = 0.0;
+    model_dynamic.update();
+
+    assert_eq!(model_dynamic.drawable_vertex_position_containers()[1][0].y, 0.0);
+    assert!(model_dynamic.drawable_dynamic_flagsets()[1].contains(DynamicDrawableFlags::VertexPositionsDidChange));
+    assert!(!model_dynamic.drawable_dynamic_flagsets()[2].contains(DynamicDrawableFlags::IsVisible));
+    assert!(model_dynamic.drawable_dynamic_flagsets()[3].contains(DynamicDrawableFlags::IsVisible));
+  }
+}
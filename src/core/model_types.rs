@@ -2,16 +2,20 @@
 
 pub mod prelude {
   pub use super::CanvasInfo;
+  pub use super::{ModelCapabilityFlags, ModelCapabilityFlagSet};
+  pub use super::{GeometryStatistics, TextureUsage};
   pub use super::{ConstantDrawableFlags, ConstantDrawableFlagSet};
-  pub use super::{DynamicDrawableFlags, DynamicDrawableFlagSet};
-  pub use super::Drawable;
+  pub use super::{DynamicDrawableFlags, DynamicDrawableFlagSet, UpdateSummary};
+  pub use super::{Drawable, UvOrigin, TriangleWinding};
 }
 
+use std::borrow::Cow;
+
 use static_assertions::{assert_eq_align, assert_eq_size};
 use num_enum::TryFromPrimitive;
 use flagset::{FlagSet, flags};
 
-use super::base_types::{TextureIndex, DrawableIndex};
+use super::base_types::{Vector2, TextureIndex, DrawableIndex};
 
 //
 // Canvas
@@ -27,6 +31,112 @@ pub struct CanvasInfo {
   /// Aspect used for scaling pixels to units.
   pub pixels_per_unit: f32,
 }
+impl CanvasInfo {
+  /// Transforms a position in model units (Y up) into canvas pixels (origin at top-left, Y down).
+  #[inline]
+  pub fn unit_to_pixel(&self, position: Vector2) -> Vector2 {
+    Vector2 {
+      x: self.origin_in_pixels.0 + position.x * self.pixels_per_unit,
+      y: self.origin_in_pixels.1 - position.y * self.pixels_per_unit,
+    }
+  }
+  /// Inverse of [`CanvasInfo::unit_to_pixel`].
+  #[inline]
+  pub fn pixel_to_unit(&self, position: Vector2) -> Vector2 {
+    Vector2 {
+      x: (position.x - self.origin_in_pixels.0) / self.pixels_per_unit,
+      y: (self.origin_in_pixels.1 - position.y) / self.pixels_per_unit,
+    }
+  }
+}
+
+//
+// Capabilities
+//
+
+flags! {
+  /// Features used by a model, which a renderer may need to support.
+  pub enum ModelCapabilityFlags: u8 {
+    /// At least one drawable is masked.
+    Masks,
+    /// At least one drawable uses `IsInvertedMask`.
+    InvertedMasks,
+    /// At least one drawable uses `BlendAdditive`.
+    AdditiveBlending,
+    /// At least one drawable uses `BlendMultiplicative`.
+    MultiplicativeBlending,
+    /// At least one parameter is of [`ParameterType::BlendShape`].
+    BlendShapeParameters,
+    /// At least one drawable has a multiply color other than white.
+    MultiplyColors,
+    /// At least one drawable has a screen color other than black.
+    ScreenColors,
+  }
+}
+
+pub type ModelCapabilityFlagSet = FlagSet<ModelCapabilityFlags>;
+
+//
+// Geometry statistics
+//
+
+/// Aggregate geometry totals of a model, e.g. for pre-allocating GPU buffers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GeometryStatistics {
+  pub drawable_count: usize,
+  pub total_vertex_count: usize,
+  pub total_triangle_count: usize,
+  /// Largest vertex count of a single drawable.
+  pub max_vertex_count: usize,
+  /// Largest triangle index count of a single drawable.
+  pub max_triangle_index_count: usize,
+  /// Number of textures referenced, i.e. the largest texture index plus one.
+  pub texture_count: usize,
+}
+impl GeometryStatistics {
+  pub fn from_drawables(drawables: &[Drawable]) -> Self {
+    drawables.iter().fold(Self { drawable_count: drawables.len(), ..Default::default() }, |statistics, drawable| {
+      let vertex_count = drawable.vertex_count() as usize;
+      let triangle_index_count = drawable.triangle_indices().len();
+
+      Self {
+        total_vertex_count: statistics.total_vertex_count + vertex_count,
+        total_triangle_count: statistics.total_triangle_count + triangle_index_count / 3,
+        max_vertex_count: statistics.max_vertex_count.max(vertex_count),
+        max_triangle_index_count: statistics.max_triangle_index_count.max(triangle_index_count),
+        texture_count: statistics.texture_count.max(drawable.texture_index().as_usize() + 1),
+        ..statistics
+      }
+    })
+  }
+}
+
+/// The drawables sampling one texture, e.g. for atlas planning or batching.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextureUsage {
+  /// In drawable order.
+  pub drawables: Vec<DrawableIndex>,
+  pub total_vertex_count: usize,
+  pub total_triangle_count: usize,
+}
+impl TextureUsage {
+  /// Usage per texture index, up to the largest texture index referenced. Textures referenced by no drawable are left empty.
+  pub fn from_drawables(drawables: &[Drawable]) -> Vec<Self> {
+    let mut usages: Vec<Self> = Vec::new();
+    for drawable in drawables {
+      let texture_index = drawable.texture_index().as_usize();
+      if usages.len() <= texture_index {
+        usages.resize_with(texture_index + 1, Default::default);
+      }
+
+      let usage = &mut usages[texture_index];
+      usage.drawables.push(drawable.index());
+      usage.total_vertex_count += drawable.vertex_count() as usize;
+      usage.total_triangle_count += drawable.triangle_indices().len() / 3;
+    }
+    usages
+  }
+}
 
 //
 // Parameter
@@ -68,6 +178,39 @@ impl Parameter {
   pub fn keys(&self) -> &[f32] {
     &self.keys
   }
+  /// Clamps `value` into [`Parameter::value_range`].
+  pub fn clamp_value(&self, value: f32) -> f32 {
+    value.clamp(self.value_range.0, self.value_range.1)
+  }
+  /// Wraps `value` into [`Parameter::value_range`] as if the range repeated, e.g. for a continuously rotating angle.
+  ///
+  /// The maximum wraps to the minimum.
+  pub fn wrap_value(&self, value: f32) -> f32 {
+    wrap_into_range(value, self.value_range)
+  }
+}
+
+pub(crate) fn wrap_into_range(value: f32, (minimum, maximum): (f32, f32)) -> f32 {
+  let period = maximum - minimum;
+  if period > 0.0 {
+    minimum + (value - minimum).rem_euclid(period)
+  } else {
+    minimum
+  }
+}
+
+/// Whether parameter values are kept within their ranges, see [`super::ModelDynamic::set_parameter_clamp_mode`].
+///
+/// _Live2D® Cubism SDK Core_ does not define its behavior for out-of-range values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ParameterClampMode {
+  /// Values are passed to _Live2D® Cubism SDK Core_ as written.
+  #[default]
+  Off,
+  /// Values are clamped into their ranges by [`super::ModelDynamic::update`], before updating the model.
+  ///
+  /// Parameters set as cyclic with [`super::ModelDynamic::set_parameter_cyclic`] are wrapped instead.
+  OnUpdate,
 }
 
 //
@@ -92,8 +235,6 @@ impl Part {
 // Drawable
 //
 
-use super::base_types::Vector2;
-
 flags! {
   /// Constant Drawable flag values.
   pub enum ConstantDrawableFlags: u8 {
@@ -127,6 +268,97 @@ pub type DynamicDrawableFlagSet = FlagSet<DynamicDrawableFlags>;
 assert_eq_align!(DynamicDrawableFlagSet, u8);
 assert_eq_size!(DynamicDrawableFlagSet, u8);
 
+flags! {
+  /// Dynamic drawable arrays refreshed on update, see [`super::ModelDynamic::set_readback_mask`].
+  ///
+  /// Dynamic flags are always refreshed.
+  pub enum DrawableReadbackFlags: u8 {
+    DrawOrders,
+    RenderOrders,
+    Opacities,
+    VertexPositions,
+    MultiplyColors,
+    ScreenColors,
+  }
+}
+
+pub type DrawableReadbackFlagSet = FlagSet<DrawableReadbackFlags>;
+
+/// Numbers of drawables with each kind of change, as reported by their [`DynamicDrawableFlags`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct UpdateSummary {
+  pub visibility_changed: usize,
+  pub opacity_changed: usize,
+  pub draw_order_changed: usize,
+  pub render_order_changed: usize,
+  pub vertex_positions_changed: usize,
+  pub blend_color_changed: usize,
+}
+impl UpdateSummary {
+  pub fn from_flagsets(flagsets: &[DynamicDrawableFlagSet]) -> Self {
+    let count = |flag: DynamicDrawableFlags| flagsets.iter().filter(|flagset| flagset.contains(flag)).count();
+    Self {
+      visibility_changed: count(DynamicDrawableFlags::VisibilityDidChange),
+      opacity_changed: count(DynamicDrawableFlags::OpacityDidChange),
+      draw_order_changed: count(DynamicDrawableFlags::DrawOrderDidChange),
+      render_order_changed: count(DynamicDrawableFlags::RenderOrderDidChange),
+      vertex_positions_changed: count(DynamicDrawableFlags::VertexPositionsDidChange),
+      blend_color_changed: count(DynamicDrawableFlags::BlendColorDidChange),
+    }
+  }
+
+  /// Whether nothing changed, so the previous frame can be presented again.
+  pub fn is_unchanged(&self) -> bool {
+    *self == Self::default()
+  }
+}
+
+/// Where `(0, 0)` is in a texture, i.e. the texture coordinate convention of a graphics API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum UvOrigin {
+  /// As in _Live2D®_ models and OpenGL.
+  #[default]
+  BottomLeft,
+  /// As in Direct3D, Metal, Vulkan and wgpu.
+  TopLeft,
+}
+
+/// Orientation of the triangles of a drawable, in model units (Y up).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TriangleWinding {
+  CounterClockwise,
+  Clockwise,
+  /// Both orientations are present, so no culling mode works for every triangle.
+  Mixed,
+}
+impl TriangleWinding {
+  /// Classifies the triangles of `triangle_indices` over `positions`, ignoring degenerate ones
+  /// and ones with an index out of range, as found in broken models (see [`super::MeshIssues::IndexOutOfRange`]).
+  ///
+  /// Returns `None` if there are no such non-degenerate triangles.
+  pub fn of(positions: &[Vector2], triangle_indices: &[u16]) -> Option<Self> {
+    let (mut counter_clockwise, mut clockwise) = (false, false);
+    for triangle in triangle_indices.chunks_exact(3) {
+      let corner = |i: usize| positions.get(usize::from(triangle[i])).copied();
+      let (Some(a), Some(b), Some(c)) = (corner(0), corner(1), corner(2)) else {
+        continue;
+      };
+      let cross = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+      if cross > 0.0 {
+        counter_clockwise = true;
+      } else if cross < 0.0 {
+        clockwise = true;
+      }
+    }
+    match (counter_clockwise, clockwise) {
+      (true, true) => Some(Self::Mixed),
+      (true, false) => Some(Self::CounterClockwise),
+      (false, true) => Some(Self::Clockwise),
+      (false, false) => None,
+    }
+  }
+}
+
 #[derive(Debug, Clone)]
 pub struct Drawable {
   pub(crate) id: String,
@@ -159,13 +391,61 @@ impl Drawable {
   pub fn vertex_count(&self) -> u32 {
     self.vertex_count
   }
+  /// UVs with a bottom-left origin. See [`Drawable::vertex_uvs_with_origin`].
   pub fn vertex_uvs(&self) -> &[Vector2] {
     &self.vertex_uvs
   }
+  /// UVs for `origin`, V-flipped if it is [`UvOrigin::TopLeft`].
+  pub fn vertex_uvs_with_origin(&self, origin: UvOrigin) -> Cow<'_, [Vector2]> {
+    match origin {
+      UvOrigin::BottomLeft => Cow::Borrowed(&self.vertex_uvs),
+      UvOrigin::TopLeft => Cow::Owned(self.vertex_uvs.iter().map(|uv| Vector2 { x: uv.x, y: 1.0 - uv.y }).collect()),
+    }
+  }
+  /// Triangle indices as authored, with no consistent winding guaranteed.
+  ///
+  /// The official renderers only enable back-face culling for drawables without [`ConstantDrawableFlags::IsDoubleSided`],
+  /// culling clockwise triangles. See [`TriangleWinding::of`] to check a mesh before culling.
   pub fn triangle_indices(&self) -> &[u16] {
     &self.triangle_indices
   }
+  /// Number of complete triangles in [`Drawable::triangle_indices`].
+  pub fn triangle_count(&self) -> usize {
+    self.triangle_indices.len() / 3
+  }
+  /// Triangle indices with the winding of every triangle reversed, e.g. for pipelines culling counter-clockwise triangles.
+  pub fn triangle_indices_reversed(&self) -> Vec<u16> {
+    self.triangle_indices.chunks_exact(3)
+      .flat_map(|triangle| [triangle[0], triangle[2], triangle[1]])
+      .collect()
+  }
   pub fn parent_part_index(&self) -> Option<usize> {
     self.parent_part_index
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn positions() -> Vec<Vector2> {
+    [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)].into_iter().map(|(x, y)| Vector2 { x, y }).collect()
+  }
+
+  #[test]
+  fn winding() {
+    let positions = positions();
+    assert_eq!(TriangleWinding::of(&positions, &[0, 1, 2]), Some(TriangleWinding::CounterClockwise));
+    assert_eq!(TriangleWinding::of(&positions, &[0, 2, 1]), Some(TriangleWinding::Clockwise));
+    assert_eq!(TriangleWinding::of(&positions, &[0, 1, 2, 0, 2, 1]), Some(TriangleWinding::Mixed));
+    assert_eq!(TriangleWinding::of(&positions, &[0, 0, 1]), None);
+    assert_eq!(TriangleWinding::of(&positions, &[]), None);
+  }
+
+  #[test]
+  fn winding_skips_out_of_range_indices() {
+    let positions = positions();
+    assert_eq!(TriangleWinding::of(&positions, &[0, 1, 3]), None);
+    assert_eq!(TriangleWinding::of(&positions, &[0, 2, u16::MAX, 0, 1, 2]), Some(TriangleWinding::CounterClockwise));
+  }
+}
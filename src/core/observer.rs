@@ -0,0 +1,82 @@
+//! Reporting of parameter value changes between updates, e.g. to sync UI sliders or replicate a model over the network.
+
+use super::base_types::ParameterIndex;
+use super::{Model, ModelDynamic, PostUpdateHookId};
+
+/// A parameter whose value changed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParameterChange {
+  pub index: ParameterIndex,
+  pub previous: f32,
+  pub current: f32,
+}
+
+/// Remembers the parameter values of a model, to report which ones changed since the last call.
+#[derive(Debug, Clone)]
+pub struct ParameterChangeTracker {
+  values: Box<[f32]>,
+  /// Changes not greater than this are ignored, and accumulate until they are.
+  pub threshold: f32,
+}
+impl ParameterChangeTracker {
+  /// Starts tracking from the current values of `dynamic`.
+  pub fn new(dynamic: &ModelDynamic) -> Self {
+    Self {
+      values: dynamic.parameter_values().into(),
+      threshold: 0.0,
+    }
+  }
+
+  /// Reports the changes since the previous call, or since [`ParameterChangeTracker::new`], in parameter order.
+  ///
+  /// ## Panics
+  /// - If `dynamic` belongs to a model of a different moc.
+  pub fn changes(&mut self, dynamic: &ModelDynamic) -> Vec<ParameterChange> {
+    let mut changes = Vec::new();
+    self.write_changes(dynamic, &mut changes);
+    changes
+  }
+  /// Like [`ParameterChangeTracker::changes`], but writes into `out` to reuse its allocation. `out` is cleared first.
+  ///
+  /// ## Panics
+  /// - If `dynamic` belongs to a model of a different moc.
+  pub fn write_changes(&mut self, dynamic: &ModelDynamic, out: &mut Vec<ParameterChange>) {
+    assert_eq!(self.values.len(), dynamic.parameter_values().len(), "Tracker does not match the model");
+
+    out.clear();
+    for (i, (previous, &current)) in self.values.iter_mut().zip(dynamic.parameter_values().iter()).enumerate() {
+      if (current - *previous).abs() > self.threshold {
+        out.push(ParameterChange {
+          index: ParameterIndex::from(i),
+          previous: *previous,
+          current,
+        });
+        *previous = current;
+      }
+    }
+  }
+}
+
+impl Model {
+  /// Calls `callback` after each update in which the value of the parameter at `index` changed,
+  /// compared to its value on the previous call or when observing started.
+  ///
+  /// This is a post-update hook; see [`Model::add_post_update_hook`] for when it is called, and remove it with [`Model::remove_post_update_hook`].
+  /// Acquires a read lock for [`ModelDynamic`].
+  ///
+  /// ## Panics
+  /// - If `index` is out of range.
+  pub fn observe_parameter<F>(&self, index: ParameterIndex, mut callback: F) -> PostUpdateHookId
+  where
+    F: FnMut(ParameterChange) + Send + 'static,
+  {
+    let mut previous = self.read_dynamic()[index];
+    self.add_post_update_hook(move |_, dynamic| {
+      let current = dynamic[index];
+      if current != previous {
+        callback(ParameterChange { index, previous, current });
+        previous = current;
+      }
+    })
+  }
+}
@@ -0,0 +1,70 @@
+//! Detection of NaN and infinite values in the outputs of an update, which otherwise silently corrupt GPU buffers.
+
+use super::base_types::DrawableIndex;
+use super::{Model, ModelDynamic, PostUpdateHookId};
+
+/// Which output of a drawable is not finite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NonFiniteValue {
+  Opacity,
+  VertexPosition { vertex_index: usize },
+}
+
+/// The first non-finite output found by [`ModelDynamic::find_non_finite_output`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NonFiniteOutput {
+  pub drawable_index: DrawableIndex,
+  pub value: NonFiniteValue,
+  /// [`ModelDynamic::parameter_state_hash`] of the state producing it, for bug reports.
+  pub parameter_state_hash: u64,
+}
+impl std::fmt::Display for NonFiniteOutput {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self.value {
+      NonFiniteValue::Opacity => write!(f, "Non-finite opacity of drawable {}", self.drawable_index)?,
+      NonFiniteValue::VertexPosition { vertex_index } => write!(f, "Non-finite position of vertex {vertex_index} of drawable {}", self.drawable_index)?,
+    }
+    write!(f, " (parameter state hash: {:016x})", self.parameter_state_hash)
+  }
+}
+
+impl ModelDynamic {
+  /// Scans drawable opacities and vertex positions for NaN or infinite values, returning the first one found.
+  pub fn find_non_finite_output(&self) -> Option<NonFiniteOutput> {
+    let value = self.drawable_opacities().iter().zip(self.drawable_vertex_position_containers())
+      .enumerate()
+      .find_map(|(drawable_index, (opacity, positions))| {
+        let value = if !opacity.is_finite() {
+          NonFiniteValue::Opacity
+        } else {
+          let vertex_index = positions.iter().position(|position| !position.x.is_finite() || !position.y.is_finite())?;
+          NonFiniteValue::VertexPosition { vertex_index }
+        };
+        Some((DrawableIndex::from(drawable_index), value))
+      });
+
+    value.map(|(drawable_index, value)| NonFiniteOutput {
+      drawable_index,
+      value,
+      parameter_state_hash: self.parameter_state_hash(),
+    })
+  }
+}
+
+impl Model {
+  /// Checks the outputs after every update through [`Model::write_dynamic`] with [`ModelDynamic::find_non_finite_output`],
+  /// calling `on_found` if any is not finite, e.g. to log it or to capture the parameter values for a bug report.
+  ///
+  /// Remove the check with [`Model::remove_post_update_hook`]. The same restrictions as for
+  /// [`Model::add_post_update_hook`] apply to `on_found`.
+  pub fn add_non_finite_output_guard<F>(&self, mut on_found: F) -> PostUpdateHookId
+  where
+    F: FnMut(&ModelDynamic, &NonFiniteOutput) + Send + 'static,
+  {
+    self.add_post_update_hook(move |_, model_dynamic| {
+      if let Some(non_finite_output) = model_dynamic.find_non_finite_output() {
+        on_found(model_dynamic, &non_finite_output);
+      }
+    })
+  }
+}
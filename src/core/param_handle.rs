@@ -0,0 +1,53 @@
+//! Handles to parameters resolved by ID, as generated by `live2d_params!`.
+
+use thiserror::Error;
+
+use super::base_types::ParameterIndex;
+use super::{ModelStatic, ModelDynamic};
+
+/// A parameter ID not found in a model.
+#[derive(Debug, Clone, Error)]
+#[error("Parameter \"{id}\" not found in the model.")]
+pub struct MissingParameterError {
+  pub id: String,
+}
+
+/// A parameter of a model, resolved by ID once and then accessed by index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParameterHandle {
+  index: ParameterIndex,
+}
+impl ParameterHandle {
+  pub fn new(index: ParameterIndex) -> Self {
+    Self { index }
+  }
+  /// Looks up the parameter with `id` in `model_static`.
+  pub fn resolve(model_static: &ModelStatic, id: &str) -> Result<Self, MissingParameterError> {
+    model_static
+      .parameter_index(id)
+      .map(Self::new)
+      .ok_or_else(|| MissingParameterError { id: id.to_owned() })
+  }
+
+  pub fn index(&self) -> ParameterIndex {
+    self.index
+  }
+
+  /// ## Panics
+  /// - If `dynamic` belongs to a model of a different moc with fewer parameters.
+  pub fn get(&self, dynamic: &ModelDynamic) -> f32 {
+    dynamic.parameter_values()[self.index.as_usize()]
+  }
+  /// ## Panics
+  /// - If `dynamic` belongs to a model of a different moc with fewer parameters.
+  pub fn set(&self, dynamic: &mut ModelDynamic, value: f32) {
+    dynamic.parameter_values_mut()[self.index.as_usize()] = value;
+  }
+  /// Adds `delta` to the current value.
+  ///
+  /// ## Panics
+  /// - If `dynamic` belongs to a model of a different moc with fewer parameters.
+  pub fn add(&self, dynamic: &mut ModelDynamic, delta: f32) {
+    dynamic.parameter_values_mut()[self.index.as_usize()] += delta;
+  }
+}
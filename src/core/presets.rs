@@ -0,0 +1,158 @@
+//! Named sets of parameter values, e.g. expressions or poses bound to shortcut keys.
+
+use thiserror::Error;
+
+use super::{ModelStatic, ModelDynamic};
+
+/// Errors generated by [`Presets`].
+#[derive(Debug, Error)]
+pub enum PresetError {
+  #[error("Preset \"{name}\" not found.")]
+  UnknownPreset { name: String },
+  #[error("Unsupported presets version {0}. Latest supported is {latest}.", latest = Presets::VERSION)]
+  UnsupportedVersion(u32),
+  #[cfg(feature = "serde")]
+  #[error("Failed to parse presets JSON: {0}")]
+  Json(#[from] serde_json::Error),
+}
+
+/// A named set of parameter values, keyed by parameter ID.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Preset {
+  name: String,
+  parameters: Vec<(String, f32)>,
+}
+impl Preset {
+  pub fn new(name: impl Into<String>, parameters: impl IntoIterator<Item = (String, f32)>) -> Self {
+    Self {
+      name: name.into(),
+      parameters: parameters.into_iter().collect(),
+    }
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+  pub fn parameters(&self) -> &[(String, f32)] {
+    &self.parameters
+  }
+}
+
+/// A collection of [`Preset`]s, in insertion order.
+///
+/// Stored as JSON as e.g.:
+/// ```json
+/// {
+///   "Version": 1,
+///   "Presets": [
+///     { "Name": "Smile", "Parameters": [{ "Id": "ParamMouthForm", "Value": 1.0 }] }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Presets {
+  presets: Vec<Preset>,
+}
+impl Presets {
+  /// Current version of the JSON format.
+  pub const VERSION: u32 = 1;
+
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Adds `preset`, replacing any preset with the same name.
+  pub fn insert(&mut self, preset: Preset) {
+    match self.presets.iter_mut().find(|existing| existing.name == preset.name) {
+      Some(existing) => *existing = preset,
+      None => self.presets.push(preset),
+    }
+  }
+  pub fn remove(&mut self, name: &str) -> Option<Preset> {
+    let index = self.presets.iter().position(|preset| preset.name == name)?;
+    Some(self.presets.remove(index))
+  }
+  pub fn get(&self, name: &str) -> Option<&Preset> {
+    self.presets.iter().find(|preset| preset.name == name)
+  }
+  pub fn iter(&self) -> impl Iterator<Item = &Preset> {
+    self.presets.iter()
+  }
+  pub fn len(&self) -> usize {
+    self.presets.len()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.presets.is_empty()
+  }
+
+  /// Moves the parameters of the preset named `name` towards its values by `weight`, clamped to `[0, 1]`,
+  /// `1` setting them to the preset values.
+  ///
+  /// Parameters not found in the model are ignored, so that presets can be shared between models.
+  pub fn apply(&self, name: &str, model_static: &ModelStatic, dynamic: &mut ModelDynamic, weight: f32) -> Result<(), PresetError> {
+    let preset = self.get(name).ok_or_else(|| PresetError::UnknownPreset { name: name.to_owned() })?;
+
+    let weight = weight.clamp(0.0, 1.0);
+    for (id, value) in &preset.parameters {
+      if let Some(index) = model_static.parameter_index(id) {
+        let current = dynamic[index];
+        dynamic[index] = current + (value - current) * weight;
+      }
+    }
+    Ok(())
+  }
+
+  #[cfg(feature = "serde")]
+  pub fn to_json(&self) -> String {
+    let json = json::PresetsJson {
+      version: Self::VERSION,
+      presets: self.presets.iter()
+        .map(|preset| json::Preset {
+          name: preset.name.clone(),
+          parameters: preset.parameters.iter().map(|(id, value)| json::Parameter { id: id.clone(), value: *value }).collect(),
+        })
+        .collect(),
+    };
+    serde_json::to_string_pretty(&json).expect("Serializing presets should not fail")
+  }
+  #[cfg(feature = "serde")]
+  pub fn from_json(json: &str) -> Result<Self, PresetError> {
+    let json: json::PresetsJson = serde_json::from_str(json)?;
+    if json.version == 0 || json.version > Self::VERSION {
+      return Err(PresetError::UnsupportedVersion(json.version));
+    }
+
+    let mut presets = Self::new();
+    for preset in json.presets {
+      presets.insert(Preset::new(preset.name, preset.parameters.into_iter().map(|parameter| (parameter.id, parameter.value))));
+    }
+    Ok(presets)
+  }
+}
+
+/// Serialized form of the JSON presets.
+#[cfg(feature = "serde")]
+mod json {
+  use serde::{Serialize, Deserialize};
+
+  #[derive(Debug, Serialize, Deserialize)]
+  #[serde(rename_all = "PascalCase")]
+  pub struct PresetsJson {
+    pub version: u32,
+    pub presets: Vec<Preset>,
+  }
+
+  #[derive(Debug, Serialize, Deserialize)]
+  #[serde(rename_all = "PascalCase")]
+  pub struct Preset {
+    pub name: String,
+    pub parameters: Vec<Parameter>,
+  }
+
+  #[derive(Debug, Serialize, Deserialize)]
+  #[serde(rename_all = "PascalCase")]
+  pub struct Parameter {
+    pub id: String,
+    pub value: f32,
+  }
+}
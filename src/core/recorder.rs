@@ -0,0 +1,221 @@
+//! Recording of parameter automation, e.g. performances captured from face tracking.
+
+use super::base_types::ParameterIndex;
+use super::{ModelStatic, ModelDynamic};
+
+/// Records the values of all parameters of a model over time.
+#[derive(Debug, Clone)]
+pub struct ParameterRecorder {
+  parameter_ids: Box<[String]>,
+  /// Time of each frame, in seconds.
+  times: Vec<f32>,
+  /// Frame-major parameter values, `parameter_ids.len()` per frame.
+  values: Vec<f32>,
+}
+impl ParameterRecorder {
+  pub fn new(model_static: &ModelStatic) -> Self {
+    Self {
+      parameter_ids: model_static.parameters().iter().map(|parameter| parameter.id().to_owned()).collect(),
+      times: Vec::new(),
+      values: Vec::new(),
+    }
+  }
+
+  pub fn parameter_ids(&self) -> &[String] {
+    &self.parameter_ids
+  }
+  pub fn frame_count(&self) -> usize {
+    self.times.len()
+  }
+  /// Time between the first and the last recorded frames, in seconds.
+  pub fn duration(&self) -> f32 {
+    match (self.times.first(), self.times.last()) {
+      (Some(first), Some(last)) => last - first,
+      _ => 0.0,
+    }
+  }
+  pub fn clear(&mut self) {
+    self.times.clear();
+    self.values.clear();
+  }
+
+  /// Records the current parameter values of `dynamic` at `time` seconds.
+  ///
+  /// ## Panics
+  /// - If `time` is earlier than the previously recorded frame.
+  /// - If `dynamic` does not belong to a model of the same moc as the one this recorder was created for.
+  pub fn record(&mut self, time: f32, dynamic: &ModelDynamic) {
+    if let Some(&last_time) = self.times.last() {
+      assert!(last_time <= time, "Frames must be recorded in chronological order. last: {last_time} given: {time}");
+    }
+    let parameter_values = dynamic.parameter_values();
+    assert_eq!(parameter_values.len(), self.parameter_ids.len(), "Parameter count mismatch");
+
+    self.times.push(time);
+    self.values.extend_from_slice(parameter_values);
+  }
+
+  /// Iterates `(time, value)` of a parameter over the recorded frames, with time relative to the first frame.
+  pub fn track(&self, parameter_index: ParameterIndex) -> impl Iterator<Item = (f32, f32)> + '_ {
+    let parameter_count = self.parameter_ids.len();
+    let start_time = self.times.first().copied().unwrap_or_default();
+
+    self.times.iter()
+      .enumerate()
+      .filter(move |_| parameter_index.as_usize() < parameter_count)
+      .map(move |(frame, &time)| (time - start_time, self.values[frame * parameter_count + parameter_index.as_usize()]))
+  }
+
+  /// Serializes the recorded tracks as a `.motion3.json` document.
+  #[cfg(feature = "serde")]
+  pub fn to_motion3_json(&self, options: &Motion3ExportOptions) -> String {
+    serde_json::to_string_pretty(&self.to_motion3(options)).expect("Serializing a motion3.json should not fail")
+  }
+  /// Like [`ParameterRecorder::to_motion3_json`], but writes into `writer`.
+  #[cfg(feature = "serde")]
+  pub fn write_motion3_json<W: std::io::Write>(&self, writer: W, options: &Motion3ExportOptions) -> Result<(), serde_json::Error> {
+    serde_json::to_writer_pretty(writer, &self.to_motion3(options))
+  }
+
+  #[cfg(feature = "serde")]
+  fn to_motion3(&self, options: &Motion3ExportOptions) -> motion3::Motion3Json {
+    let curves: Vec<_> = (0..self.parameter_ids.len())
+      .map(ParameterIndex::from)
+      .filter_map(|parameter_index| {
+        let points = simplify_track(self.track(parameter_index), options.tolerance);
+        let is_constant = points.windows(2).all(|pair| pair[0].1 == pair[1].1);
+        if options.skip_constant_tracks && is_constant {
+          return None;
+        }
+
+        // The first point, then a linear segment (`0`) followed by its end point for each subsequent point.
+        let mut segments = Vec::with_capacity(points.len() * 3);
+        for (i, &(time, value)) in points.iter().enumerate() {
+          if i > 0 {
+            segments.push(0.0);
+          }
+          segments.push(time);
+          segments.push(value);
+        }
+
+        Some(motion3::Curve {
+          target: "Parameter",
+          id: self.parameter_ids[parameter_index.as_usize()].clone(),
+          segment_count: points.len().saturating_sub(1),
+          point_count: points.len(),
+          segments,
+        })
+      })
+      .collect();
+
+    motion3::Motion3Json {
+      version: 3,
+      meta: motion3::Meta {
+        duration: self.duration(),
+        fps: options.fps,
+        is_loop: options.is_loop,
+        are_beziers_restricted: true,
+        curve_count: curves.len(),
+        total_segment_count: curves.iter().map(|curve| curve.segment_count).sum(),
+        total_point_count: curves.iter().map(|curve| curve.point_count).sum(),
+        user_data_count: 0,
+        total_user_data_size: 0,
+      },
+      curves,
+    }
+  }
+}
+
+/// Options for [`ParameterRecorder::to_motion3_json`].
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone)]
+pub struct Motion3ExportOptions {
+  /// Informative frame rate written into `Meta.Fps`.
+  pub fps: f32,
+  pub is_loop: bool,
+  /// Points deviating less than this from a straight line between their neighbors are dropped.
+  pub tolerance: f32,
+  /// Whether to omit parameters whose value never changed.
+  pub skip_constant_tracks: bool,
+}
+#[cfg(feature = "serde")]
+impl Default for Motion3ExportOptions {
+  fn default() -> Self {
+    Self {
+      fps: 30.0,
+      is_loop: false,
+      tolerance: 1e-3,
+      skip_constant_tracks: true,
+    }
+  }
+}
+
+/// Greedily drops points that lie within `tolerance` of the line between the last kept point and a later one.
+#[cfg(feature = "serde")]
+fn simplify_track(track: impl Iterator<Item = (f32, f32)>, tolerance: f32) -> Vec<(f32, f32)> {
+  let points: Vec<_> = track.collect();
+  if points.len() <= 2 {
+    return points;
+  }
+
+  let is_within_tolerance = |anchor: (f32, f32), end: (f32, f32), between: &[(f32, f32)]| {
+    between.iter().all(|&(time, value)| {
+      let t = if end.0 > anchor.0 { (time - anchor.0) / (end.0 - anchor.0) } else { 0.0 };
+      let interpolated = anchor.1 + (end.1 - anchor.1) * t;
+      (value - interpolated).abs() <= tolerance
+    })
+  };
+
+  let mut simplified = vec![points[0]];
+  let mut anchor = 0;
+  for end in 2..points.len() {
+    if !is_within_tolerance(points[anchor], points[end], &points[anchor + 1..end]) {
+      anchor = end - 1;
+      simplified.push(points[anchor]);
+    }
+  }
+  simplified.push(*points.last().unwrap());
+  simplified
+}
+
+/// Serialized form of `.motion3.json`.
+#[cfg(feature = "serde")]
+mod motion3 {
+  use serde::Serialize;
+
+  #[derive(Debug, Serialize)]
+  #[serde(rename_all = "PascalCase")]
+  pub struct Motion3Json {
+    pub version: u32,
+    pub meta: Meta,
+    pub curves: Vec<Curve>,
+  }
+
+  #[derive(Debug, Serialize)]
+  #[serde(rename_all = "PascalCase")]
+  pub struct Meta {
+    pub duration: f32,
+    pub fps: f32,
+    #[serde(rename = "Loop")]
+    pub is_loop: bool,
+    pub are_beziers_restricted: bool,
+    pub curve_count: usize,
+    pub total_segment_count: usize,
+    pub total_point_count: usize,
+    pub user_data_count: usize,
+    pub total_user_data_size: usize,
+  }
+
+  #[derive(Debug, Serialize)]
+  #[serde(rename_all = "PascalCase")]
+  pub struct Curve {
+    pub target: &'static str,
+    pub id: String,
+    pub segments: Vec<f32>,
+
+    #[serde(skip)]
+    pub segment_count: usize,
+    #[serde(skip)]
+    pub point_count: usize,
+  }
+}
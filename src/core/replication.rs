@@ -0,0 +1,468 @@
+//! Compact delta encoding of parameter values and part opacities, for replicating avatar state over a network,
+//! e.g. in multiplayer or virtual-space apps at 20 to 60 Hz.
+//!
+//! Parameter values are quantized to 16 bits over their range, and part opacities to 8 bits.
+//! Each packet is either a keyframe holding the whole state, or a delta against the packet sequenced right before it.
+//! A delta is only applied on top of that packet, so after a loss the decoder rejects deltas until the next keyframe;
+//! send keyframes periodically with [`ReplicationEncoder::set_keyframe_interval`], or on request with
+//! [`ReplicationEncoder::force_keyframe`].
+//!
+//! Layout: a header byte of version and packet kind, then the sequence number as little-endian `u16`.
+//! Keyframes continue with the parameter and part counts as varints, then every parameter as little-endian `u16` and
+//! every part as `u8`. Deltas continue with the number of changed parameters as varint, then per parameter the index gap
+//! from the previous one as varint and the zigzag-encoded quantized difference as varint, then likewise for parts, each
+//! with its new value as `u8`.
+
+use thiserror::Error;
+
+use super::ModelDynamic;
+
+/// Errors generated when decoding a replication packet.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ReplicationError {
+  #[error("Not a replication packet, or of an unsupported version.")]
+  InvalidHeader,
+  #[error("Replication packet is truncated or malformed.")]
+  Malformed,
+  #[error("Replication packet is for {parameter_count} parameters and {part_count} parts, which does not match the model.")]
+  LayoutMismatch {
+    parameter_count: usize,
+    part_count: usize,
+  },
+  /// The packet is not newer than the last one applied, e.g. reordered by the transport.
+  #[error("Replication packet {sequence} is not newer than the last one applied.")]
+  Stale {
+    sequence: u16,
+  },
+  /// The packet is a delta against a packet that was not applied, e.g. lost; wait for or request a keyframe.
+  #[error("Replication packet {sequence} is a delta against packet {baseline}, which was not applied.")]
+  MissingBaseline {
+    sequence: u16,
+    baseline: u16,
+  },
+}
+
+const VERSION: u8 = 1;
+const KEYFRAME_BIT: u8 = 1;
+
+/// Quantized parameter values and part opacities.
+#[derive(Debug, Clone, PartialEq)]
+struct QuantizedState {
+  parameter_value_ranges: Box<[(f32, f32)]>,
+  parameters: Box<[u16]>,
+  parts: Box<[u8]>,
+}
+impl QuantizedState {
+  fn new(dynamic: &ModelDynamic) -> Self {
+    let mut state = Self {
+      parameter_value_ranges: dynamic.parameter_value_ranges.clone(),
+      parameters: vec![0; dynamic.parameter_values().len()].into(),
+      parts: vec![0; dynamic.part_opacities().len()].into(),
+    };
+    state.quantize(dynamic);
+    state
+  }
+
+  fn quantize(&mut self, dynamic: &ModelDynamic) {
+    let (mut parameters, mut parts) = (std::mem::take(&mut self.parameters), std::mem::take(&mut self.parts));
+    self.quantize_into(&mut parameters, &mut parts, dynamic);
+    (self.parameters, self.parts) = (parameters, parts);
+  }
+  fn quantize_into(&self, parameters: &mut [u16], parts: &mut [u8], dynamic: &ModelDynamic) {
+    assert!(
+      parameters.len() == dynamic.parameter_values().len() && parts.len() == dynamic.part_opacities().len(),
+      "Replication state does not match the model"
+    );
+    for ((quantized, &value), &(minimum, maximum)) in parameters.iter_mut().zip(dynamic.parameter_values()).zip(self.parameter_value_ranges.iter()) {
+      let normalized = if maximum > minimum { (value - minimum) / (maximum - minimum) } else { 0.0 };
+      *quantized = (normalized.clamp(0.0, 1.0) * f32::from(u16::MAX)).round() as u16;
+    }
+    for (quantized, &opacity) in parts.iter_mut().zip(dynamic.part_opacities()) {
+      *quantized = (opacity.clamp(0.0, 1.0) * f32::from(u8::MAX)).round() as u8;
+    }
+  }
+
+  fn dequantize_into(&self, dynamic: &mut ModelDynamic) {
+    assert!(
+      self.parameters.len() == dynamic.parameter_values().len() && self.parts.len() == dynamic.part_opacities().len(),
+      "Replication state does not match the model"
+    );
+    for ((value, &quantized), &(minimum, maximum)) in dynamic.parameter_values_mut().iter_mut().zip(self.parameters.iter()).zip(self.parameter_value_ranges.iter()) {
+      *value = minimum + f32::from(quantized) / f32::from(u16::MAX) * (maximum - minimum);
+    }
+    for (opacity, &quantized) in dynamic.part_opacities_mut().iter_mut().zip(self.parts.iter()) {
+      *opacity = f32::from(quantized) / f32::from(u8::MAX);
+    }
+  }
+}
+
+/// Encodes the state of a model into replication packets, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct ReplicationEncoder {
+  /// As of the last packet encoded.
+  sent: QuantizedState,
+  /// Scratch for the current state.
+  current: QuantizedState,
+  next_sequence: u16,
+  keyframe_interval: Option<u32>,
+  /// `None` until the first keyframe, or after [`ReplicationEncoder::force_keyframe`].
+  packets_since_keyframe: Option<u32>,
+}
+impl ReplicationEncoder {
+  /// Default of [`ReplicationEncoder::keyframe_interval`], a keyframe every second at 30 Hz.
+  pub const DEFAULT_KEYFRAME_INTERVAL: u32 = 30;
+
+  /// Creates an encoder for the model of `dynamic`, whose first packet is a keyframe.
+  pub fn new(dynamic: &ModelDynamic) -> Self {
+    let state = QuantizedState::new(dynamic);
+    Self {
+      sent: state.clone(),
+      current: state,
+      next_sequence: 0,
+      keyframe_interval: Some(Self::DEFAULT_KEYFRAME_INTERVAL),
+      packets_since_keyframe: None,
+    }
+  }
+
+  /// Number of packets from one keyframe to the next, or `None` to send keyframes only when forced.
+  pub fn keyframe_interval(&self) -> Option<u32> {
+    self.keyframe_interval
+  }
+  /// ## Panics
+  /// - If `keyframe_interval` is `Some(0)`.
+  pub fn set_keyframe_interval(&mut self, keyframe_interval: Option<u32>) {
+    assert_ne!(keyframe_interval, Some(0), "Keyframe interval must be positive");
+    self.keyframe_interval = keyframe_interval;
+  }
+  /// Makes the next packet a keyframe, e.g. when a peer joins or reports [`ReplicationError::MissingBaseline`].
+  pub fn force_keyframe(&mut self) {
+    self.packets_since_keyframe = None;
+  }
+  /// Sequence number of the next packet, wrapping around.
+  pub fn next_sequence(&self) -> u16 {
+    self.next_sequence
+  }
+
+  /// Encodes the current state of `dynamic` into a packet.
+  ///
+  /// ## Panics
+  /// - If `dynamic` belongs to a model of a different moc.
+  pub fn encode(&mut self, dynamic: &ModelDynamic) -> Vec<u8> {
+    let mut packet = Vec::new();
+    self.encode_into(dynamic, &mut packet);
+    packet
+  }
+  /// Like [`ReplicationEncoder::encode`], but writes into `out` to reuse its allocation. `out` is cleared first.
+  pub fn encode_into(&mut self, dynamic: &ModelDynamic, out: &mut Vec<u8>) {
+    self.current.quantize(dynamic);
+
+    let is_keyframe = match (self.packets_since_keyframe, self.keyframe_interval) {
+      (None, _) => true,
+      (Some(count), Some(interval)) => count + 1 >= interval,
+      (Some(_), None) => false,
+    };
+
+    out.clear();
+    out.push(VERSION << 1 | if is_keyframe { KEYFRAME_BIT } else { 0 });
+    out.extend_from_slice(&self.next_sequence.to_le_bytes());
+
+    let QuantizedState { parameters, parts, .. } = &self.current;
+    if is_keyframe {
+      write_varint(out, parameters.len() as u64);
+      write_varint(out, parts.len() as u64);
+      parameters.iter().for_each(|parameter| out.extend_from_slice(&parameter.to_le_bytes()));
+      out.extend_from_slice(parts);
+      self.packets_since_keyframe = Some(0);
+    } else {
+      let changed_parameters = || parameters.iter().zip(self.sent.parameters.iter()).enumerate().filter(|(_, (current, sent))| current != sent);
+      write_varint(out, changed_parameters().count() as u64);
+      let mut next_index = 0;
+      for (index, (&current, &sent)) in changed_parameters() {
+        write_varint(out, (index - next_index) as u64);
+        write_varint(out, zigzag_encode(i32::from(current) - i32::from(sent)));
+        next_index = index + 1;
+      }
+
+      let changed_parts = || parts.iter().zip(self.sent.parts.iter()).enumerate().filter(|(_, (current, sent))| current != sent);
+      write_varint(out, changed_parts().count() as u64);
+      let mut next_index = 0;
+      for (index, (&current, _)) in changed_parts() {
+        write_varint(out, (index - next_index) as u64);
+        out.push(current);
+        next_index = index + 1;
+      }
+      self.packets_since_keyframe = self.packets_since_keyframe.map(|count| count + 1);
+    }
+
+    std::mem::swap(&mut self.sent, &mut self.current);
+    self.next_sequence = self.next_sequence.wrapping_add(1);
+  }
+}
+
+/// Decodes replication packets from a [`ReplicationEncoder`] into the state of a model.
+#[derive(Debug, Clone)]
+pub struct ReplicationDecoder {
+  /// As of the last packet applied.
+  state: QuantizedState,
+  last_sequence: Option<u16>,
+}
+impl ReplicationDecoder {
+  /// Creates a decoder for the model of `dynamic`, which waits for a keyframe.
+  pub fn new(dynamic: &ModelDynamic) -> Self {
+    Self {
+      state: QuantizedState::new(dynamic),
+      last_sequence: None,
+    }
+  }
+
+  /// Sequence number of the last packet applied, `None` until the first keyframe.
+  pub fn last_sequence(&self) -> Option<u16> {
+    self.last_sequence
+  }
+  /// Discards the state received, waiting for a keyframe again.
+  pub fn reset(&mut self) {
+    self.last_sequence = None;
+  }
+
+  /// Decodes `packet` and writes the resulting parameter values and part opacities into `dynamic`,
+  /// returning the sequence number of the packet.
+  ///
+  /// On error, neither the decoder nor `dynamic` is modified.
+  ///
+  /// ## Panics
+  /// - If `dynamic` belongs to a model of a different moc than the decoder.
+  pub fn decode(&mut self, packet: &[u8], dynamic: &mut ModelDynamic) -> Result<u16, ReplicationError> {
+    let mut reader = PacketReader(packet);
+    let header = reader.read_u8()?;
+    if header >> 1 != VERSION {
+      return Err(ReplicationError::InvalidHeader);
+    }
+    let sequence = u16::from_le_bytes([reader.read_u8()?, reader.read_u8()?]);
+    if let Some(last_sequence) = self.last_sequence {
+      if (sequence.wrapping_sub(last_sequence) as i16) <= 0 {
+        return Err(ReplicationError::Stale { sequence });
+      }
+    }
+
+    let mut state = self.state.clone();
+    if header & KEYFRAME_BIT != 0 {
+      let parameter_count = reader.read_varint()? as usize;
+      let part_count = reader.read_varint()? as usize;
+      if parameter_count != state.parameters.len() || part_count != state.parts.len() {
+        return Err(ReplicationError::LayoutMismatch { parameter_count, part_count });
+      }
+      for parameter in state.parameters.iter_mut() {
+        *parameter = u16::from_le_bytes([reader.read_u8()?, reader.read_u8()?]);
+      }
+      for part in state.parts.iter_mut() {
+        *part = reader.read_u8()?;
+      }
+    } else {
+      let baseline = sequence.wrapping_sub(1);
+      if self.last_sequence != Some(baseline) {
+        return Err(ReplicationError::MissingBaseline { sequence, baseline });
+      }
+
+      let changed_count = reader.read_varint()?;
+      let mut next_index = 0;
+      for _ in 0..changed_count {
+        let index = reader.read_index_after(next_index)?;
+        let delta = zigzag_decode(reader.read_varint()?);
+        let parameter = state.parameters.get_mut(index).ok_or(ReplicationError::Malformed)?;
+        *parameter = u16::try_from(i64::from(*parameter) + delta).map_err(|_| ReplicationError::Malformed)?;
+        next_index = index + 1;
+      }
+
+      let changed_count = reader.read_varint()?;
+      let mut next_index = 0;
+      for _ in 0..changed_count {
+        let index = reader.read_index_after(next_index)?;
+        *state.parts.get_mut(index).ok_or(ReplicationError::Malformed)? = reader.read_u8()?;
+        next_index = index + 1;
+      }
+    }
+    if !reader.0.is_empty() {
+      return Err(ReplicationError::Malformed);
+    }
+
+    state.dequantize_into(dynamic);
+    self.state = state;
+    self.last_sequence = Some(sequence);
+    Ok(sequence)
+  }
+}
+
+struct PacketReader<'a>(&'a [u8]);
+impl PacketReader<'_> {
+  fn read_u8(&mut self) -> Result<u8, ReplicationError> {
+    let (&byte, rest) = self.0.split_first().ok_or(ReplicationError::Malformed)?;
+    self.0 = rest;
+    Ok(byte)
+  }
+  /// Reads an unsigned LEB128 varint.
+  fn read_varint(&mut self) -> Result<u64, ReplicationError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+      let byte = self.read_u8()?;
+      value |= u64::from(byte & 0x7f) << shift;
+      if byte & 0x80 == 0 {
+        return Ok(value);
+      }
+    }
+    Err(ReplicationError::Malformed)
+  }
+  /// Reads an index encoded as a varint gap from `next_index`.
+  fn read_index_after(&mut self, next_index: usize) -> Result<usize, ReplicationError> {
+    let gap = self.read_varint()?;
+    usize::try_from(gap).ok()
+      .and_then(|gap| next_index.checked_add(gap))
+      .ok_or(ReplicationError::Malformed)
+  }
+}
+
+/// Writes an unsigned LEB128 varint.
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+  while value >= 0x80 {
+    out.push(value as u8 | 0x80);
+    value >>= 7;
+  }
+  out.push(value as u8);
+}
+fn zigzag_encode(value: i32) -> u64 {
+  u64::from(((value << 1) ^ (value >> 31)) as u32)
+}
+fn zigzag_decode(value: u64) -> i64 {
+  (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+  use crate::core::{CubismCore, Model, MockMoc};
+
+  use super::*;
+
+  fn model() -> Model {
+    let moc = CubismCore::default().moc_from_bytes(&MockMoc { parameter_count: 40, ..Default::default() }.to_bytes()).unwrap();
+    Model::from_moc(&moc)
+  }
+
+  #[test]
+  fn round_trip() {
+    let (sender, receiver) = (model(), model());
+    let (mut sender_dynamic, mut receiver_dynamic) = (sender.write_dynamic(), receiver.write_dynamic());
+    let mut encoder = ReplicationEncoder::new(&sender_dynamic);
+    encoder.set_keyframe_interval(Some(3));
+    let mut decoder = ReplicationDecoder::new(&receiver_dynamic);
+
+    for frame in 0..8u16 {
+      for (index, value) in sender_dynamic.parameter_values_mut().iter_mut().enumerate() {
+        if index % 3 == usize::from(frame) % 3 {
+          *value = (index as f32 * 0.37 + f32::from(frame) * 0.11).sin() * 0.5;
+        }
+      }
+      sender_dynamic.part_opacities_mut()[usize::from(frame) % 2] = f32::from(frame) / 8.0;
+
+      let packet = encoder.encode(&sender_dynamic);
+      assert_eq!(packet[0] & KEYFRAME_BIT != 0, frame % 3 == 0);
+      assert_eq!(decoder.decode(&packet, &mut receiver_dynamic), Ok(frame));
+
+      for (sent, received) in sender_dynamic.parameter_values().iter().zip(receiver_dynamic.parameter_values()) {
+        assert!((sent - received).abs() < 1e-4, "sent: {sent} received: {received}");
+      }
+      for (sent, received) in sender_dynamic.part_opacities().iter().zip(receiver_dynamic.part_opacities()) {
+        assert!((sent - received).abs() < 1.0 / 255.0);
+      }
+    }
+  }
+
+  #[test]
+  fn missing_baseline_and_stale() {
+    let model = model();
+    let mut dynamic = model.write_dynamic();
+    let mut encoder = ReplicationEncoder::new(&dynamic);
+    let mut decoder = ReplicationDecoder::new(&dynamic);
+
+    let keyframe = encoder.encode(&dynamic);
+    let delta = encoder.encode(&dynamic);
+    assert_eq!(decoder.decode(&delta, &mut dynamic), Err(ReplicationError::MissingBaseline { sequence: 1, baseline: 0 }));
+    assert_eq!(decoder.last_sequence(), None);
+
+    assert_eq!(decoder.decode(&keyframe, &mut dynamic), Ok(0));
+    assert_eq!(decoder.decode(&delta, &mut dynamic), Ok(1));
+    assert_eq!(decoder.decode(&delta, &mut dynamic), Err(ReplicationError::Stale { sequence: 1 }));
+    assert_eq!(decoder.decode(&keyframe, &mut dynamic), Err(ReplicationError::Stale { sequence: 0 }));
+
+    let lost = encoder.encode(&dynamic);
+    let after_loss = encoder.encode(&dynamic);
+    assert!(matches!(decoder.decode(&after_loss, &mut dynamic), Err(ReplicationError::MissingBaseline { .. })));
+    assert_eq!(decoder.decode(&lost, &mut dynamic), Ok(2));
+  }
+
+  #[test]
+  fn malformed() {
+    let model = model();
+    let mut dynamic = model.write_dynamic();
+    let mut encoder = ReplicationEncoder::new(&dynamic);
+    let mut decoder = ReplicationDecoder::new(&dynamic);
+    let keyframe = encoder.encode(&dynamic);
+    assert_eq!(decoder.decode(&keyframe, &mut dynamic), Ok(0));
+    let values = dynamic.parameter_values().to_vec();
+
+    let delta_header = [VERSION << 1, 1, 0];
+    // The second index overflows past the first.
+    let mut huge_gap = delta_header.to_vec();
+    huge_gap.push(2);
+    for gap in [0, u64::MAX] {
+      write_varint(&mut huge_gap, gap);
+      write_varint(&mut huge_gap, 0);
+    }
+    write_varint(&mut huge_gap, 0);
+
+    let mut out_of_range = delta_header.to_vec();
+    out_of_range.push(1);
+    write_varint(&mut out_of_range, 1000);
+    write_varint(&mut out_of_range, 0);
+    write_varint(&mut out_of_range, 0);
+
+    let mut overflowing_delta = delta_header.to_vec();
+    overflowing_delta.push(1);
+    write_varint(&mut overflowing_delta, 0);
+    write_varint(&mut overflowing_delta, zigzag_encode(i32::from(u16::MAX) + 1));
+    write_varint(&mut overflowing_delta, 0);
+
+    // Newer keyframes, so that they are not rejected as stale before being parsed.
+    let mut truncated = keyframe.clone();
+    truncated[1] = 1;
+    truncated.pop();
+    let mut trailing = keyframe.clone();
+    trailing[1] = 1;
+    trailing.push(0);
+
+    for packet in [&[][..], &[0xff, 1, 0], &truncated, &huge_gap, &out_of_range, &overflowing_delta, &trailing, &[VERSION << 1, 1, 0, 0x80]] {
+      let err = decoder.decode(packet, &mut dynamic).expect_err("malformed packet should be rejected");
+      assert!(matches!(err, ReplicationError::Malformed | ReplicationError::InvalidHeader), "{err:?}");
+    }
+    assert_eq!(decoder.last_sequence(), Some(0));
+    assert_eq!(dynamic.parameter_values(), values);
+
+    let mut layout_mismatch = vec![VERSION << 1 | KEYFRAME_BIT, 1, 0];
+    write_varint(&mut layout_mismatch, 1);
+    write_varint(&mut layout_mismatch, 0);
+    layout_mismatch.extend_from_slice(&[0, 0]);
+    assert_eq!(decoder.decode(&layout_mismatch, &mut dynamic), Err(ReplicationError::LayoutMismatch { parameter_count: 1, part_count: 0 }));
+  }
+
+  #[test]
+  fn varint() {
+    for value in [0, 1, 127, 128, 300, u64::from(u32::MAX), u64::MAX] {
+      let mut bytes = Vec::new();
+      write_varint(&mut bytes, value);
+      let mut reader = PacketReader(&bytes);
+      assert_eq!(reader.read_varint(), Ok(value));
+      assert!(reader.0.is_empty());
+    }
+    for value in [0, 1, -1, i32::from(u16::MAX), -i32::from(u16::MAX)] {
+      assert_eq!(zigzag_decode(zigzag_encode(value)), i64::from(value));
+    }
+  }
+}
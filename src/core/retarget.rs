@@ -0,0 +1,120 @@
+//! Reusing parameter animation authored for one model on another, e.g. a motion pack shared between characters.
+
+use std::collections::HashMap;
+
+use super::base_types::ParameterIndex;
+use super::{ModelStatic, ModelDynamic, Parameter};
+
+/// How source parameter values are converted for the target model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RangeMapping {
+  /// Values are copied as is.
+  Identity,
+  /// Values are rescaled so that the default value and the minimum and maximum of the source map onto those of the target,
+  /// each side of the default value separately.
+  #[default]
+  Rescale,
+}
+
+/// Maps the parameters of a source model onto a target model, by ID or through aliases.
+///
+/// The source values can come from a model of the source moc, a [`super::ParameterRecorder`] recorded from one, etc.
+#[derive(Debug, Clone)]
+pub struct ParameterRetargeter {
+  mappings: Vec<Mapping>,
+  unmapped: Vec<String>,
+  range_mapping: RangeMapping,
+}
+impl ParameterRetargeter {
+  /// * `aliases` - Target parameter ID per source parameter ID, for parameters named differently in the target.
+  ///   Parameters without an alias are matched by their own ID.
+  pub fn new(source: &ModelStatic, target: &ModelStatic, aliases: &HashMap<String, String>, range_mapping: RangeMapping) -> Self {
+    let mut mappings = Vec::new();
+    let mut unmapped = Vec::new();
+
+    for (source_index, source_parameter) in source.parameters().iter().enumerate() {
+      let target_id = aliases.get(source_parameter.id()).map_or(source_parameter.id(), String::as_str);
+      match target.parameter_index(target_id) {
+        Some(target_index) => mappings.push(Mapping {
+          source: ParameterIndex::from(source_index),
+          target: target_index,
+          source_range: ValueRange::of(source_parameter),
+          target_range: ValueRange::of(&target[target_index]),
+        }),
+        None => unmapped.push(source_parameter.id().to_owned()),
+      }
+    }
+
+    Self {
+      mappings,
+      unmapped,
+      range_mapping,
+    }
+  }
+
+  /// IDs of the source parameters without a counterpart in the target, in source order.
+  pub fn unmapped(&self) -> &[String] {
+    &self.unmapped
+  }
+  pub fn mapped_count(&self) -> usize {
+    self.mappings.len()
+  }
+
+  /// Converts the value of the source parameter at `source_index`, or returns `None` if it is unmapped.
+  pub fn map_value(&self, source_index: ParameterIndex, value: f32) -> Option<(ParameterIndex, f32)> {
+    self.mappings.iter()
+      .find(|mapping| mapping.source == source_index)
+      .map(|mapping| (mapping.target, self.convert(value, mapping)))
+  }
+
+  /// Writes the converted `source_values`, one per source parameter, into `target`.
+  pub fn apply(&self, source_values: &[f32], target: &mut ModelDynamic) {
+    for mapping in &self.mappings {
+      if let Some(&value) = source_values.get(mapping.source.as_usize()) {
+        target[mapping.target] = self.convert(value, mapping);
+      }
+    }
+  }
+
+  fn convert(&self, value: f32, mapping: &Mapping) -> f32 {
+    let (source, target) = (&mapping.source_range, &mapping.target_range);
+    match self.range_mapping {
+      RangeMapping::Identity => value,
+      RangeMapping::Rescale => {
+        let rescale = |source_extent: f32, target_extent: f32| {
+          if source_extent == source.default {
+            target.default
+          } else {
+            target.default + (value - source.default) / (source_extent - source.default) * (target_extent - target.default)
+          }
+        };
+        if value >= source.default { rescale(source.maximum, target.maximum) } else { rescale(source.minimum, target.minimum) }
+      }
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+struct Mapping {
+  source: ParameterIndex,
+  target: ParameterIndex,
+  source_range: ValueRange,
+  target_range: ValueRange,
+}
+
+#[derive(Debug, Clone)]
+struct ValueRange {
+  minimum: f32,
+  default: f32,
+  maximum: f32,
+}
+impl ValueRange {
+  fn of(parameter: &Parameter) -> Self {
+    let (minimum, maximum) = parameter.value_range();
+    Self {
+      minimum,
+      default: parameter.default_value(),
+      maximum,
+    }
+  }
+}
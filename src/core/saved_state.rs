@@ -0,0 +1,242 @@
+//! Persisting the pose of a model between sessions, e.g. a character's expression in an avatar app.
+
+use thiserror::Error;
+
+use super::builder::copy_by_id;
+use super::{ModelStatic, ModelDynamic};
+
+/// Errors generated when decoding a [`SavedState`].
+#[derive(Debug, Error)]
+pub enum SavedStateError {
+  #[error("Not a saved state.")]
+  InvalidMagic,
+  #[error("Unsupported saved state version {0}. Latest supported is {latest}.", latest = SavedState::VERSION)]
+  UnsupportedVersion(u32),
+  #[error("Saved state is truncated.")]
+  UnexpectedEnd,
+  #[error("Saved state contains an ID that is not valid UTF-8.")]
+  InvalidId,
+  #[cfg(feature = "serde")]
+  #[error("Failed to parse saved state JSON: {0}")]
+  Json(#[from] serde_json::Error),
+}
+
+/// Parameter values and part opacities of a model, keyed by ID so that they can be restored into a model of a different revision of the moc.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SavedState {
+  parameter_ids: Vec<String>,
+  parameter_values: Vec<f32>,
+  part_ids: Vec<String>,
+  part_opacities: Vec<f32>,
+}
+impl SavedState {
+  /// Current version of the binary and JSON formats.
+  pub const VERSION: u32 = 1;
+  const MAGIC: [u8; 4] = *b"L2DS";
+
+  pub fn capture(model_static: &ModelStatic, dynamic: &ModelDynamic) -> Self {
+    Self {
+      parameter_ids: model_static.parameters().iter().map(|parameter| parameter.id().to_owned()).collect(),
+      parameter_values: dynamic.parameter_values().to_vec(),
+      part_ids: model_static.parts().iter().map(|part| part.id().to_owned()).collect(),
+      part_opacities: dynamic.part_opacities().to_vec(),
+    }
+  }
+
+  /// Restores the values into `dynamic` by ID. Parameters and parts not in the saved state are left untouched.
+  ///
+  /// [`ModelDynamic::update`] needs to be called afterwards for the drawables to reflect the restored state.
+  pub fn restore(&self, model_static: &ModelStatic, dynamic: &mut ModelDynamic) {
+    copy_by_id(
+      self.parameter_ids.iter().map(String::as_str),
+      &self.parameter_values,
+      model_static.parameters().iter().map(|parameter| parameter.id()),
+      dynamic.parameter_values_mut(),
+    );
+    copy_by_id(
+      self.part_ids.iter().map(String::as_str),
+      &self.part_opacities,
+      model_static.parts().iter().map(|part| part.id()),
+      dynamic.part_opacities_mut(),
+    );
+  }
+
+  pub fn parameters(&self) -> impl Iterator<Item = (&str, f32)> {
+    self.parameter_ids.iter().map(String::as_str).zip(self.parameter_values.iter().copied())
+  }
+  pub fn parts(&self) -> impl Iterator<Item = (&str, f32)> {
+    self.part_ids.iter().map(String::as_str).zip(self.part_opacities.iter().copied())
+  }
+
+  /// Encodes into a compact little-endian binary form.
+  ///
+  /// Layout: `"L2DS"`, version as `u32`, then parameters and parts, each as a `u32` count followed by
+  /// entries of a `u32` ID byte length, the UTF-8 ID and an `f32` value.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&Self::MAGIC);
+    bytes.extend_from_slice(&Self::VERSION.to_le_bytes());
+    for (ids, values) in [(&self.parameter_ids, &self.parameter_values), (&self.part_ids, &self.part_opacities)] {
+      bytes.extend_from_slice(&(ids.len() as u32).to_le_bytes());
+      for (id, value) in ids.iter().zip(values.iter()) {
+        bytes.extend_from_slice(&(id.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(id.as_bytes());
+        bytes.extend_from_slice(&value.to_le_bytes());
+      }
+    }
+    bytes
+  }
+  /// Decodes from the form written by [`SavedState::to_bytes`].
+  pub fn from_bytes(bytes: &[u8]) -> Result<Self, SavedStateError> {
+    let mut reader = ByteReader { bytes };
+    if reader.take(4)? != Self::MAGIC {
+      return Err(SavedStateError::InvalidMagic);
+    }
+    let version = reader.read_u32()?;
+    if version == 0 || version > Self::VERSION {
+      return Err(SavedStateError::UnsupportedVersion(version));
+    }
+
+    let (parameter_ids, parameter_values) = reader.read_entries()?;
+    let (part_ids, part_opacities) = reader.read_entries()?;
+    Ok(Self {
+      parameter_ids,
+      parameter_values,
+      part_ids,
+      part_opacities,
+    })
+  }
+
+  /// Serializes as JSON, e.g. `{"Version":1,"Parameters":[{"Id":"ParamAngleX","Value":15.0}],"Parts":[...]}`.
+  #[cfg(feature = "serde")]
+  pub fn to_json(&self) -> String {
+    let json = json::SavedStateJson {
+      version: Self::VERSION,
+      parameters: self.parameters().map(|(id, value)| json::Entry { id: id.to_owned(), value }).collect(),
+      parts: self.parts().map(|(id, value)| json::Entry { id: id.to_owned(), value }).collect(),
+    };
+    serde_json::to_string_pretty(&json).expect("Serializing a saved state should not fail")
+  }
+  /// Deserializes from the JSON written by [`SavedState::to_json`].
+  #[cfg(feature = "serde")]
+  pub fn from_json(json: &str) -> Result<Self, SavedStateError> {
+    let json: json::SavedStateJson = serde_json::from_str(json)?;
+    if json.version == 0 || json.version > Self::VERSION {
+      return Err(SavedStateError::UnsupportedVersion(json.version));
+    }
+
+    let (parameter_ids, parameter_values) = json.parameters.into_iter().map(|entry| (entry.id, entry.value)).unzip();
+    let (part_ids, part_opacities) = json.parts.into_iter().map(|entry| (entry.id, entry.value)).unzip();
+    Ok(Self {
+      parameter_ids,
+      parameter_values,
+      part_ids,
+      part_opacities,
+    })
+  }
+}
+
+struct ByteReader<'a> {
+  bytes: &'a [u8],
+}
+impl<'a> ByteReader<'a> {
+  fn take(&mut self, len: usize) -> Result<&'a [u8], SavedStateError> {
+    if self.bytes.len() < len {
+      return Err(SavedStateError::UnexpectedEnd);
+    }
+    let (taken, rest) = self.bytes.split_at(len);
+    self.bytes = rest;
+    Ok(taken)
+  }
+  fn read_u32(&mut self) -> Result<u32, SavedStateError> {
+    Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+  }
+  fn read_entries(&mut self) -> Result<(Vec<String>, Vec<f32>), SavedStateError> {
+    let count = self.read_u32()? as usize;
+    // Not trusting `count` for the capacity, as the bytes may be corrupted.
+    let mut ids = Vec::new();
+    let mut values = Vec::new();
+    for _ in 0..count {
+      let id_len = self.read_u32()? as usize;
+      let id = std::str::from_utf8(self.take(id_len)?).map_err(|_| SavedStateError::InvalidId)?;
+      ids.push(id.to_owned());
+      values.push(f32::from_le_bytes(self.take(4)?.try_into().unwrap()));
+    }
+    Ok((ids, values))
+  }
+}
+
+/// Serialized form of the JSON saved state.
+#[cfg(feature = "serde")]
+mod json {
+  use serde::{Serialize, Deserialize};
+
+  #[derive(Debug, Serialize, Deserialize)]
+  #[serde(rename_all = "PascalCase")]
+  pub struct SavedStateJson {
+    pub version: u32,
+    pub parameters: Vec<Entry>,
+    pub parts: Vec<Entry>,
+  }
+
+  #[derive(Debug, Serialize, Deserialize)]
+  #[serde(rename_all = "PascalCase")]
+  pub struct Entry {
+    pub id: String,
+    pub value: f32,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn saved_state() -> SavedState {
+    SavedState {
+      parameter_ids: vec!["ParamAngleX".to_owned(), "ParamEyeLOpen".to_owned()],
+      parameter_values: vec![15.0, 0.5],
+      part_ids: vec!["PartArm".to_owned()],
+      part_opacities: vec![0.0],
+    }
+  }
+
+  #[test]
+  fn bytes_round_trip() {
+    let state = saved_state();
+    assert_eq!(SavedState::from_bytes(&state.to_bytes()).unwrap(), state);
+  }
+
+  #[test]
+  fn bytes_truncated() {
+    let bytes = saved_state().to_bytes();
+    for len in 4..bytes.len() {
+      assert!(matches!(SavedState::from_bytes(&bytes[..len]), Err(SavedStateError::UnexpectedEnd)), "{len}");
+    }
+    assert!(SavedState::from_bytes(&bytes[..3]).is_err());
+  }
+
+  #[test]
+  fn bytes_corrupted() {
+    let bytes = saved_state().to_bytes();
+
+    let mut corrupted = bytes.clone();
+    corrupted[0] = b'X';
+    assert!(matches!(SavedState::from_bytes(&corrupted), Err(SavedStateError::InvalidMagic)));
+
+    for version in [0, SavedState::VERSION + 1] {
+      let mut corrupted = bytes.clone();
+      corrupted[4..8].copy_from_slice(&version.to_le_bytes());
+      assert!(matches!(SavedState::from_bytes(&corrupted), Err(SavedStateError::UnsupportedVersion(v)) if v == version));
+    }
+
+    // The first byte of the first parameter ID.
+    let mut corrupted = bytes.clone();
+    corrupted[16] = 0xff;
+    assert!(matches!(SavedState::from_bytes(&corrupted), Err(SavedStateError::InvalidId)));
+
+    // A huge parameter count fails on the missing entries rather than allocating for them.
+    let mut corrupted = bytes;
+    corrupted[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(SavedState::from_bytes(&corrupted), Err(SavedStateError::UnexpectedEnd)));
+  }
+}
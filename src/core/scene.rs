@@ -0,0 +1,238 @@
+//! Composition of several models into a single draw order, e.g. a character and accessories loaded as separate models.
+
+use std::sync::Arc;
+
+use super::base_types::{Vector2, Vector4, DrawableIndex};
+use super::{Model, Moc};
+
+/// Placement of a model in a [`Scene`]: scaled, then rotated, then translated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SceneTransform {
+  /// In scene units.
+  pub translation: Vector2,
+  pub scale: Vector2,
+  /// Counter-clockwise, in radians.
+  pub rotation: f32,
+}
+impl Default for SceneTransform {
+  fn default() -> Self {
+    Self {
+      translation: Vector2 { x: 0.0, y: 0.0 },
+      scale: Vector2 { x: 1.0, y: 1.0 },
+      rotation: 0.0,
+    }
+  }
+}
+impl SceneTransform {
+  /// Transforms `position` from model units into scene units.
+  pub fn apply(&self, position: Vector2) -> Vector2 {
+    let (sin, cos) = self.rotation.sin_cos();
+    let (x, y) = (position.x * self.scale.x, position.y * self.scale.y);
+    Vector2 {
+      x: x * cos - y * sin + self.translation.x,
+      y: x * sin + y * cos + self.translation.y,
+    }
+  }
+  /// The transform as a column-major 3x3 matrix, e.g. for a shader uniform.
+  pub fn to_matrix(&self) -> [f32; 9] {
+    let (sin, cos) = self.rotation.sin_cos();
+    [
+      cos * self.scale.x, sin * self.scale.x, 0.0,
+      -sin * self.scale.y, cos * self.scale.y, 0.0,
+      self.translation.x, self.translation.y, 1.0,
+    ]
+  }
+}
+
+/// Identifies a model in a [`Scene`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SceneModelId(u64);
+
+#[derive(Debug)]
+struct SceneEntry {
+  id: SceneModelId,
+  model: Arc<Model>,
+  z_order: i32,
+  transform: SceneTransform,
+}
+
+/// An element of [`Scene::draw_list`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SceneDrawItem {
+  pub model_id: SceneModelId,
+  pub drawable_index: DrawableIndex,
+}
+
+/// Per-instance data of a [`SceneDrawBatch`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SceneDrawInstance {
+  pub model_id: SceneModelId,
+  pub transform: SceneTransform,
+  pub opacity: f32,
+  pub multiply_color: Vector4,
+  pub screen_color: Vector4,
+}
+
+/// An element of [`Scene::batched_draw_list`]: one drawable drawn once per instance, e.g. with GPU instancing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneDrawBatch {
+  /// The model whose vertex positions every instance is drawn with.
+  pub source_model_id: SceneModelId,
+  pub drawable_index: DrawableIndex,
+  /// Back to front.
+  pub instances: Vec<SceneDrawInstance>,
+}
+
+/// A set of models, each with a z-order and a [`SceneTransform`].
+#[derive(Debug, Default)]
+pub struct Scene {
+  /// Sorted by z-order, then by insertion.
+  entries: Vec<SceneEntry>,
+  next_id: u64,
+}
+impl Scene {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Adds `model`, drawn after models with a lower `z_order` and after existing models with the same `z_order`.
+  pub fn add(&mut self, model: Arc<Model>, z_order: i32, transform: SceneTransform) -> SceneModelId {
+    let id = SceneModelId(self.next_id);
+    self.next_id += 1;
+
+    let position = self.entries.partition_point(|entry| entry.z_order <= z_order);
+    self.entries.insert(position, SceneEntry { id, model, z_order, transform });
+    id
+  }
+  pub fn remove(&mut self, id: SceneModelId) -> Option<Arc<Model>> {
+    let position = self.entries.iter().position(|entry| entry.id == id)?;
+    Some(self.entries.remove(position).model)
+  }
+
+  pub fn model(&self, id: SceneModelId) -> Option<&Arc<Model>> {
+    self.entry(id).map(|entry| &entry.model)
+  }
+  pub fn transform(&self, id: SceneModelId) -> Option<&SceneTransform> {
+    self.entry(id).map(|entry| &entry.transform)
+  }
+  pub fn set_transform(&mut self, id: SceneModelId, transform: SceneTransform) {
+    if let Some(entry) = self.entries.iter_mut().find(|entry| entry.id == id) {
+      entry.transform = transform;
+    }
+  }
+  pub fn z_order(&self, id: SceneModelId) -> Option<i32> {
+    self.entry(id).map(|entry| entry.z_order)
+  }
+  /// Moves the model after the existing models with the same `z_order`.
+  pub fn set_z_order(&mut self, id: SceneModelId, z_order: i32) {
+    let Some(position) = self.entries.iter().position(|entry| entry.id == id) else {
+      return;
+    };
+    let mut entry = self.entries.remove(position);
+    entry.z_order = z_order;
+    let position = self.entries.partition_point(|existing| existing.z_order <= z_order);
+    self.entries.insert(position, entry);
+  }
+
+  /// Models in drawing order.
+  pub fn iter(&self) -> impl Iterator<Item = (SceneModelId, &Arc<Model>)> {
+    self.entries.iter().map(|entry| (entry.id, &entry.model))
+  }
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  /// The drawables of all models to draw, back to front: by model z-order, then by [`super::ModelDynamic::drawing_sequence`] within each model.
+  ///
+  /// Drawables not drawn according to [`super::ModelDynamic::is_drawable_drawn`] are skipped.
+  /// Acquires a read lock for the [`super::ModelDynamic`] of each model in turn.
+  pub fn draw_list(&self) -> Vec<SceneDrawItem> {
+    let mut draw_list = Vec::new();
+    self.write_draw_list(&mut draw_list);
+    draw_list
+  }
+  /// Like [`Scene::draw_list`], but writes into `out` to reuse its allocation. `out` is cleared first.
+  pub fn write_draw_list(&self, out: &mut Vec<SceneDrawItem>) {
+    out.clear();
+
+    let mut drawable_indices = Vec::new();
+    for entry in &self.entries {
+      let dynamic = entry.model.read_dynamic();
+
+      dynamic.write_drawing_sequence(&mut drawable_indices);
+      drawable_indices.retain(|&index| dynamic.is_drawable_drawn(index));
+
+      out.extend(drawable_indices.iter().map(|&drawable_index| SceneDrawItem { model_id: entry.id, drawable_index }));
+    }
+  }
+
+  /// Like [`Scene::draw_list`], but merges models adjacent in z-order into instanced batches, e.g. for background crowds.
+  ///
+  /// Models are merged if they share the same [`super::Moc`], draw the same drawables in the same sequence,
+  /// and their vertex positions differ by at most `vertex_position_tolerance` in model units.
+  /// Transforms, opacities and colors may differ per instance.
+  ///
+  /// Within a batch, instances are layered drawable by drawable rather than model by model,
+  /// which only shows where they overlap.
+  pub fn batched_draw_list(&self, vertex_position_tolerance: f32) -> Vec<SceneDrawBatch> {
+    let mut batches = Vec::new();
+    self.write_batched_draw_list(vertex_position_tolerance, &mut batches);
+    batches
+  }
+  /// Like [`Scene::batched_draw_list`], but writes into `out`. `out` is cleared first.
+  pub fn write_batched_draw_list(&self, vertex_position_tolerance: f32, out: &mut Vec<SceneDrawBatch>) {
+    out.clear();
+
+    // The current group is `out[group_start..]`, one batch per drawable drawn by its first model.
+    let mut group_start = 0;
+    let mut group_model: Option<&Arc<Model>> = None;
+    let mut group_positions: Vec<Vec<Vector2>> = Vec::new();
+    let mut drawable_indices = Vec::new();
+    for entry in &self.entries {
+      let dynamic = entry.model.read_dynamic();
+
+      dynamic.write_drawing_sequence(&mut drawable_indices);
+      drawable_indices.retain(|&index| dynamic.is_drawable_drawn(index));
+
+      let vertex_positions = dynamic.drawable_vertex_position_containers();
+      let joins_group = group_model.is_some_and(|group_model| {
+        Moc::ptr_eq(group_model.moc(), entry.model.moc())
+          && out.len() - group_start == drawable_indices.len()
+          && out[group_start..].iter().zip(drawable_indices.iter()).zip(group_positions.iter())
+            .all(|((batch, &drawable_index), group_positions)| {
+              batch.drawable_index == drawable_index
+                && group_positions.iter().zip(vertex_positions[drawable_index.as_usize()].iter())
+                  .all(|(a, b)| (a.x - b.x).abs() <= vertex_position_tolerance && (a.y - b.y).abs() <= vertex_position_tolerance)
+            })
+      });
+      if !joins_group {
+        group_start = out.len();
+        group_model = Some(&entry.model);
+        group_positions.clear();
+        group_positions.extend(drawable_indices.iter().map(|&drawable_index| vertex_positions[drawable_index.as_usize()].to_vec()));
+        out.extend(drawable_indices.iter().map(|&drawable_index| SceneDrawBatch {
+          source_model_id: entry.id,
+          drawable_index,
+          instances: Vec::new(),
+        }));
+      }
+
+      for (batch, &drawable_index) in out[group_start..].iter_mut().zip(drawable_indices.iter()) {
+        batch.instances.push(SceneDrawInstance {
+          model_id: entry.id,
+          transform: entry.transform,
+          opacity: dynamic.drawable_opacities()[drawable_index.as_usize()],
+          multiply_color: dynamic.drawable_multiply_colors()[drawable_index.as_usize()],
+          screen_color: dynamic.drawable_screen_colors()[drawable_index.as_usize()],
+        });
+      }
+    }
+  }
+
+  fn entry(&self, id: SceneModelId) -> Option<&SceneEntry> {
+    self.entries.iter().find(|entry| entry.id == id)
+  }
+}
@@ -0,0 +1,187 @@
+//! Comparison of the static descriptions of two mocs, e.g. an updated character against the one its motions were made for.
+//!
+//! Parameters, parts and drawables are matched by ID, since indices are not stable across edits.
+
+use flagset::{FlagSet, flags};
+
+use super::ModelStatic;
+
+flags! {
+  /// Aspects in which a parameter differs between two mocs.
+  pub enum ParameterSchemaChanges: u8 {
+    /// [`super::ParameterType`].
+    Kind,
+    ValueRange,
+    DefaultValue,
+    /// Key values, which only matter to editors.
+    Keys,
+  }
+}
+
+pub type ParameterSchemaChangeSet = FlagSet<ParameterSchemaChanges>;
+
+flags! {
+  /// Aspects in which a drawable differs between two mocs.
+  pub enum DrawableSchemaChanges: u8 {
+    /// Constant flags, i.e. blend mode, double-sidedness or mask inversion.
+    ConstantFlags,
+    Texture,
+    /// The drawables masking it, by ID.
+    Masks,
+    /// Vertex count, UVs or triangles.
+    Mesh,
+    /// The parent part, by ID.
+    ParentPart,
+  }
+}
+
+pub type DrawableSchemaChangeSet = FlagSet<DrawableSchemaChanges>;
+
+/// Result of [`SchemaDiff::new`]. All lists are of IDs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDiff {
+  added_parameters: Vec<String>,
+  removed_parameters: Vec<String>,
+  changed_parameters: Vec<(String, ParameterSchemaChangeSet)>,
+  added_parts: Vec<String>,
+  removed_parts: Vec<String>,
+  /// Parts whose parent part changed.
+  reparented_parts: Vec<String>,
+  added_drawables: Vec<String>,
+  removed_drawables: Vec<String>,
+  changed_drawables: Vec<(String, DrawableSchemaChangeSet)>,
+}
+impl SchemaDiff {
+  /// Compares `new` against `old`.
+  ///
+  /// Added items are listed in the order of `new`, removed and changed ones in the order of `old`.
+  pub fn new(old: &ModelStatic, new: &ModelStatic) -> Self {
+    let mut diff = Self::default();
+
+    for old_parameter in old.parameters() {
+      let Some(new_parameter) = new.parameters().iter().find(|parameter| parameter.id() == old_parameter.id()) else {
+        diff.removed_parameters.push(old_parameter.id().to_owned());
+        continue;
+      };
+      let mut changes = ParameterSchemaChangeSet::default();
+      if old_parameter.ty() != new_parameter.ty() {
+        changes |= ParameterSchemaChanges::Kind;
+      }
+      if old_parameter.value_range() != new_parameter.value_range() {
+        changes |= ParameterSchemaChanges::ValueRange;
+      }
+      if old_parameter.default_value() != new_parameter.default_value() {
+        changes |= ParameterSchemaChanges::DefaultValue;
+      }
+      if old_parameter.keys() != new_parameter.keys() {
+        changes |= ParameterSchemaChanges::Keys;
+      }
+      if !changes.is_empty() {
+        diff.changed_parameters.push((old_parameter.id().to_owned(), changes));
+      }
+    }
+    diff.added_parameters = new.parameters().iter()
+      .filter(|new_parameter| old.parameter_index(new_parameter.id()).is_none())
+      .map(|new_parameter| new_parameter.id().to_owned())
+      .collect();
+
+    let part_id = |model_static: &ModelStatic, index: Option<usize>| index.map(|index| model_static.parts()[index].id().to_owned());
+    for old_part in old.parts() {
+      match new.part_index(old_part.id()) {
+        None => diff.removed_parts.push(old_part.id().to_owned()),
+        Some(new_index) => {
+          let new_part = &new.parts()[new_index.as_usize()];
+          if part_id(old, old_part.parent_part_index()) != part_id(new, new_part.parent_part_index()) {
+            diff.reparented_parts.push(old_part.id().to_owned());
+          }
+        },
+      }
+    }
+    diff.added_parts = new.parts().iter()
+      .filter(|new_part| old.part_index(new_part.id()).is_none())
+      .map(|new_part| new_part.id().to_owned())
+      .collect();
+
+    let mask_ids = |model_static: &ModelStatic, masks: &[usize]| -> Vec<String> {
+      masks.iter().map(|&index| model_static.drawables()[index].id().to_owned()).collect()
+    };
+    for old_drawable in old.drawables() {
+      let Some(new_drawable) = new.drawables().iter().find(|drawable| drawable.id() == old_drawable.id()) else {
+        diff.removed_drawables.push(old_drawable.id().to_owned());
+        continue;
+      };
+      let mut changes = DrawableSchemaChangeSet::default();
+      if old_drawable.constant_flagset() != new_drawable.constant_flagset() {
+        changes |= DrawableSchemaChanges::ConstantFlags;
+      }
+      if old_drawable.texture_index() != new_drawable.texture_index() {
+        changes |= DrawableSchemaChanges::Texture;
+      }
+      if mask_ids(old, old_drawable.masks()) != mask_ids(new, new_drawable.masks()) {
+        changes |= DrawableSchemaChanges::Masks;
+      }
+      if old_drawable.vertex_uvs() != new_drawable.vertex_uvs() || old_drawable.triangle_indices() != new_drawable.triangle_indices() {
+        changes |= DrawableSchemaChanges::Mesh;
+      }
+      if part_id(old, old_drawable.parent_part_index()) != part_id(new, new_drawable.parent_part_index()) {
+        changes |= DrawableSchemaChanges::ParentPart;
+      }
+      if !changes.is_empty() {
+        diff.changed_drawables.push((old_drawable.id().to_owned(), changes));
+      }
+    }
+    diff.added_drawables = new.drawables().iter()
+      .filter(|new_drawable| old.drawables().iter().all(|old_drawable| old_drawable.id() != new_drawable.id()))
+      .map(|new_drawable| new_drawable.id().to_owned())
+      .collect();
+
+    diff
+  }
+
+  pub fn is_empty(&self) -> bool {
+    *self == Self::default()
+  }
+  /// Whether motions made for the old moc still target only parameters and parts of the new one,
+  /// with parameters of the same type and range.
+  pub fn is_motion_compatible(&self) -> bool {
+    self.removed_parameters.is_empty()
+      && self.removed_parts.is_empty()
+      && self.changed_parameters.iter().all(|(_, changes)| (*changes & (ParameterSchemaChanges::Kind | ParameterSchemaChanges::ValueRange)).is_empty())
+  }
+
+  pub fn added_parameters(&self) -> &[String] {
+    &self.added_parameters
+  }
+  pub fn removed_parameters(&self) -> &[String] {
+    &self.removed_parameters
+  }
+  pub fn changed_parameters(&self) -> &[(String, ParameterSchemaChangeSet)] {
+    &self.changed_parameters
+  }
+  pub fn added_parts(&self) -> &[String] {
+    &self.added_parts
+  }
+  pub fn removed_parts(&self) -> &[String] {
+    &self.removed_parts
+  }
+  /// Parts whose parent part changed.
+  pub fn reparented_parts(&self) -> &[String] {
+    &self.reparented_parts
+  }
+  pub fn added_drawables(&self) -> &[String] {
+    &self.added_drawables
+  }
+  pub fn removed_drawables(&self) -> &[String] {
+    &self.removed_drawables
+  }
+  pub fn changed_drawables(&self) -> &[(String, DrawableSchemaChangeSet)] {
+    &self.changed_drawables
+  }
+}
+
+impl ModelStatic {
+  /// Shorthand for [`SchemaDiff::new`] with `self` as the old description.
+  pub fn schema_diff(&self, new: &ModelStatic) -> SchemaDiff {
+    SchemaDiff::new(self, new)
+  }
+}
@@ -0,0 +1,214 @@
+//! Per-frame parameter logic in [rhai](https://rhai.rs) scripts, e.g. procedural tails or an ear wiggle on an event,
+//! changeable without recompiling.
+//!
+//! A script defines `update`, and optionally `on_event`:
+//! ```rhai
+//! // Called on each frame, with the time since the script started and the time since the previous frame, in seconds.
+//! fn update(time, dt) {
+//!   set("ParamHairSide", sin(time * 3.0) * 0.3);
+//!   set_var("wiggle", max(get_var("wiggle") - dt, 0.0));
+//!   set("ParamEarL", get_var("wiggle"));
+//! }
+//! // Called before the next `update` for each event passed to `ParameterScript::trigger`.
+//! fn on_event(name) {
+//!   if name == "tap" { set_var("wiggle", 1.0); }
+//! }
+//! ```
+//! Functions available to scripts:
+//! - `get(id)` - The value of the parameter with ID `id`, or `0.0` if the model has none.
+//! - `set(id, value)` - Sets the value of the parameter with ID `id`, if the model has one.
+//! - `has(id)` - Whether the model has a parameter with ID `id`.
+//! - `get_var(name)`, `set_var(name, value)` - Numbers kept between calls, as script functions cannot keep state. `0.0` until set.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use thiserror::Error;
+
+use super::animator::AnimationStage;
+use super::{ModelStatic, ModelDynamic};
+
+/// Errors generated by [`ParameterScript`].
+#[derive(Debug, Error)]
+pub enum ScriptError {
+  #[error("Failed to compile the script: {0}")]
+  Compile(#[from] rhai::ParseError),
+  #[error("Script failed: {0}")]
+  Runtime(String),
+}
+
+/// State shared with the functions registered in the engine.
+#[derive(Debug, Default)]
+struct ScriptState {
+  parameter_indices: HashMap<String, usize>,
+  parameter_values: Vec<f32>,
+  variables: HashMap<String, f64>,
+}
+
+/// A compiled script run on each frame, also usable as an [`AnimationStage`].
+#[derive(Debug)]
+pub struct ParameterScript {
+  engine: rhai::Engine,
+  ast: rhai::AST,
+  state: Arc<Mutex<ScriptState>>,
+  pending_events: Vec<String>,
+  time: f32,
+}
+impl ParameterScript {
+  /// Maximum number of operations per call, after which the call fails, so that a runaway script cannot hang the frame.
+  pub const MAX_OPERATIONS: u64 = 1_000_000;
+
+  /// Compiles `source` for the model of `model_static`.
+  pub fn new(source: &str, model_static: &ModelStatic) -> Result<Self, ScriptError> {
+    let state = Arc::new(Mutex::new(ScriptState {
+      parameter_indices: model_static.parameters().iter()
+        .enumerate()
+        .map(|(index, parameter)| (parameter.id().to_owned(), index))
+        .collect(),
+      ..Default::default()
+    }));
+
+    let mut engine = rhai::Engine::new();
+    engine.set_max_operations(Self::MAX_OPERATIONS);
+    {
+      let state = Arc::clone(&state);
+      engine.register_fn("get", move |id: &str| -> f64 {
+        let state = state.lock();
+        state.parameter_indices.get(id).map_or(0.0, |&index| f64::from(state.parameter_values[index]))
+      });
+    }
+    {
+      let state = Arc::clone(&state);
+      engine.register_fn("set", move |id: &str, value: f64| {
+        let mut state = state.lock();
+        if let Some(&index) = state.parameter_indices.get(id) {
+          state.parameter_values[index] = value as f32;
+        }
+      });
+    }
+    {
+      let state = Arc::clone(&state);
+      engine.register_fn("has", move |id: &str| -> bool {
+        state.lock().parameter_indices.contains_key(id)
+      });
+    }
+    {
+      let state = Arc::clone(&state);
+      engine.register_fn("get_var", move |name: &str| -> f64 {
+        state.lock().variables.get(name).copied().unwrap_or(0.0)
+      });
+    }
+    {
+      let state = Arc::clone(&state);
+      engine.register_fn("set_var", move |name: &str, value: f64| {
+        state.lock().variables.insert(name.to_owned(), value);
+      });
+    }
+
+    let ast = engine.compile(source)?;
+    Ok(Self {
+      engine,
+      ast,
+      state,
+      pending_events: Vec::new(),
+      time: 0.0,
+    })
+  }
+
+  /// Queues an event for the script's `on_event`, called before its next `update`.
+  pub fn trigger(&mut self, event: impl Into<String>) {
+    self.pending_events.push(event.into());
+  }
+  /// Time since the script started, in seconds.
+  pub fn time(&self) -> f32 {
+    self.time
+  }
+
+  /// Advances the time by `dt` seconds and runs the script on the parameters of `dynamic`.
+  ///
+  /// On failure, no parameter is written.
+  pub fn run(&mut self, dt: f32, dynamic: &mut ModelDynamic) -> Result<(), ScriptError> {
+    self.time += dt;
+    {
+      let mut state = self.state.lock();
+      state.parameter_values.clear();
+      state.parameter_values.extend_from_slice(dynamic.parameter_values());
+    }
+
+    // The registered functions lock `state`, so it MUST NOT be locked during calls.
+    let mut scope = rhai::Scope::new();
+    for event in std::mem::take(&mut self.pending_events) {
+      match self.engine.call_fn::<rhai::Dynamic>(&mut scope, &self.ast, "on_event", (event,)) {
+        Ok(_) => {},
+        Err(err) if matches!(*err, rhai::EvalAltResult::ErrorFunctionNotFound(..)) => {},
+        Err(err) => return Err(ScriptError::Runtime(err.to_string())),
+      }
+    }
+    // Whatever `update` returns is ignored.
+    let _ = self.engine.call_fn::<rhai::Dynamic>(&mut scope, &self.ast, "update", (f64::from(self.time), f64::from(dt)))
+      .map_err(|err| ScriptError::Runtime(err.to_string()))?;
+
+    dynamic.parameter_values_mut().copy_from_slice(&self.state.lock().parameter_values);
+    Ok(())
+  }
+}
+impl AnimationStage for ParameterScript {
+  /// Failures are logged, and leave the parameters untouched for that frame.
+  fn advance(&mut self, dt: f32, dynamic: &mut ModelDynamic) {
+    if let Err(err) = self.run(dt, dynamic) {
+      log::warn!("ParameterScript: {err}");
+    }
+  }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+  use crate::core::{CubismCore, Model, MockMoc};
+
+  use super::*;
+
+  const SOURCE: &str = r#"
+    fn update(time, dt) {
+      set("MockParam0", time);
+      set("MockParam1", get_var("pressed"));
+      set("MockParam2", if has("Missing") { 1.0 } else { get("MockParam3") + dt });
+      set("Missing", 1.0);
+    }
+    fn on_event(name) {
+      if name == "press" { set_var("pressed", 0.75); }
+    }
+  "#;
+
+  #[test]
+  fn update_and_on_event() {
+    let moc = CubismCore::default().moc_from_bytes(&MockMoc::default().to_bytes()).unwrap();
+    let model = Model::from_moc(&moc);
+    let mut dynamic = model.write_dynamic();
+    dynamic.parameter_values_mut()[3] = 0.5;
+
+    let mut script = ParameterScript::new(SOURCE, model.get_static()).unwrap();
+    script.run(0.25, &mut dynamic).unwrap();
+    assert_eq!(&dynamic.parameter_values()[..3], &[0.25, 0.0, 0.75]);
+
+    script.trigger("press");
+    script.run(0.25, &mut dynamic).unwrap();
+    assert_eq!(&dynamic.parameter_values()[..4], &[0.5, 0.75, 0.75, 0.5]);
+  }
+
+  #[test]
+  fn failures() {
+    let moc = CubismCore::default().moc_from_bytes(&MockMoc::default().to_bytes()).unwrap();
+    let model = Model::from_moc(&moc);
+    let mut dynamic = model.write_dynamic();
+
+    assert!(matches!(ParameterScript::new("fn update(", model.get_static()), Err(ScriptError::Compile(_))));
+
+    let mut script = ParameterScript::new(r#"fn update(time, dt) { set("MockParam0", 1.0); throw "failed"; }"#, model.get_static()).unwrap();
+    assert!(matches!(script.run(0.1, &mut dynamic), Err(ScriptError::Runtime(_))));
+    assert_eq!(dynamic.parameter_values()[0], 0.0);
+
+    let mut script = ParameterScript::new("fn update(time, dt) { loop {} }", model.get_static()).unwrap();
+    assert!(matches!(script.run(0.1, &mut dynamic), Err(ScriptError::Runtime(_))));
+  }
+}
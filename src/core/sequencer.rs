@@ -0,0 +1,227 @@
+//! Scheduling of animation on labeled tracks over time, e.g. for cutscenes or scripted streams.
+
+use super::animator::AnimationStage;
+use super::base_types::ParameterIndex;
+use super::presets::Preset;
+use super::tween::ParamTween;
+use super::{ModelStatic, ModelDynamic};
+
+struct Clip {
+  /// In seconds since the start of the sequence.
+  start: f32,
+  /// `None` to run until the stage is finished.
+  duration: Option<f32>,
+  stage: Box<dyn AnimationStage>,
+  finished: bool,
+}
+impl Clip {
+  fn end(&self) -> f32 {
+    self.duration.map_or(f32::INFINITY, |duration| self.start + duration)
+  }
+}
+
+struct Track {
+  label: String,
+  clips: Vec<Clip>,
+  muted: bool,
+}
+
+/// Clips of [`AnimationStage`]s, e.g. [`ParamTween`]s or [`super::LipSync`], each starting at a given time on a labeled track.
+///
+/// Tracks apply in the order they were created, so later tracks override earlier ones where they write the same parameters.
+#[derive(Default)]
+pub struct Sequencer {
+  tracks: Vec<Track>,
+  time: f32,
+}
+impl Sequencer {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Schedules `stage` on `track` from `start` seconds, for `duration` seconds or, if `None`, until it is finished.
+  ///
+  /// The track is created if needed.
+  pub fn add_stage<S: AnimationStage + 'static>(&mut self, track: &str, start: f32, duration: Option<f32>, stage: S) {
+    let track = match self.tracks.iter().position(|existing| existing.label == track) {
+      Some(index) => &mut self.tracks[index],
+      None => {
+        self.tracks.push(Track { label: track.to_owned(), clips: Vec::new(), muted: false });
+        self.tracks.last_mut().unwrap()
+      },
+    };
+    track.clips.push(Clip {
+      start,
+      duration,
+      stage: Box::new(stage),
+      finished: false,
+    });
+  }
+  /// Schedules `tween` on `track` from `start` seconds, until it is finished.
+  pub fn add_tween(&mut self, track: &str, start: f32, tween: ParamTween) {
+    self.add_stage(track, start, None, tween);
+  }
+  /// Schedules `preset`, e.g. an expression, on `track` from `start` for `duration` seconds,
+  /// fading in and out over `fade_time` seconds each.
+  ///
+  /// Parameters not found in the model of `model_static` are ignored.
+  pub fn add_preset(&mut self, track: &str, start: f32, duration: f32, preset: &Preset, fade_time: f32, model_static: &ModelStatic) {
+    let parameters = preset.parameters().iter()
+      .filter_map(|(id, value)| model_static.parameter_index(id).map(|index| (index, *value)))
+      .collect();
+    self.add_stage(track, start, Some(duration), PresetStage {
+      parameters,
+      duration,
+      fade_time,
+      time: 0.0,
+    });
+  }
+
+  /// Removes a track and its clips. Returns `false` if there is none with `label`.
+  pub fn remove_track(&mut self, label: &str) -> bool {
+    let len = self.tracks.len();
+    self.tracks.retain(|track| track.label != label);
+    self.tracks.len() != len
+  }
+  /// Mutes or unmutes a track. Time still passes for the clips of a muted track, but they are not advanced.
+  pub fn set_track_muted(&mut self, label: &str, muted: bool) {
+    if let Some(track) = self.tracks.iter_mut().find(|track| track.label == label) {
+      track.muted = muted;
+    }
+  }
+  /// Labels of the tracks, in the order they apply.
+  pub fn track_labels(&self) -> impl Iterator<Item = &str> {
+    self.tracks.iter().map(|track| track.label.as_str())
+  }
+
+  /// Time since the start of the sequence, in seconds.
+  pub fn time(&self) -> f32 {
+    self.time
+  }
+  /// End of the last clip with a duration, in seconds. Clips running until finished are not accounted for.
+  pub fn duration(&self) -> f32 {
+    self.tracks.iter()
+      .flat_map(|track| track.clips.iter())
+      .filter(|clip| clip.duration.is_some())
+      .map(Clip::end)
+      .fold(0.0, f32::max)
+  }
+
+  /// Advances by `dt` seconds, advancing each clip overlapping that time by the overlap, and dropping finished clips.
+  pub fn advance(&mut self, dt: f32, dynamic: &mut ModelDynamic) {
+    let (previous_time, time) = (self.time, self.time + dt);
+    self.time = time;
+
+    for track in self.tracks.iter_mut() {
+      for clip in track.clips.iter_mut() {
+        // Scheduled to end before this advance, e.g. when added late.
+        if previous_time >= clip.end() {
+          clip.finished = true;
+          continue;
+        }
+        let clip_dt = time.min(clip.end()) - previous_time.max(clip.start);
+        if time < clip.start || clip_dt < 0.0 {
+          continue;
+        }
+        if !track.muted {
+          clip.stage.advance(clip_dt, dynamic);
+        }
+        clip.finished = time >= clip.end() || clip.stage.is_finished();
+      }
+      track.clips.retain(|clip| !clip.finished);
+    }
+  }
+  /// Whether every clip is finished.
+  pub fn is_finished(&self) -> bool {
+    self.tracks.iter().all(|track| track.clips.is_empty())
+  }
+}
+impl AnimationStage for Sequencer {
+  fn advance(&mut self, dt: f32, dynamic: &mut ModelDynamic) {
+    Sequencer::advance(self, dt, dynamic);
+  }
+  fn is_finished(&self) -> bool {
+    Sequencer::is_finished(self)
+  }
+}
+impl std::fmt::Debug for Sequencer {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Sequencer")
+      .field("track_labels", &self.track_labels().collect::<Vec<_>>())
+      .field("time", &self.time)
+      .finish()
+  }
+}
+
+/// Blends a preset in and out over its clip.
+struct PresetStage {
+  parameters: Vec<(ParameterIndex, f32)>,
+  duration: f32,
+  fade_time: f32,
+  time: f32,
+}
+impl AnimationStage for PresetStage {
+  fn advance(&mut self, dt: f32, dynamic: &mut ModelDynamic) {
+    self.time += dt;
+
+    let weight = if self.fade_time > 0.0 {
+      (self.time.min(self.duration - self.time) / self.fade_time).clamp(0.0, 1.0)
+    } else {
+      1.0
+    };
+    for &(index, value) in &self.parameters {
+      let current = dynamic[index];
+      dynamic[index] = current + (value - current) * weight;
+    }
+  }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+  use crate::core::{CubismCore, Model, MockMoc};
+
+  use super::*;
+
+  /// Counts its advances, never finishing by itself.
+  struct CountingStage(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+  impl AnimationStage for CountingStage {
+    fn advance(&mut self, _dt: f32, _dynamic: &mut ModelDynamic) {
+      self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+  }
+
+  #[test]
+  fn clip_scheduled_in_the_past_finishes() {
+    let moc = CubismCore::default().moc_from_bytes(&MockMoc::default().to_bytes()).unwrap();
+    let model = Model::from_moc(&moc);
+    let mut dynamic = model.write_dynamic();
+
+    let mut sequencer = Sequencer::new();
+    sequencer.advance(2.0, &mut dynamic);
+
+    let advance_count = std::sync::Arc::default();
+    sequencer.add_stage("late", 0.5, Some(1.0), CountingStage(std::sync::Arc::clone(&advance_count)));
+    assert!(!sequencer.is_finished());
+    sequencer.advance(0.1, &mut dynamic);
+    assert!(sequencer.is_finished());
+    assert_eq!(advance_count.load(std::sync::atomic::Ordering::Relaxed), 0);
+  }
+
+  #[test]
+  fn clip_finishes_at_its_end() {
+    let moc = CubismCore::default().moc_from_bytes(&MockMoc::default().to_bytes()).unwrap();
+    let model = Model::from_moc(&moc);
+    let mut dynamic = model.write_dynamic();
+
+    let advance_count = std::sync::Arc::default();
+    let mut sequencer = Sequencer::new();
+    sequencer.add_stage("track", 0.5, Some(1.0), CountingStage(std::sync::Arc::clone(&advance_count)));
+    sequencer.advance(0.25, &mut dynamic);
+    assert_eq!(advance_count.load(std::sync::atomic::Ordering::Relaxed), 0);
+    sequencer.advance(1.0, &mut dynamic);
+    assert!(!sequencer.is_finished());
+    sequencer.advance(1.0, &mut dynamic);
+    assert!(sequencer.is_finished());
+    assert_eq!(advance_count.load(std::sync::atomic::Ordering::Relaxed), 2);
+  }
+}
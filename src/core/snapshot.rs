@@ -0,0 +1,179 @@
+//! Owned copies of the dynamic state of a model, and comparison between them.
+
+use flagset::{FlagSet, flags};
+
+use super::base_types::{Vector2, Vector4, DrawableIndex, ParameterIndex, PartIndex};
+use super::ModelDynamic;
+
+/// An owned copy of the dynamic state of a model at some point in time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelDynamicSnapshot {
+  pub(crate) parameter_values: Box<[f32]>,
+  pub(crate) part_opacities: Box<[f32]>,
+  pub(crate) drawable_draw_orders: Box<[i32]>,
+  pub(crate) drawable_render_orders: Box<[i32]>,
+  pub(crate) drawable_opacities: Box<[f32]>,
+  pub(crate) drawable_vertex_positions: Box<[Box<[Vector2]>]>,
+  pub(crate) drawable_multiply_colors: Box<[Vector4]>,
+  pub(crate) drawable_screen_colors: Box<[Vector4]>,
+}
+impl ModelDynamicSnapshot {
+  pub fn new(dynamic: &ModelDynamic) -> Self {
+    Self {
+      parameter_values: dynamic.parameter_values().into(),
+      part_opacities: dynamic.part_opacities().into(),
+      drawable_draw_orders: dynamic.drawable_draw_orders().into(),
+      drawable_render_orders: dynamic.drawable_render_orders().into(),
+      drawable_opacities: dynamic.drawable_opacities().into(),
+      drawable_vertex_positions: dynamic.drawable_vertex_position_containers().iter()
+        .map(|&positions| positions.into())
+        .collect(),
+      drawable_multiply_colors: dynamic.drawable_multiply_colors().into(),
+      drawable_screen_colors: dynamic.drawable_screen_colors().into(),
+    }
+  }
+
+  pub fn parameter_values(&self) -> &[f32] {
+    &self.parameter_values
+  }
+  pub fn part_opacities(&self) -> &[f32] {
+    &self.part_opacities
+  }
+  pub fn drawable_draw_orders(&self) -> &[i32] {
+    &self.drawable_draw_orders
+  }
+  pub fn drawable_render_orders(&self) -> &[i32] {
+    &self.drawable_render_orders
+  }
+  pub fn drawable_opacities(&self) -> &[f32] {
+    &self.drawable_opacities
+  }
+  pub fn drawable_vertex_positions(&self) -> &[Box<[Vector2]>] {
+    &self.drawable_vertex_positions
+  }
+  pub fn drawable_multiply_colors(&self) -> &[Vector4] {
+    &self.drawable_multiply_colors
+  }
+  pub fn drawable_screen_colors(&self) -> &[Vector4] {
+    &self.drawable_screen_colors
+  }
+
+  /// Compares against `other` with [`DiffTolerances::default`].
+  pub fn diff(&self, other: &Self) -> SnapshotDiff {
+    self.diff_with_tolerances(other, &DiffTolerances::default())
+  }
+  /// Compares against `other`, treating values within `tolerances` as equal.
+  ///
+  /// Elements present in only one of the snapshots, e.g. when they were taken from different mocs, are reported as differing.
+  pub fn diff_with_tolerances(&self, other: &Self, tolerances: &DiffTolerances) -> SnapshotDiff {
+    let parameters = differing_indices(&self.parameter_values, &other.parameter_values, |a, b| (a - b).abs() <= tolerances.parameter_value)
+      .map(ParameterIndex::from)
+      .collect();
+    let parts = differing_indices(&self.part_opacities, &other.part_opacities, |a, b| (a - b).abs() <= tolerances.opacity)
+      .map(PartIndex::from)
+      .collect();
+
+    let drawable_count = self.drawable_opacities.len().max(other.drawable_opacities.len());
+    let mut drawables = vec![DrawableDiffFlagSet::default(); drawable_count];
+    let mut mark = |indices: &mut dyn Iterator<Item = usize>, flag: DrawableDiffFlags| {
+      for index in indices {
+        drawables[index] |= flag;
+      }
+    };
+
+    mark(&mut differing_indices(&self.drawable_opacities, &other.drawable_opacities, |a, b| (a - b).abs() <= tolerances.opacity), DrawableDiffFlags::Opacity);
+    mark(&mut differing_indices(&self.drawable_draw_orders, &other.drawable_draw_orders, |a, b| a == b), DrawableDiffFlags::DrawOrder);
+    mark(&mut differing_indices(&self.drawable_render_orders, &other.drawable_render_orders, |a, b| a == b), DrawableDiffFlags::RenderOrder);
+    mark(
+      &mut differing_indices(&self.drawable_vertex_positions, &other.drawable_vertex_positions, |a, b| {
+        a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| {
+          (a.x - b.x).abs() <= tolerances.vertex_position && (a.y - b.y).abs() <= tolerances.vertex_position
+        })
+      }),
+      DrawableDiffFlags::VertexPositions,
+    );
+    let color_eq = |a: &Vector4, b: &Vector4| {
+      [a.x - b.x, a.y - b.y, a.z - b.z, a.w - b.w].iter().all(|d| d.abs() <= tolerances.color)
+    };
+    mark(&mut differing_indices(&self.drawable_multiply_colors, &other.drawable_multiply_colors, color_eq), DrawableDiffFlags::BlendColor);
+    mark(&mut differing_indices(&self.drawable_screen_colors, &other.drawable_screen_colors, color_eq), DrawableDiffFlags::BlendColor);
+
+    let drawables = drawables.into_iter()
+      .enumerate()
+      .filter(|(_, flagset)| !flagset.is_empty())
+      .map(|(index, flagset)| (DrawableIndex::from(index), flagset))
+      .collect();
+
+    SnapshotDiff {
+      parameters,
+      parts,
+      drawables,
+    }
+  }
+}
+
+/// Absolute tolerances under which values are considered equal by [`ModelDynamicSnapshot::diff_with_tolerances`].
+#[derive(Debug, Clone)]
+pub struct DiffTolerances {
+  pub parameter_value: f32,
+  /// Applies to both part and drawable opacities.
+  pub opacity: f32,
+  /// In model units, per component.
+  pub vertex_position: f32,
+  /// Per component of multiply and screen colors.
+  pub color: f32,
+}
+impl Default for DiffTolerances {
+  fn default() -> Self {
+    Self {
+      parameter_value: 1e-6,
+      opacity: 1e-6,
+      vertex_position: 1e-6,
+      color: 1e-6,
+    }
+  }
+}
+
+flags! {
+  /// Aspects in which a drawable differs between two snapshots.
+  pub enum DrawableDiffFlags: u8 {
+    Opacity,
+    DrawOrder,
+    RenderOrder,
+    VertexPositions,
+    /// Multiply or screen color.
+    BlendColor,
+  }
+}
+
+pub type DrawableDiffFlagSet = FlagSet<DrawableDiffFlags>;
+
+/// Result of [`ModelDynamicSnapshot::diff`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SnapshotDiff {
+  parameters: Vec<ParameterIndex>,
+  parts: Vec<PartIndex>,
+  drawables: Vec<(DrawableIndex, DrawableDiffFlagSet)>,
+}
+impl SnapshotDiff {
+  pub fn is_empty(&self) -> bool {
+    self.parameters.is_empty() && self.parts.is_empty() && self.drawables.is_empty()
+  }
+  /// Parameters whose values differ, in ascending order.
+  pub fn parameters(&self) -> &[ParameterIndex] {
+    &self.parameters
+  }
+  /// Parts whose opacities differ, in ascending order.
+  pub fn parts(&self) -> &[PartIndex] {
+    &self.parts
+  }
+  /// Drawables differing in any aspect, in ascending index order.
+  pub fn drawables(&self) -> &[(DrawableIndex, DrawableDiffFlagSet)] {
+    &self.drawables
+  }
+}
+
+fn differing_indices<'a, T>(a: &'a [T], b: &'a [T], eq: impl Fn(&T, &T) -> bool + 'a) -> impl Iterator<Item = usize> + 'a {
+  let common = a.len().min(b.len());
+  (0..a.len().max(b.len())).filter(move |&index| index >= common || !eq(&a[index], &b[index]))
+}
@@ -0,0 +1,100 @@
+//! IDs of the standard parameters of _Live2D Cubism Editor_, and lookup of them in models that name them differently.
+
+use super::base_types::ParameterIndex;
+use super::ModelStatic;
+
+pub const ANGLE_X: &str = "ParamAngleX";
+pub const ANGLE_Y: &str = "ParamAngleY";
+pub const ANGLE_Z: &str = "ParamAngleZ";
+pub const EYE_L_OPEN: &str = "ParamEyeLOpen";
+pub const EYE_L_SMILE: &str = "ParamEyeLSmile";
+pub const EYE_R_OPEN: &str = "ParamEyeROpen";
+pub const EYE_R_SMILE: &str = "ParamEyeRSmile";
+pub const EYE_BALL_X: &str = "ParamEyeBallX";
+pub const EYE_BALL_Y: &str = "ParamEyeBallY";
+pub const BROW_L_Y: &str = "ParamBrowLY";
+pub const BROW_R_Y: &str = "ParamBrowRY";
+pub const BROW_L_X: &str = "ParamBrowLX";
+pub const BROW_R_X: &str = "ParamBrowRX";
+pub const BROW_L_ANGLE: &str = "ParamBrowLAngle";
+pub const BROW_R_ANGLE: &str = "ParamBrowRAngle";
+pub const BROW_L_FORM: &str = "ParamBrowLForm";
+pub const BROW_R_FORM: &str = "ParamBrowRForm";
+pub const MOUTH_FORM: &str = "ParamMouthForm";
+pub const MOUTH_OPEN_Y: &str = "ParamMouthOpenY";
+pub const CHEEK: &str = "ParamCheek";
+pub const BODY_ANGLE_X: &str = "ParamBodyAngleX";
+pub const BODY_ANGLE_Y: &str = "ParamBodyAngleY";
+pub const BODY_ANGLE_Z: &str = "ParamBodyAngleZ";
+pub const BREATH: &str = "ParamBreath";
+pub const HAIR_FRONT: &str = "ParamHairFront";
+pub const HAIR_SIDE: &str = "ParamHairSide";
+pub const HAIR_BACK: &str = "ParamHairBack";
+
+/// All standard parameter IDs above.
+pub const ALL: &[&str] = &[
+  ANGLE_X, ANGLE_Y, ANGLE_Z,
+  EYE_L_OPEN, EYE_L_SMILE, EYE_R_OPEN, EYE_R_SMILE, EYE_BALL_X, EYE_BALL_Y,
+  BROW_L_Y, BROW_R_Y, BROW_L_X, BROW_R_X, BROW_L_ANGLE, BROW_R_ANGLE, BROW_L_FORM, BROW_R_FORM,
+  MOUTH_FORM, MOUTH_OPEN_Y, CHEEK,
+  BODY_ANGLE_X, BODY_ANGLE_Y, BODY_ANGLE_Z, BREATH,
+  HAIR_FRONT, HAIR_SIDE, HAIR_BACK,
+];
+
+/// Finds the parameter for the standard `id`, e.g. [`ANGLE_X`], trying in order:
+/// 1. `id` itself.
+/// 2. IDs in `aliases` for `id`, in order.
+/// 3. The _Cubism 2_ form of `id`, e.g. `PARAM_ANGLE_X`.
+/// 4. An ID equal to `id` when ignoring case and non-alphanumeric characters, e.g. `param_angle_x`.
+/// 5. The only ID ending with `id` in that same sense, e.g. `Char1_ParamAngleX`.
+pub fn resolve(model_static: &ModelStatic, id: &str, aliases: &[(&str, &str)]) -> Option<ParameterIndex> {
+  if let Some(index) = model_static.parameter_index(id) {
+    return Some(index);
+  }
+  if let Some(index) = aliases.iter()
+    .filter(|(standard_id, _)| *standard_id == id)
+    .find_map(|(_, alias)| model_static.parameter_index(alias))
+  {
+    return Some(index);
+  }
+  if let Some(index) = model_static.parameter_index(&to_cubism2_id(id)) {
+    return Some(index);
+  }
+
+  let normalized_id = normalize(id);
+  let normalized_ids: Vec<String> = model_static.parameters().iter().map(|parameter| normalize(parameter.id())).collect();
+  if let Some(index) = normalized_ids.iter().position(|candidate| *candidate == normalized_id) {
+    return Some(ParameterIndex::from(index));
+  }
+  let mut suffixed = normalized_ids.iter().enumerate().filter(|(_, candidate)| candidate.ends_with(&normalized_id));
+  match (suffixed.next(), suffixed.next()) {
+    (Some((index, _)), None) => Some(ParameterIndex::from(index)),
+    _ => None,
+  }
+}
+
+/// Resolves every ID in [`ALL`] with [`resolve`], returning the found ones.
+pub fn resolve_all(model_static: &ModelStatic, aliases: &[(&str, &str)]) -> Vec<(&'static str, ParameterIndex)> {
+  ALL.iter()
+    .filter_map(|&id| resolve(model_static, id, aliases).map(|index| (id, index)))
+    .collect()
+}
+
+/// `ParamEyeLOpen` -> `PARAM_EYE_L_OPEN`.
+fn to_cubism2_id(id: &str) -> String {
+  let mut cubism2_id = String::with_capacity(id.len() + 4);
+  for (i, c) in id.chars().enumerate() {
+    if i > 0 && c.is_ascii_uppercase() {
+      cubism2_id.push('_');
+    }
+    cubism2_id.push(c.to_ascii_uppercase());
+  }
+  cubism2_id
+}
+
+fn normalize(id: &str) -> String {
+  id.chars()
+    .filter(char::is_ascii_alphanumeric)
+    .map(|c| c.to_ascii_lowercase())
+    .collect()
+}
@@ -0,0 +1,88 @@
+//! Stable hashing of the dynamic state of a model, for golden-state regression tests.
+
+use super::ModelDynamic;
+
+/// Default quantization step of vertex positions for [`ModelDynamic::state_hash`], in model units.
+pub const DEFAULT_VERTEX_POSITION_QUANTUM: f32 = 1.0 / 4096.0;
+
+/// 64-bit FNV-1a, which unlike [`std::collections::hash_map::DefaultHasher`] is stable across
+/// platforms, processes and Rust versions.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Fnv1a(u64);
+impl Fnv1a {
+  const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+  const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+  pub(crate) fn new() -> Self {
+    Self(Self::OFFSET_BASIS)
+  }
+  pub(crate) fn write(&mut self, bytes: &[u8]) {
+    for &byte in bytes {
+      self.0 ^= u64::from(byte);
+      self.0 = self.0.wrapping_mul(Self::PRIME);
+    }
+  }
+  pub(crate) fn write_u32(&mut self, value: u32) {
+    self.write(&value.to_le_bytes());
+  }
+  pub(crate) fn write_i32(&mut self, value: i32) {
+    self.write(&value.to_le_bytes());
+  }
+  /// Hashes the bit pattern of `value`, treating `-0.0` as `0.0` and all NaNs as one.
+  pub(crate) fn write_f32(&mut self, value: f32) {
+    let bits = if value == 0.0 {
+      0
+    } else if value.is_nan() {
+      f32::NAN.to_bits()
+    } else {
+      value.to_bits()
+    };
+    self.write_u32(bits);
+  }
+  pub(crate) fn finish(&self) -> u64 {
+    self.0
+  }
+}
+
+// Each section is prefixed with its length, so that values can't shift between sections.
+fn write_f32s(hasher: &mut Fnv1a, values: &[f32]) {
+  hasher.write_u32(values.len() as u32);
+  values.iter().for_each(|&value| hasher.write_f32(value));
+}
+
+pub(crate) fn parameter_state_hash(dynamic: &ModelDynamic) -> u64 {
+  let mut hasher = Fnv1a::new();
+  write_f32s(&mut hasher, dynamic.parameter_values());
+  write_f32s(&mut hasher, dynamic.part_opacities());
+  hasher.finish()
+}
+
+pub(crate) fn state_hash(dynamic: &ModelDynamic, vertex_position_quantum: f32) -> u64 {
+  let mut hasher = Fnv1a::new();
+
+  write_f32s(&mut hasher, dynamic.parameter_values());
+  write_f32s(&mut hasher, dynamic.part_opacities());
+  write_f32s(&mut hasher, dynamic.drawable_opacities());
+
+  for orders in [dynamic.drawable_draw_orders(), dynamic.drawable_render_orders()] {
+    hasher.write_u32(orders.len() as u32);
+    orders.iter().for_each(|&order| hasher.write_i32(order));
+  }
+
+  let containers = dynamic.drawable_vertex_position_containers();
+  hasher.write_u32(containers.len() as u32);
+  for positions in containers {
+    hasher.write_u32(positions.len() as u32);
+    for position in positions.iter() {
+      hasher.write_i32(quantize(position.x, vertex_position_quantum));
+      hasher.write_i32(quantize(position.y, vertex_position_quantum));
+    }
+  }
+
+  hasher.finish()
+}
+
+fn quantize(value: f32, quantum: f32) -> i32 {
+  // `as` saturates, and maps NaN to 0.
+  (value / quantum).round() as i32
+}
@@ -0,0 +1,82 @@
+//! Canonical hashed outputs of a model driven by a parameter script, to check that the native and web backends
+//! stay numerically consistent: run the same script on both and compare the [`ConsistencyReport`]s.
+
+use super::state_hash::Fnv1a;
+use super::{CubismCore, MocError, Model, ModelStatic};
+
+/// Parameter values set by ID before an update. Values persist into later frames until set again.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConsistencyFrame {
+  pub parameter_values: Vec<(String, f32)>,
+}
+
+/// The frames to run, each followed by an update.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConsistencyScript {
+  pub frames: Vec<ConsistencyFrame>,
+}
+impl ConsistencyScript {
+  /// Sweeps each parameter in turn from its minimum to its maximum in `steps` frames, then back to its default,
+  /// so that every parameter is covered without a handwritten script.
+  pub fn sweep(model_static: &ModelStatic, steps: usize) -> Self {
+    let steps = steps.max(2);
+    let mut frames = Vec::new();
+    for parameter in model_static.parameters().iter() {
+      let (min, max) = parameter.value_range();
+      for step in 0..steps {
+        let value = min + (max - min) * step as f32 / (steps - 1) as f32;
+        frames.push(ConsistencyFrame { parameter_values: vec![(parameter.id().to_owned(), value)] });
+      }
+      frames.push(ConsistencyFrame { parameter_values: vec![(parameter.id().to_owned(), parameter.default_value())] });
+    }
+    Self { frames }
+  }
+}
+
+/// The [`super::ModelDynamic::state_hash_with_quantum`] after each frame of a [`ConsistencyScript`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConsistencyReport {
+  pub frame_hashes: Vec<u64>,
+}
+impl ConsistencyReport {
+  /// A single hash of every frame, e.g. to store as a golden value.
+  pub fn combined_hash(&self) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write_u32(self.frame_hashes.len() as u32);
+    for hash in &self.frame_hashes {
+      hasher.write(&hash.to_le_bytes());
+    }
+    hasher.finish()
+  }
+  /// The index of the first frame differing from `other`, or of the first frame only one of them has.
+  pub fn first_mismatch(&self, other: &ConsistencyReport) -> Option<usize> {
+    self.frame_hashes.iter().zip(other.frame_hashes.iter())
+      .position(|(a, b)| a != b)
+      .or_else(|| (self.frame_hashes.len() != other.frame_hashes.len()).then(|| self.frame_hashes.len().min(other.frame_hashes.len())))
+  }
+}
+
+/// Runs `script` on a new model of the moc in `moc_bytes`, hashing its state after each update.
+///
+/// Vertex positions are hashed quantized by `vertex_position_quantum`, which must be coarse enough to absorb the
+/// floating-point differences between backends, e.g. [`super::state_hash::DEFAULT_VERTEX_POSITION_QUANTUM`].
+/// Parameters of the script not found in the model are ignored.
+pub fn run_consistency_script(cubism_core: &CubismCore, moc_bytes: &[u8], script: &ConsistencyScript, vertex_position_quantum: f32) -> Result<ConsistencyReport, MocError> {
+  let moc = cubism_core.moc_from_bytes(moc_bytes)?;
+  let model = Model::from_moc(&moc);
+  let model_static = model.get_static();
+  let mut dynamic = model.write_dynamic();
+
+  let mut frame_hashes = Vec::with_capacity(script.frames.len());
+  for frame in &script.frames {
+    for (id, value) in &frame.parameter_values {
+      if let Some(index) = model_static.parameter_index(id) {
+        dynamic[index] = *value;
+      }
+    }
+    dynamic.update();
+    frame_hashes.push(dynamic.state_hash_with_quantum(vertex_position_quantum));
+  }
+
+  Ok(ConsistencyReport { frame_hashes })
+}
@@ -0,0 +1,164 @@
+//! Parameter tweening with easing curves, for UI-triggered reactions without motion files.
+
+use super::base_types::ParameterIndex;
+use super::ModelDynamic;
+
+/// Easing curve mapping linear progress in `[0, 1]` to eased progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Easing {
+  #[default]
+  Linear,
+  CubicIn,
+  CubicOut,
+  CubicInOut,
+  /// Overshoots and oscillates around the target before settling.
+  ElasticOut,
+}
+impl Easing {
+  /// Maps `t`, clamped to `[0, 1]`, through the curve.
+  pub fn apply(&self, t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    match self {
+      Self::Linear => t,
+      Self::CubicIn => t * t * t,
+      Self::CubicOut => 1.0 - (1.0 - t).powi(3),
+      Self::CubicInOut => {
+        if t < 0.5 {
+          4.0 * t * t * t
+        } else {
+          1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+        }
+      }
+      Self::ElasticOut => {
+        const C4: f32 = (2.0 * std::f32::consts::PI) / 3.0;
+        if t == 0.0 || t == 1.0 {
+          t
+        } else {
+          2.0f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * C4).sin() + 1.0
+        }
+      }
+    }
+  }
+}
+
+/// Animates a single parameter from its current value to a target over a duration.
+///
+/// The start value is captured on the first [`ParamTween::advance`], unless given with [`ParamTween::from_value`].
+#[derive(Debug, Clone)]
+pub struct ParamTween {
+  parameter_index: ParameterIndex,
+  start_value: Option<f32>,
+  target_value: f32,
+  duration: f32,
+  elapsed: f32,
+  easing: Easing,
+}
+impl ParamTween {
+  /// * `duration` - In seconds. A non-positive duration jumps to `target_value` on the first advance.
+  pub fn new(parameter_index: ParameterIndex, target_value: f32, duration: f32, easing: Easing) -> Self {
+    Self {
+      parameter_index,
+      start_value: None,
+      target_value,
+      duration: duration.max(0.0),
+      elapsed: 0.0,
+      easing,
+    }
+  }
+  /// Uses `start_value` instead of the parameter's value at the first advance.
+  pub fn from_value(mut self, start_value: f32) -> Self {
+    self.start_value = Some(start_value);
+    self
+  }
+
+  pub fn parameter_index(&self) -> ParameterIndex {
+    self.parameter_index
+  }
+  pub fn target_value(&self) -> f32 {
+    self.target_value
+  }
+  pub fn duration(&self) -> f32 {
+    self.duration
+  }
+  pub fn easing(&self) -> Easing {
+    self.easing
+  }
+  /// Linear progress in `[0, 1]`.
+  pub fn progress(&self) -> f32 {
+    if self.duration > 0.0 {
+      self.elapsed / self.duration
+    } else {
+      1.0
+    }
+  }
+  /// Whether the tween has been advanced to its end.
+  pub fn is_finished(&self) -> bool {
+    self.start_value.is_some() && self.elapsed >= self.duration
+  }
+
+  /// Advances the tween by `dt` seconds and writes the eased value into `dynamic`.
+  ///
+  /// Returns whether the tween is finished. A parameter index out of range finishes the tween without writing.
+  pub fn advance(&mut self, dt: f32, dynamic: &mut ModelDynamic) -> bool {
+    let value = match dynamic.parameter_values_mut().get_mut(self.parameter_index.as_usize()) {
+      Some(value) => value,
+      None => {
+        self.elapsed = self.duration;
+        self.start_value.get_or_insert(self.target_value);
+        return true;
+      }
+    };
+
+    let start_value = *self.start_value.get_or_insert(*value);
+    self.elapsed = (self.elapsed + dt.max(0.0)).min(self.duration);
+
+    *value = start_value + (self.target_value - start_value) * self.easing.apply(self.progress());
+
+    self.is_finished()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const EASINGS: [Easing; 5] = [Easing::Linear, Easing::CubicIn, Easing::CubicOut, Easing::CubicInOut, Easing::ElasticOut];
+
+  #[test]
+  fn easing_endpoints() {
+    for easing in EASINGS {
+      assert_eq!(easing.apply(0.0), 0.0, "{easing:?}");
+      assert_eq!(easing.apply(1.0), 1.0, "{easing:?}");
+      assert_eq!(easing.apply(-1.0), 0.0, "{easing:?}");
+      assert_eq!(easing.apply(2.0), 1.0, "{easing:?}");
+    }
+    assert_eq!(Easing::CubicInOut.apply(0.5), 0.5);
+    assert!(Easing::CubicIn.apply(0.5) < 0.5 && Easing::CubicOut.apply(0.5) > 0.5);
+    assert!((0..100).any(|i| Easing::ElasticOut.apply(i as f32 / 100.0) > 1.0));
+  }
+
+  #[cfg(feature = "mock")]
+  #[test]
+  fn tween() {
+    use crate::core::{CubismCore, Model, MockMoc};
+
+    let moc = CubismCore::default().moc_from_bytes(&MockMoc::default().to_bytes()).unwrap();
+    let model = Model::from_moc(&moc);
+    let mut dynamic = model.write_dynamic();
+    dynamic.parameter_values_mut()[1] = 0.5;
+
+    let mut tween = ParamTween::new(ParameterIndex::from(1), 1.0, 2.0, Easing::Linear);
+    assert!(!tween.is_finished());
+    assert!(!tween.advance(1.0, &mut dynamic));
+    assert_eq!(dynamic.parameter_values()[1], 0.75);
+    assert!(tween.advance(5.0, &mut dynamic));
+    assert_eq!((dynamic.parameter_values()[1], tween.progress()), (1.0, 1.0));
+
+    let mut tween = ParamTween::new(ParameterIndex::from(0), 1.0, 0.0, Easing::CubicIn).from_value(-1.0);
+    assert!(tween.advance(0.0, &mut dynamic));
+    assert_eq!(dynamic.parameter_values()[0], 1.0);
+
+    let mut tween = ParamTween::new(ParameterIndex::from(100), 1.0, 1.0, Easing::Linear);
+    assert!(tween.advance(0.1, &mut dynamic));
+  }
+}
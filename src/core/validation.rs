@@ -0,0 +1,88 @@
+//! Validation of the static mesh data of a model, to report broken exports before a renderer trips over them.
+
+use flagset::{FlagSet, flags};
+
+use super::base_types::DrawableIndex;
+use super::model_types::Drawable;
+use super::ModelStatic;
+
+flags! {
+  /// Problems found in the mesh of a drawable.
+  pub enum MeshIssues: u8 {
+    /// No triangles at all, so nothing is drawn.
+    NoTriangles,
+    /// The number of triangle indices is not a multiple of 3.
+    IncompleteTriangle,
+    /// A triangle index is not below the vertex count.
+    IndexOutOfRange,
+    /// A triangle uses the same vertex more than once.
+    DegenerateTriangle,
+    /// A UV is NaN or infinite.
+    NonFiniteUv,
+  }
+}
+
+pub type MeshIssueSet = FlagSet<MeshIssues>;
+
+impl Drawable {
+  /// Checks the static mesh data of this drawable.
+  pub fn mesh_issues(&self) -> MeshIssueSet {
+    let mut issues = MeshIssueSet::default();
+    let triangle_indices = self.triangle_indices();
+    let vertex_count = self.vertex_uvs().len();
+
+    if triangle_indices.len() < 3 {
+      issues |= MeshIssues::NoTriangles;
+    }
+    if !triangle_indices.len().is_multiple_of(3) {
+      issues |= MeshIssues::IncompleteTriangle;
+    }
+    if triangle_indices.iter().any(|&index| usize::from(index) >= vertex_count) {
+      issues |= MeshIssues::IndexOutOfRange;
+    }
+    if triangle_indices.chunks_exact(3).any(|triangle| triangle[0] == triangle[1] || triangle[1] == triangle[2] || triangle[2] == triangle[0]) {
+      issues |= MeshIssues::DegenerateTriangle;
+    }
+    if self.vertex_uvs().iter().any(|uv| !uv.x.is_finite() || !uv.y.is_finite()) {
+      issues |= MeshIssues::NonFiniteUv;
+    }
+    issues
+  }
+}
+
+/// Result of [`ModelStatic::validate_meshes`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MeshValidationReport {
+  drawables: Vec<(DrawableIndex, MeshIssueSet)>,
+}
+impl MeshValidationReport {
+  pub fn is_ok(&self) -> bool {
+    self.drawables.is_empty()
+  }
+  /// Drawables with any issue, in ascending index order.
+  pub fn drawables(&self) -> &[(DrawableIndex, MeshIssueSet)] {
+    &self.drawables
+  }
+  /// Issues of a drawable, empty if none.
+  pub fn issues_of(&self, index: DrawableIndex) -> MeshIssueSet {
+    self.drawables.iter()
+      .find(|(drawable_index, _)| *drawable_index == index)
+      .map(|(_, issues)| *issues)
+      .unwrap_or_default()
+  }
+}
+
+impl ModelStatic {
+  /// Checks the static mesh data of every drawable, e.g. right after loading a user-provided model.
+  ///
+  /// Drawables with [`MeshIssues::NoTriangles`] only are legitimate in some models, e.g. placeholders for masks
+  /// of other drawables, so whether to reject a model is left to the caller.
+  pub fn validate_meshes(&self) -> MeshValidationReport {
+    MeshValidationReport {
+      drawables: self.drawables().iter()
+        .map(|drawable| (drawable.index(), drawable.mesh_issues()))
+        .filter(|(_, issues)| !issues.is_empty())
+        .collect(),
+    }
+  }
+}
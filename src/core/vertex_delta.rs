@@ -0,0 +1,90 @@
+//! Vertex positions as deltas from a rest pose, which are small or zero for most vertices in most frames,
+//! e.g. for streaming or compactly recording a performance.
+
+use super::base_types::{Vector2, DrawableIndex};
+use super::{ModelDynamic, ModelDynamicSnapshot};
+
+/// A vertex whose position differs from the rest pose.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VertexDelta {
+  pub vertex_index: u32,
+  pub delta: Vector2,
+}
+
+/// The vertex positions of all drawables at rest, typically captured after the first update with default parameter values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestPose {
+  drawable_vertex_positions: Box<[Box<[Vector2]>]>,
+}
+impl RestPose {
+  /// Captures the current vertex positions of `dynamic` as the rest pose.
+  pub fn capture(dynamic: &ModelDynamic) -> Self {
+    Self {
+      drawable_vertex_positions: dynamic.drawable_vertex_position_containers().iter()
+        .map(|&positions| positions.into())
+        .collect(),
+    }
+  }
+  pub fn from_snapshot(snapshot: &ModelDynamicSnapshot) -> Self {
+    Self {
+      drawable_vertex_positions: snapshot.drawable_vertex_positions().into(),
+    }
+  }
+
+  pub fn drawable_vertex_positions(&self) -> &[Box<[Vector2]>] {
+    &self.drawable_vertex_positions
+  }
+
+  /// The current minus the rest position of every vertex of a drawable, or `None` if `index` is out of range.
+  ///
+  /// ## Panics
+  /// - If `dynamic` belongs to a model of a different moc.
+  pub fn drawable_deltas(&self, dynamic: &ModelDynamic, index: DrawableIndex) -> Option<Vec<Vector2>> {
+    let (rest, current) = self.drawable_positions(dynamic, index)?;
+    Some(rest.iter().zip(current.iter()).map(|(rest, current)| Vector2 { x: current.x - rest.x, y: current.y - rest.y }).collect())
+  }
+  /// Like [`RestPose::drawable_deltas`], but only the vertices that moved by more than `threshold` in either axis, in vertex order.
+  ///
+  /// Writes into `out` to reuse its allocation. `out` is cleared first.
+  /// Returns the number of deltas written, or `None` if `index` is out of range.
+  ///
+  /// ## Panics
+  /// - If `dynamic` belongs to a model of a different moc.
+  pub fn write_sparse_drawable_deltas(&self, dynamic: &ModelDynamic, index: DrawableIndex, threshold: f32, out: &mut Vec<VertexDelta>) -> Option<usize> {
+    let (rest, current) = self.drawable_positions(dynamic, index)?;
+
+    out.clear();
+    for (vertex_index, (rest, current)) in rest.iter().zip(current.iter()).enumerate() {
+      let delta = Vector2 { x: current.x - rest.x, y: current.y - rest.y };
+      if delta.x.abs() > threshold || delta.y.abs() > threshold {
+        out.push(VertexDelta { vertex_index: vertex_index as u32, delta });
+      }
+    }
+    Some(out.len())
+  }
+
+  /// Reconstructs the vertex positions of a drawable from sparse `deltas`, e.g. received over the network.
+  ///
+  /// `out` is cleared first. Deltas of vertices out of range are ignored.
+  /// Returns the number of vertices written, or `None` if `index` is out of range.
+  pub fn reconstruct_drawable(&self, index: DrawableIndex, deltas: &[VertexDelta], out: &mut Vec<Vector2>) -> Option<usize> {
+    let rest = self.drawable_vertex_positions.get(index.as_usize())?;
+
+    out.clear();
+    out.extend_from_slice(rest);
+    for vertex_delta in deltas {
+      if let Some(position) = out.get_mut(vertex_delta.vertex_index as usize) {
+        position.x += vertex_delta.delta.x;
+        position.y += vertex_delta.delta.y;
+      }
+    }
+    Some(rest.len())
+  }
+
+  fn drawable_positions<'a>(&'a self, dynamic: &'a ModelDynamic, index: DrawableIndex) -> Option<(&'a [Vector2], &'a [Vector2])> {
+    let rest = self.drawable_vertex_positions.get(index.as_usize())?;
+    let current = dynamic.drawable_vertex_position_containers().get(index.as_usize())?;
+    assert_eq!(rest.len(), current.len(), "Rest pose does not match the model");
+    Some((rest, current))
+  }
+}
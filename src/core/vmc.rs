@@ -0,0 +1,195 @@
+//! Driving a model from tracking software speaking the VMC (Virtual Motion Capture) protocol over OSC.
+//!
+//! See <https://protocol.vmc.info/english> for the protocol.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::collections::HashMap;
+use std::net::{ToSocketAddrs, UdpSocket};
+
+use rosc::{OscMessage, OscPacket, OscType};
+
+use super::{ModelStatic, ModelDynamic};
+
+/// An axis of the Tait-Bryan angles of a bone rotation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VmcAxis {
+  /// Pitch.
+  X,
+  /// Yaw.
+  Y,
+  /// Roll.
+  Z,
+}
+
+/// A value received through VMC.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum VmcSource {
+  /// The value of a blend shape from `/VMC/Ext/Blend/Val`, typically in `[0, 1]`.
+  BlendShape(String),
+  /// An angle of a bone rotation from `/VMC/Ext/Bone/Pos`, in degrees.
+  BoneRotation { bone: String, axis: VmcAxis },
+}
+
+/// Maps a [`VmcSource`] onto a parameter as `value * scale + offset`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VmcMapping {
+  pub source: VmcSource,
+  pub parameter_id: String,
+  pub scale: f32,
+  pub offset: f32,
+}
+impl VmcMapping {
+  pub fn blend_shape(name: impl Into<String>, parameter_id: impl Into<String>, scale: f32, offset: f32) -> Self {
+    Self {
+      source: VmcSource::BlendShape(name.into()),
+      parameter_id: parameter_id.into(),
+      scale,
+      offset,
+    }
+  }
+  pub fn bone_rotation(bone: impl Into<String>, axis: VmcAxis, parameter_id: impl Into<String>, scale: f32) -> Self {
+    Self {
+      source: VmcSource::BoneRotation { bone: bone.into(), axis },
+      parameter_id: parameter_id.into(),
+      scale,
+      offset: 0.0,
+    }
+  }
+}
+
+/// A list of [`VmcMapping`]s.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VmcMappingTable {
+  pub mappings: Vec<VmcMapping>,
+}
+impl VmcMappingTable {
+  /// Maps the VRM 0.x preset blend shapes and the head rotation onto the standard parameters of _Live2D Cubism Editor_.
+  ///
+  /// Signs and scales may need adjusting, depending on the tracking software.
+  pub fn standard() -> Self {
+    Self {
+      mappings: vec![
+        VmcMapping::blend_shape("Blink_L", "ParamEyeLOpen", -1.0, 1.0),
+        VmcMapping::blend_shape("Blink_R", "ParamEyeROpen", -1.0, 1.0),
+        VmcMapping::blend_shape("A", "ParamMouthOpenY", 1.0, 0.0),
+        VmcMapping::blend_shape("Joy", "ParamMouthForm", 1.0, 0.0),
+        VmcMapping::bone_rotation("Head", VmcAxis::Y, "ParamAngleX", -1.0),
+        VmcMapping::bone_rotation("Head", VmcAxis::X, "ParamAngleY", -1.0),
+        VmcMapping::bone_rotation("Head", VmcAxis::Z, "ParamAngleZ", 1.0),
+      ],
+    }
+  }
+}
+
+/// Receives VMC messages on a UDP socket and applies them to a model through a [`VmcMappingTable`].
+///
+/// The socket is non-blocking: [`VmcReceiver::poll`] only drains the datagrams already received, and is typically called once per frame.
+#[derive(Debug)]
+pub struct VmcReceiver {
+  socket: UdpSocket,
+  mapping_table: VmcMappingTable,
+  /// Blend shape values staged by `/VMC/Ext/Blend/Val`, until `/VMC/Ext/Blend/Apply`.
+  pending_blend_shapes: HashMap<String, f32>,
+  blend_shapes: HashMap<String, f32>,
+  /// Local rotations as quaternions, `x, y, z, w`.
+  bone_rotations: HashMap<String, [f32; 4]>,
+  buffer: Box<[u8]>,
+}
+impl VmcReceiver {
+  /// Binds a UDP socket at `addr`, e.g. `"0.0.0.0:39539"`, the default port of VMC.
+  pub fn bind(addr: impl ToSocketAddrs, mapping_table: VmcMappingTable) -> std::io::Result<Self> {
+    let socket = UdpSocket::bind(addr)?;
+    socket.set_nonblocking(true)?;
+    Ok(Self {
+      socket,
+      mapping_table,
+      pending_blend_shapes: HashMap::new(),
+      blend_shapes: HashMap::new(),
+      bone_rotations: HashMap::new(),
+      buffer: vec![0; rosc::decoder::MTU].into_boxed_slice(),
+    })
+  }
+
+  pub fn mapping_table(&self) -> &VmcMappingTable {
+    &self.mapping_table
+  }
+  pub fn mapping_table_mut(&mut self) -> &mut VmcMappingTable {
+    &mut self.mapping_table
+  }
+
+  /// Receives all pending datagrams, returning how many were received. Malformed packets are logged and skipped.
+  pub fn poll(&mut self) -> std::io::Result<usize> {
+    let mut count = 0;
+    loop {
+      let size = match self.socket.recv(&mut self.buffer) {
+        Ok(size) => size,
+        Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => return Ok(count),
+        Err(err) => return Err(err),
+      };
+      count += 1;
+
+      match rosc::decoder::decode_udp(&self.buffer[..size]) {
+        Ok((_, packet)) => self.handle_packet(packet),
+        Err(err) => log::warn!("Skipping malformed OSC packet: {err:?}"),
+      }
+    }
+  }
+
+  /// Gets the latest value of `source`, if received.
+  pub fn value(&self, source: &VmcSource) -> Option<f32> {
+    match source {
+      VmcSource::BlendShape(name) => self.blend_shapes.get(name).copied(),
+      VmcSource::BoneRotation { bone, axis } => self.bone_rotations.get(bone).map(|&rotation| quaternion_to_euler_degrees(rotation, *axis)),
+    }
+  }
+
+  /// Writes the mapped values into `dynamic`. Mappings whose source has not been received yet, or whose parameter is not in the model, are skipped.
+  pub fn apply(&self, model_static: &ModelStatic, dynamic: &mut ModelDynamic) {
+    for mapping in &self.mapping_table.mappings {
+      let (Some(value), Some(index)) = (self.value(&mapping.source), model_static.parameter_index(&mapping.parameter_id)) else {
+        continue;
+      };
+      dynamic[index] = value * mapping.scale + mapping.offset;
+    }
+  }
+
+  fn handle_packet(&mut self, packet: OscPacket) {
+    match packet {
+      OscPacket::Message(message) => self.handle_message(message),
+      OscPacket::Bundle(bundle) => {
+        for packet in bundle.content {
+          self.handle_packet(packet);
+        }
+      }
+    }
+  }
+
+  fn handle_message(&mut self, message: OscMessage) {
+    match (message.addr.as_str(), message.args.as_slice()) {
+      ("/VMC/Ext/Blend/Val", [OscType::String(name), OscType::Float(value)]) => {
+        self.pending_blend_shapes.insert(name.clone(), *value);
+      }
+      ("/VMC/Ext/Blend/Apply", _) => {
+        self.blend_shapes.extend(self.pending_blend_shapes.drain());
+      }
+      (
+        "/VMC/Ext/Bone/Pos",
+        [OscType::String(name), OscType::Float(_), OscType::Float(_), OscType::Float(_), OscType::Float(x), OscType::Float(y), OscType::Float(z), OscType::Float(w)],
+      ) => {
+        self.bone_rotations.insert(name.clone(), [*x, *y, *z, *w]);
+      }
+      _ => {}
+    }
+  }
+}
+
+/// Converts a unit quaternion `x, y, z, w` into the Tait-Bryan angle around `axis`, in degrees.
+fn quaternion_to_euler_degrees([x, y, z, w]: [f32; 4], axis: VmcAxis) -> f32 {
+  let radians = match axis {
+    VmcAxis::X => (2.0 * (w * x + y * z)).atan2(1.0 - 2.0 * (x * x + y * y)),
+    VmcAxis::Y => (2.0 * (w * y - z * x)).clamp(-1.0, 1.0).asin(),
+    VmcAxis::Z => (2.0 * (w * z + x * y)).atan2(1.0 - 2.0 * (y * y + z * z)),
+  };
+  radians.to_degrees()
+}
@@ -0,0 +1,192 @@
+//! Watching of a model's files on disk, for editor and preview tools reloading a model as an artist edits it.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::base_types::MocError;
+use super::{CubismCore, Model};
+
+/// Errors generated by [`ModelWatcher`].
+#[derive(Debug, Error)]
+pub enum ModelWatchError {
+  #[error("Failed to watch files: {0}")]
+  Notify(#[from] notify::Error),
+  #[error("Failed to read \"{path}\": {source}")]
+  Io { path: PathBuf, source: std::io::Error },
+  #[error("Failed to parse \"{path}\": {source}")]
+  Model3Json { path: PathBuf, source: serde_json::Error },
+  #[error("Failed to load the reloaded moc: {0}")]
+  Moc(#[from] MocError),
+}
+
+/// A change to a watched file, reported by [`ModelWatcher::poll`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReloadEvent {
+  /// The moc was modified.
+  MocChanged { moc_path: PathBuf },
+  /// The `.model3.json` was modified, and may now reference a different moc.
+  Model3JsonChanged { model3_json_path: PathBuf, moc_path: PathBuf },
+}
+impl ReloadEvent {
+  /// Path of the moc to reload.
+  pub fn moc_path(&self) -> &Path {
+    match self {
+      Self::MocChanged { moc_path } | Self::Model3JsonChanged { moc_path, .. } => moc_path,
+    }
+  }
+}
+
+/// Watches a `.moc3`, or a `.model3.json` and the moc it references, for modifications.
+///
+/// Changes are collected in the background and reported by [`ModelWatcher::poll`], typically called once per frame.
+#[derive(Debug)]
+pub struct ModelWatcher {
+  watcher: RecommendedWatcher,
+  events: Receiver<notify::Result<notify::Event>>,
+  model3_json_path: Option<PathBuf>,
+  moc_path: PathBuf,
+  watched_dirs: Vec<PathBuf>,
+}
+impl ModelWatcher {
+  /// Starts watching `path`, either a `.moc3` or a `.model3.json`.
+  ///
+  /// Parent directories are watched instead of the files themselves, so that editors saving through a rename are supported.
+  pub fn new(path: impl AsRef<Path>) -> Result<Self, ModelWatchError> {
+    let path = canonicalize(path.as_ref())?;
+
+    let (sender, events) = mpsc::channel();
+    let watcher = notify::recommended_watcher(move |event| {
+      // The receiver is only gone once the `ModelWatcher` is dropped.
+      let _ = sender.send(event);
+    })?;
+
+    let (model3_json_path, moc_path) = if is_model3_json(&path) {
+      let moc_path = read_moc_path(&path)?;
+      (Some(path), moc_path)
+    } else {
+      (None, path)
+    };
+
+    let mut model_watcher = Self {
+      watcher,
+      events,
+      model3_json_path,
+      moc_path,
+      watched_dirs: Vec::new(),
+    };
+    model_watcher.update_watched_dirs()?;
+    Ok(model_watcher)
+  }
+
+  /// Path of the moc currently being watched.
+  pub fn moc_path(&self) -> &Path {
+    &self.moc_path
+  }
+  pub fn model3_json_path(&self) -> Option<&Path> {
+    self.model3_json_path.as_deref()
+  }
+
+  /// Collects the changes since the last call, coalesced into at most one event.
+  ///
+  /// A changed `.model3.json` takes precedence over a changed moc, as it is re-read to find the moc path again.
+  pub fn poll(&mut self) -> Result<Option<ReloadEvent>, ModelWatchError> {
+    let mut moc_changed = false;
+    let mut model3_json_changed = false;
+
+    for event in self.events.try_iter() {
+      let event = event?;
+      if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+        continue;
+      }
+      for path in &event.paths {
+        if *path == self.moc_path {
+          moc_changed = true;
+        }
+        if self.model3_json_path.as_ref() == Some(path) {
+          model3_json_changed = true;
+        }
+      }
+    }
+
+    if model3_json_changed {
+      let model3_json_path = self.model3_json_path.clone().expect("model3_json_path should be set");
+      self.moc_path = read_moc_path(&model3_json_path)?;
+      self.update_watched_dirs()?;
+      return Ok(Some(ReloadEvent::Model3JsonChanged { model3_json_path, moc_path: self.moc_path.clone() }));
+    }
+    if moc_changed {
+      return Ok(Some(ReloadEvent::MocChanged { moc_path: self.moc_path.clone() }));
+    }
+    Ok(None)
+  }
+
+  /// Polls for changes and, if any, hot-reloads `model` from the moc on disk with [`Model::reload_from`].
+  ///
+  /// Returns the reloaded model, or `None` if nothing changed.
+  pub fn reload_if_changed(&mut self, cubism_core: &CubismCore, model: &Model) -> Result<Option<Model>, ModelWatchError> {
+    let Some(event) = self.poll()? else {
+      return Ok(None);
+    };
+
+    let moc_path = event.moc_path();
+    let bytes = std::fs::read(moc_path).map_err(|source| ModelWatchError::Io { path: moc_path.to_owned(), source })?;
+    let moc = cubism_core.moc_from_bytes(&bytes)?;
+    log::info!("Reloaded \"{}\"", moc_path.display());
+    Ok(Some(model.reload_from(&moc)))
+  }
+
+  fn update_watched_dirs(&mut self) -> Result<(), ModelWatchError> {
+    let mut dirs: Vec<PathBuf> = self.model3_json_path.iter()
+      .chain(std::iter::once(&self.moc_path))
+      .filter_map(|path| path.parent().map(Path::to_owned))
+      .collect();
+    dirs.dedup();
+
+    for dir in self.watched_dirs.iter().filter(|dir| !dirs.contains(dir)) {
+      self.watcher.unwatch(dir)?;
+    }
+    for dir in dirs.iter().filter(|dir| !self.watched_dirs.contains(dir)) {
+      self.watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+    self.watched_dirs = dirs;
+    Ok(())
+  }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Model3Json {
+  file_references: Model3JsonFileReferences,
+}
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Model3JsonFileReferences {
+  moc: String,
+}
+
+fn is_model3_json(path: &Path) -> bool {
+  path.file_name()
+    .and_then(|file_name| file_name.to_str())
+    .is_some_and(|file_name| file_name.to_ascii_lowercase().ends_with(".model3.json"))
+}
+
+/// Reads `FileReferences.Moc`, resolved against the directory of the `.model3.json`.
+fn read_moc_path(model3_json_path: &Path) -> Result<PathBuf, ModelWatchError> {
+  let json = std::fs::read(model3_json_path).map_err(|source| ModelWatchError::Io { path: model3_json_path.to_owned(), source })?;
+  let model3_json: Model3Json = serde_json::from_slice(&json)
+    .map_err(|source| ModelWatchError::Model3Json { path: model3_json_path.to_owned(), source })?;
+
+  let moc_path = model3_json_path.parent().unwrap_or(Path::new("")).join(model3_json.file_references.moc);
+  canonicalize(&moc_path)
+}
+
+/// Paths reported by `notify` are absolute, so watched paths are canonicalized to compare against them.
+fn canonicalize(path: &Path) -> Result<PathBuf, ModelWatchError> {
+  std::fs::canonicalize(path).map_err(|source| ModelWatchError::Io { path: path.to_owned(), source })
+}
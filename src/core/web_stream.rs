@@ -0,0 +1,89 @@
+//! Loading of mocs from a web `ReadableStream`, e.g. the body of a `fetch` response, with progress reporting.
+
+#![cfg(target_arch = "wasm32")]
+
+use thiserror::Error;
+use wasm_bindgen::JsCast as _;
+use wasm_bindgen_futures::JsFuture;
+
+use super::base_types::MocError;
+use super::{CubismCore, Moc};
+
+#[derive(Debug, Clone, Error)]
+pub enum MocStreamError {
+  /// Reading from the stream failed, with the JavaScript error formatted.
+  #[error("Failed to read the stream: {0}")]
+  Read(String),
+  #[error(transparent)]
+  Moc(#[from] MocError),
+}
+impl From<wasm_bindgen::JsValue> for MocStreamError {
+  fn from(value: wasm_bindgen::JsValue) -> Self {
+    Self::Read(format!("{value:?}"))
+  }
+}
+
+impl CubismCore {
+  /// Reads `stream` to its end, then deserializes a [`Moc`] from the bytes read.
+  ///
+  /// * `expected_len` - Total length if known, e.g. from `Content-Length`, to allocate once and report progress against.
+  /// * `on_progress` - Called after each chunk with the number of bytes read so far and `expected_len`.
+  ///
+  /// ## Platform-specific
+  /// - **Native:** Unavailable.
+  pub async fn moc_from_readable_stream<F>(&self, stream: &web_sys::ReadableStream, expected_len: Option<usize>, mut on_progress: F) -> Result<Moc, MocStreamError>
+  where
+    F: FnMut(usize, Option<usize>),
+  {
+    let reader: web_sys::ReadableStreamDefaultReader = stream.get_reader().unchecked_into();
+
+    let mut bytes = Vec::with_capacity(expected_len.unwrap_or(0));
+    loop {
+      let result = JsFuture::from(reader.read()).await?;
+      if js_sys::Reflect::get(&result, &"done".into())?.as_bool().unwrap_or(false) {
+        break;
+      }
+
+      let chunk = js_sys::Uint8Array::new(&js_sys::Reflect::get(&result, &"value".into())?);
+      let start = bytes.len();
+      bytes.resize(start + chunk.length() as usize, 0);
+      chunk.copy_to(&mut bytes[start..]);
+
+      on_progress(bytes.len(), expected_len);
+    }
+
+    Ok(self.moc_from_bytes(&bytes)?)
+  }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+  use wasm_bindgen_test::wasm_bindgen_test;
+
+  use crate::core::{MocVersion, MockMoc};
+
+  use super::*;
+
+  fn stream_of(bytes: &[u8]) -> web_sys::ReadableStream {
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    web_sys::Blob::new_with_u8_array_sequence(&parts).unwrap().stream()
+  }
+
+  #[wasm_bindgen_test]
+  async fn reads_to_end_with_progress() {
+    let bytes = MockMoc::default().to_bytes();
+    let mut progress = Vec::new();
+    let moc = CubismCore::default()
+      .moc_from_readable_stream(&stream_of(&bytes), Some(bytes.len()), |read, expected| progress.push((read, expected)))
+      .await
+      .unwrap();
+    assert_eq!(moc.version(), MocVersion::Moc3_42);
+    assert_eq!(progress.last(), Some(&(bytes.len(), Some(bytes.len()))));
+  }
+
+  #[wasm_bindgen_test]
+  async fn invalid_moc() {
+    let result = CubismCore::default().moc_from_readable_stream(&stream_of(b"not a moc"), None, |_, _| {}).await;
+    assert!(matches!(result, Err(MocStreamError::Moc(MocError::InvalidMoc))));
+  }
+}
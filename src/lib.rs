@@ -1,6 +1,15 @@
 
+// `mock` replaces the Core rather than adding to it, so features only meaningful with the actual Core are rejected
+// instead of silently doing nothing.
+#[cfg(all(feature = "mock", feature = "debug-storage"))]
+compile_error!("The `mock` feature replaces Live2D Cubism SDK Core, so `debug-storage`, which tracks its memory, cannot be enabled with it.");
+#[cfg(all(feature = "mock", feature = "debug-lib"))]
+compile_error!("The `mock` feature replaces Live2D Cubism SDK Core, so `debug-lib`, which selects the Core library to link, cannot be enabled with it.");
+
+#[cfg(all(feature = "core", any(not(target_arch = "wasm32"), feature = "mock")))]
+mod memory;
+
 if_native! {
-  mod memory;
   mod sys;
 
   pub use sys::*;
@@ -9,7 +18,13 @@ if_native! {
 #[cfg(feature = "core")]
 pub mod core;
 
-#[cfg(all(test, feature = "core"))]
+#[cfg(feature = "capi")]
+pub mod capi;
+
+#[cfg(feature = "macros")]
+pub use live2d_cubism_core_macros::{live2d_params, include_moc};
+
+#[cfg(all(test, feature = "core", not(feature = "mock")))]
 pub mod core_api_tests {
   // Use:
   // wasm-pack test --chrome
@@ -66,17 +81,16 @@ pub mod core_api_tests {
       console_log::init_with_level(log::Level::Trace).unwrap();
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    {
-      // SAFETY: We don't directly use `csmGetLogFunction` or `csmSetLogFunction`.
-      unsafe {
-        live2d_core::CubismCore::set_log_function(|message| log::info!("Live2D Cubism Core says: \"{}\"", message));
-      }
+    // SAFETY: We don't directly use `csmGetLogFunction` or `csmSetLogFunction`.
+    unsafe {
+      live2d_core::CubismCore::set_log_function(|message| log::info!("Live2D Cubism Core says: \"{}\"", message));
     }
 
     let cubism_core = live2d_core::CubismCore::default();
     log::info!("Live2D Cubism Core Version: {}", cubism_core.version());
     log::info!("Latest supported moc version: {}", cubism_core.latest_supported_moc_version());
+    #[cfg(not(target_arch = "wasm32"))]
+    assert_eq!(live2d_core::CubismCore::global().version(), cubism_core.version());
 
     {
       let invalid_moc_bytes = include_bytes!(concat!(ENV_CUBISM_SDK_DIR!(), "/Samples/Resources/Hiyori/Hiyori.model3.json"));
@@ -88,8 +102,143 @@ pub mod core_api_tests {
 
     let moc = cubism_core.moc_from_bytes(moc_bytes).expect("moc_from_bytes should succeed");
     log::info!("Moc version: {}", moc.version());
+    assert_eq!(cubism_core.moc_version_from_bytes(moc_bytes).expect("moc_version_from_bytes should succeed"), moc.version());
+    {
+      let digest = live2d_core::integrity::sha256(moc_bytes);
+      assert_eq!(live2d_core::integrity::from_hex(&live2d_core::integrity::to_hex(&digest)), Some(digest));
+      cubism_core.moc_from_bytes_with_options(moc_bytes, &live2d_core::MocLoadOptions::default().expected_sha256(digest)).expect("moc_from_bytes_with_options should succeed");
+      let mismatch = cubism_core.moc_from_bytes_with_options(moc_bytes, &live2d_core::MocLoadOptions::default().expected_sha256([0; 32]));
+      assert!(matches!(mismatch, Err(live2d_core::MocError::IntegrityMismatch { .. })));
+    }
+    {
+      let mut decrypting_core = live2d_core::CubismCore::default();
+      decrypting_core.set_decryptor(Some(live2d_core::AssetDecryptor::new(|_, bytes| Ok(bytes.iter().map(|byte| byte ^ 0x5a).collect()))));
+      let encrypted_moc_bytes: Vec<u8> = moc_bytes.iter().map(|byte| byte ^ 0x5a).collect();
+      decrypting_core.moc_from_bytes(&encrypted_moc_bytes).expect("moc_from_bytes should succeed");
+    }
+
+    {
+      let parameter_id = live2d_core::Model::from_moc(&moc).get_static().parameters()[0].id().to_owned();
+      let model = live2d_core::Model::builder(&moc)
+        .parameter(parameter_id.as_str(), 0.5)
+        .build()
+        .expect("build should succeed");
+      assert_eq!(model.read_dynamic().parameter_values()[0], 0.5);
+
+      let respawned_model = live2d_core::Model::builder(&moc)
+        .initial_state(live2d_core::InitialState::CopiedFrom(&model))
+        .build()
+        .expect("build should succeed");
+      assert_eq!(respawned_model.read_dynamic().parameter_values()[0], 0.5);
+      assert_eq!(model.duplicate().read_dynamic().parameter_values()[0], 0.5);
+      #[cfg(not(target_arch = "wasm32"))]
+      assert!(moc.model_size_hint() > 0);
+      #[cfg(not(target_arch = "wasm32"))]
+      {
+        let allocating_core = live2d_core::CubismCore::with_allocator(std::sync::Arc::new(live2d_core::SystemStorageAllocator));
+        let allocated_moc = allocating_core.moc_from_bytes(moc_bytes).expect("moc_from_bytes should succeed");
+        assert_eq!(live2d_core::Model::try_from_moc(&allocated_moc).expect("try_from_moc should succeed").get_static().parameters().len(), model.get_static().parameters().len());
+
+        let pool = std::sync::Arc::new(live2d_core::PooledStorageAllocator::new(live2d_core::SystemStorageAllocator, usize::MAX));
+        let pooling_moc = live2d_core::CubismCore::with_allocator(pool.clone()).moc_from_bytes(moc_bytes).expect("moc_from_bytes should succeed");
+        drop(live2d_core::Model::from_moc(&pooling_moc));
+        assert!(pool.cached_bytes() > 0);
+        drop(live2d_core::Model::from_moc(&pooling_moc));
+        pool.trim();
+        assert_eq!(pool.cached_bytes(), 0);
+      }
+      let reinitialized_model = model.duplicate();
+      reinitialized_model.reinitialize();
+      reinitialized_model.write_dynamic().update();
+      assert_eq!(reinitialized_model.read_dynamic().parameter_values()[0], reinitialized_model.get_static().parameters()[0].default_value());
+
+      let reloaded_model = model.reload_from(&cubism_core.moc_from_bytes(moc_bytes).unwrap());
+      assert_eq!(reloaded_model.read_dynamic().parameter_values()[0], 0.5);
+
+      let saved_state = live2d_core::SavedState::capture(model.get_static(), &model.read_dynamic());
+      let restored_state = live2d_core::SavedState::from_bytes(&saved_state.to_bytes()).expect("from_bytes should succeed");
+      assert_eq!(saved_state, restored_state);
+      let restored_model = live2d_core::Model::from_moc(&moc);
+      restored_state.restore(restored_model.get_static(), &mut restored_model.write_dynamic());
+      assert_eq!(restored_model.read_dynamic().parameter_values()[0], 0.5);
+
+      let mut frame_recorder = live2d_core::FrameRecorder::new(Vec::new(), live2d_core::FrameContentFlags::ParameterValues | live2d_core::FrameContentFlags::VertexPositions, &model.read_dynamic()).unwrap();
+      frame_recorder.write_frame(0.0, &model.read_dynamic()).unwrap();
+      let recorded_frames = live2d_core::FrameReader::new(frame_recorder.finish().unwrap().as_slice()).unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .expect("reading recorded frames should succeed");
+      assert_eq!(recorded_frames.len(), 1);
+      assert_eq!(recorded_frames[0].parameter_values[0], 0.5);
+
+      let consistency_script = live2d_core::test_support::ConsistencyScript::sweep(model.get_static(), 3);
+      let consistency_report = live2d_core::test_support::run_consistency_script(&cubism_core, moc_bytes, &consistency_script, live2d_core::state_hash::DEFAULT_VERTEX_POSITION_QUANTUM).unwrap();
+      assert_eq!(consistency_report.frame_hashes.len(), consistency_script.frames.len());
+      assert_eq!(consistency_report.first_mismatch(&consistency_report), None);
+
+      let retargeter = live2d_core::ParameterRetargeter::new(model.get_static(), restored_model.get_static(), &Default::default(), Default::default());
+      assert!(retargeter.unmapped().is_empty());
+
+      log::info!("Standard parameters: {:?}", live2d_core::standard_params::resolve_all(model.get_static(), &[]));
+      let parameter_discovery = model.get_static().discover_parameters();
+      assert!(parameter_discovery.classifications().windows(2).all(|pair| pair[0].confidence >= pair[1].confidence));
+      if let Some(angle_x) = live2d_core::standard_params::resolve(model.get_static(), live2d_core::standard_params::ANGLE_X, &[]) {
+        assert!(parameter_discovery.roles_of(angle_x).any(|classification| classification.role == live2d_core::ParameterRole::HeadAngleX));
+      }
+
+      {
+        let mut dynamic = model.write_dynamic();
+        let drawable_index = live2d_core::DrawableIndex::from(0usize);
+        dynamic.set_drawable_user_visible(drawable_index, false);
+        assert!(!dynamic.is_drawable_drawn(drawable_index));
+        dynamic.reset_drawable_user_visibilities();
+      }
+
+      let mut scene = live2d_core::Scene::new();
+      let scene_model_id = scene.add(std::sync::Arc::new(live2d_core::Model::from_moc(&moc)), 0, Default::default());
+      let scene_draw_list = scene.draw_list();
+      assert!(scene_draw_list.iter().all(|item| item.model_id == scene_model_id));
+      scene.add(std::sync::Arc::new(live2d_core::Model::from_moc(&moc)), 1, Default::default());
+      let scene_batches = scene.batched_draw_list(0.0);
+      assert_eq!(scene_batches.len(), scene_draw_list.len());
+      assert!(scene_batches.iter().all(|batch| batch.source_model_id == scene_model_id && batch.instances.len() == 2));
+
+      let mut presets = live2d_core::Presets::new();
+      let first_parameter_id = model.get_static().parameters()[0].id().to_owned();
+      presets.insert(live2d_core::Preset::new("Half", [(first_parameter_id, 1.0)]));
+      presets.apply("Half", model.get_static(), &mut model.write_dynamic(), 0.5).expect("apply should succeed");
+      assert_eq!(model.read_dynamic().parameter_values()[0], 0.75);
+
+      live2d_core::Model::builder(&moc)
+        .part_opacity("NonExistentPart", 0.0)
+        .build()
+        .expect_err("build should fail with an unknown part ID");
+    }
+
+    {
+      let moc_cache = live2d_core::MocCache::new();
+      let cached_moc = cubism_core.moc_cached(&moc_cache, moc_bytes).expect("moc_cached should succeed");
+      assert!(live2d_core::Moc::ptr_eq(&cached_moc, &cubism_core.moc_cached(&moc_cache, moc_bytes).unwrap()));
+      assert_eq!(moc_cache.len(), 1);
+
+      let cached_model = live2d_core::Model::from_moc(&cached_moc);
+      assert_eq!(cached_moc.model_count(), 1);
+      assert_eq!(moc_cache.evict_unused(), 0);
+      drop(cached_model);
+      assert_eq!(cached_moc.model_count(), 0);
+      assert_eq!(moc_cache.evict_unused(), 1);
+    }
 
     let model = live2d_core::Model::from_moc(&moc);
+    assert!(live2d_core::Moc::ptr_eq(model.moc(), &moc));
+    model.set_user_data(42_u32);
+    assert_eq!(model.user_data::<u32>().as_deref(), Some(&42));
+    assert!(model.user_data::<i32>().is_none());
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+      // SAFETY: Only the pointer itself is inspected.
+      assert!(!unsafe { moc.as_csm_moc_ptr() }.is_null());
+      assert!(!unsafe { model.as_csm_model_ptr() }.is_null());
+    }
 
     {
       let model_static = model.get_static();
@@ -98,6 +247,14 @@ pub mod core_api_tests {
       log::info!("{:?}", model_static.parameters());
       log::info!("{:?}", model_static.parts());
       log::info!("{:?}", model_static.drawables());
+      assert!(model_static.schema_diff(model_static).is_empty());
+      let mesh_validation_report = model_static.validate_meshes();
+      log::info!("{:?}", mesh_validation_report);
+      assert!(mesh_validation_report.drawables().iter().all(|(_, issues)| !issues.contains(live2d_core::MeshIssues::IndexOutOfRange)));
+      log::info!("{:?}", model_static.mask_groups());
+      log::info!("{:?}", model_static.geometry_statistics());
+      assert_eq!(model_static.drawables_by_texture().len(), model_static.geometry_statistics().texture_count);
+      log::info!("Capabilities: {:?}", model.capabilities());
     }
 
     {
@@ -118,6 +275,77 @@ pub mod core_api_tests {
 
       dynamic.reset_drawable_dynamic_flags();
       dynamic.update();
+
+      let state_hash = dynamic.state_hash();
+      let snapshot = dynamic.snapshot();
+      dynamic.update();
+      assert_eq!(dynamic.state_hash(), state_hash, "Updating without changes should not change the state hash");
+      assert!(snapshot.diff(&dynamic.snapshot()).is_empty());
+
+      for (_, parameter, value) in dynamic.iter_parameters_mut() {
+        *value = parameter.default_value();
+      }
+      let parameter_index = live2d_core::ParameterIndex::from(0);
+      dynamic[parameter_index] = model.get_static()[parameter_index].default_value();
+
+      dynamic.blend_from_snapshots(&snapshot, &snapshot, 0.5);
+      assert_eq!(dynamic.parameter_values(), snapshot.parameter_values());
+      assert!(live2d_core::Expression::capture(model.get_static(), &dynamic, &snapshot, &Default::default()).parameters.is_empty());
+
+      dynamic.set_parameter_clamp_mode(live2d_core::ParameterClampMode::OnUpdate);
+      dynamic[parameter_index] = f32::MAX;
+      dynamic.update();
+      assert_eq!(dynamic[parameter_index], model.get_static()[parameter_index].value_range().1);
+      dynamic.set_parameter_clamp_mode(live2d_core::ParameterClampMode::Off);
+
+      dynamic.set_geometry_readback(false);
+      dynamic.update();
+      assert!(!dynamic.geometry_readback());
+      dynamic.set_geometry_readback(true);
+      dynamic.set_readback_mask(live2d_core::DrawableReadbackFlags::VertexPositions | live2d_core::DrawableReadbackFlags::Opacities);
+      dynamic.update();
+      dynamic.set_readback_mask(live2d_core::DrawableReadbackFlagSet::full());
+
+      dynamic.enable_parameter_history(2);
+      for time in [0.0, 1.0, 2.0] {
+        dynamic.record_parameter_history(time);
+      }
+      let history = dynamic.parameter_history().unwrap();
+      assert_eq!(history.len(), 2);
+      assert_eq!(history.frame(0).unwrap().0, 1.0);
+      assert_eq!(history.mean(parameter_index, 2), Some(dynamic[parameter_index]));
+      assert_eq!(dynamic.parameter_velocities().unwrap()[parameter_index.as_usize()], 0.0);
+      assert!(dynamic.parameter_accelerations().is_none());
+      dynamic.disable_parameter_history();
+
+      let drawing_sequence = dynamic.drawing_sequence();
+      assert_eq!(drawing_sequence.len(), model.get_static().drawables().len());
+      assert!(drawing_sequence.windows(2).all(|pair| dynamic.drawable_render_orders()[pair[0].as_usize()] <= dynamic.drawable_render_orders()[pair[1].as_usize()]));
+      assert_eq!(dynamic.find_non_finite_output(), None);
+
+      let mut frame_interpolator = live2d_core::FrameInterpolator::new(2);
+      assert!(!frame_interpolator.apply(0.0, &mut dynamic));
+      frame_interpolator.publish(0.0, &dynamic);
+      frame_interpolator.publish(1.0 / 30.0, &dynamic);
+      let published_values = dynamic.parameter_values().to_vec();
+      assert!(frame_interpolator.apply(1.0 / 60.0, &mut dynamic));
+      assert_eq!(dynamic.parameter_values(), published_values);
+
+      let mut replication_encoder = live2d_core::ReplicationEncoder::new(&dynamic);
+      let mut replication_decoder = live2d_core::ReplicationDecoder::new(&dynamic);
+      let keyframe = replication_encoder.encode(&dynamic);
+      let delta = replication_encoder.encode(&dynamic);
+      assert!(delta.len() < keyframe.len());
+      assert_eq!(replication_decoder.decode(&delta, &mut dynamic), Err(live2d_core::ReplicationError::MissingBaseline { sequence: 1, baseline: 0 }));
+      assert_eq!(replication_decoder.decode(&keyframe, &mut dynamic), Ok(0));
+      assert_eq!(replication_decoder.decode(&delta, &mut dynamic), Ok(1));
+      assert_eq!(replication_decoder.decode(&delta, &mut dynamic), Err(live2d_core::ReplicationError::Stale { sequence: 1 }));
+
+      let mut tracker = live2d_core::ParameterChangeTracker::new(&dynamic);
+      dynamic[parameter_index] = model.get_static()[parameter_index].value_range().0;
+      assert_eq!(tracker.changes(&dynamic).len(), 1);
+      assert!(tracker.changes(&dynamic).is_empty());
+      log::info!("{:?}", dynamic.update_with_summary());
     }
 
     {
@@ -140,13 +368,14 @@ pub mod core_api_tests {
   use if_wasm;
 }
 
-#[cfg(not(target_arch = "wasm32"))]
+/// Native items needing the actual _Live2D® Cubism SDK Core_, which the `mock` feature replaces.
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "mock")))]
 macro_rules! if_native {
   ($($code:tt)*) => {
     $($code)*
   }
 }
-#[cfg(target_arch = "wasm32")]
+#[cfg(any(target_arch = "wasm32", feature = "mock"))]
 macro_rules! if_native {
   ($($code:tt)*) => {};
 }
@@ -1,39 +1,88 @@
+// Under `mock`, only the slices are used. On web, where there is no allocator, only they are available.
+#![cfg_attr(feature = "mock", allow(dead_code))]
 
+#[cfg(not(target_arch = "wasm32"))]
 use std::{
-  alloc::{Layout, alloc, dealloc, LayoutError},
+  alloc::Layout,
   ops,
+  sync::Arc,
 };
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::core::base_types::OutOfMemoryError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::core::allocator::StorageAllocator;
+
+#[cfg(not(target_arch = "wasm32"))]
 #[derive(Debug)]
 pub struct AlignedStorage {
   ptr: *mut u8,
   layout: Layout,
+  /// Deallocates `ptr`.
+  allocator: Arc<dyn StorageAllocator>,
 }
 
 // SAFETY: The content of the managed memory block pointed to by `ptr`
 // is never mutated except using methods that take a mutable reference.
+#[cfg(not(target_arch = "wasm32"))]
 unsafe impl Send for AlignedStorage {}
+#[cfg(not(target_arch = "wasm32"))]
 unsafe impl Sync for AlignedStorage {}
 
+#[cfg(not(target_arch = "wasm32"))]
 impl AlignedStorage {
-  pub fn new(size: usize, alignment: usize) -> Result<Self, LayoutError>  {
-    let layout = Layout::from_size_align(size, alignment)?;
+  /// Allocates an uninitialized memory block from `allocator`.
+  ///
+  /// Fails if the allocator fails, or if `size` rounded up to `alignment` overflows `isize`.
+  ///
+  /// ## Panics
+  /// - If `alignment` is not a power of two.
+  pub fn new(size: usize, alignment: usize, allocator: &Arc<dyn StorageAllocator>) -> Result<Self, OutOfMemoryError> {
+    Self::allocate(size, alignment, allocator, false)
+  }
+  /// Like [`AlignedStorage::new`], but with every byte set to `0`.
+  pub fn new_zeroed(size: usize, alignment: usize, allocator: &Arc<dyn StorageAllocator>) -> Result<Self, OutOfMemoryError> {
+    Self::allocate(size, alignment, allocator, true)
+  }
 
-    unsafe {
-      let ptr = alloc(layout);
-      assert!(!ptr.is_null());
-      Ok(AlignedStorage { ptr, layout })
+  fn allocate(size: usize, alignment: usize, allocator: &Arc<dyn StorageAllocator>, zeroed: bool) -> Result<Self, OutOfMemoryError> {
+    assert!(alignment.is_power_of_two(), "Alignment should be a power of two");
+    let layout = Layout::from_size_align(size, alignment).map_err(|_| OutOfMemoryError { size, alignment })?;
+
+    // Allocators need not support zero bytes, so a dangling but aligned pointer stands in.
+    let ptr = if size == 0 {
+      alignment as *mut u8
+    } else if zeroed {
+      allocator.allocate_zeroed(layout)
+    } else {
+      allocator.allocate(layout)
+    };
+    if ptr.is_null() {
+      return Err(OutOfMemoryError { size, alignment });
     }
+    Ok(AlignedStorage { ptr, layout, allocator: Arc::clone(allocator) })
+  }
+
+  /// Gets a pointer to the memory block through which it may be written.
+  ///
+  /// ## Safety
+  /// - Writes MUST NOT overlap any live reference obtained through [`ops::Deref`] or [`ops::DerefMut`].
+  pub unsafe fn as_raw_mut_ptr(&self) -> *mut u8 {
+    self.ptr
   }
 }
+#[cfg(not(target_arch = "wasm32"))]
 impl Drop for AlignedStorage {
   fn drop(&mut self) {
-    unsafe {
-      dealloc(self.ptr, self.layout);
+    if self.layout.size() != 0 {
+      unsafe {
+        self.allocator.deallocate(self.ptr, self.layout);
+      }
     }
   }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl ops::Deref for AlignedStorage {
   type Target = [u8];
   fn deref(&self) -> &Self::Target {
@@ -42,6 +91,7 @@ impl ops::Deref for AlignedStorage {
     }
   }
 }
+#[cfg(not(target_arch = "wasm32"))]
 impl ops::DerefMut for AlignedStorage {
   fn deref_mut(&mut self) -> &mut Self::Target {
     unsafe {
@@ -49,3 +99,112 @@ impl ops::DerefMut for AlignedStorage {
     }
   }
 }
+
+/// A slice inside a memory block owned elsewhere, e.g. the array of parameter values inside a `csmModel`,
+/// borrowed only for as long as the owner of the [`RawSlice`] is.
+///
+/// Unlike a `&'static [T]`, no lifetime longer than the memory block can ever be observed.
+#[derive(Debug)]
+pub struct RawSlice<T> {
+  ptr: *mut T,
+  len: usize,
+}
+impl<T> RawSlice<T> {
+  /// `ptr` may be null if `len` is `0`.
+  ///
+  /// ## Safety
+  /// - Unless `len` is `0`, `ptr` MUST be valid for reads, and writes if [`RawSlice::as_mut_slice`] is used,
+  ///   of `len` elements of `T` for as long as the [`RawSlice`] is used.
+  pub unsafe fn new(ptr: *mut T, len: usize) -> Self {
+    Self { ptr, len }
+  }
+
+  /// Gets the slice for as long as `self` is borrowed.
+  pub fn as_slice(&self) -> &[T] {
+    if self.len == 0 {
+      return &[];
+    }
+    // SAFETY: Guaranteed by the contract of `RawSlice::new`.
+    unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+  }
+  /// Gets the slice mutably for as long as `self` is borrowed.
+  pub fn as_mut_slice(&mut self) -> &mut [T] {
+    if self.len == 0 {
+      return &mut [];
+    }
+    // SAFETY: Guaranteed by the contract of `RawSlice::new`.
+    unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+  }
+}
+
+/// Several [`RawSlice`]s, e.g. the vertex positions of every drawable, viewable as a `&[&[T]]`.
+#[derive(Debug)]
+pub struct RawSlices<T> {
+  /// Never null, so that each has the layout of a valid `&[T]`.
+  inner: Box<[*const [T]]>,
+}
+impl<T> RawSlices<T> {
+  /// Pointers may be null if their length is `0`.
+  ///
+  /// ## Safety
+  /// - As with [`RawSlice::new`], for reads only, for each pointer and length.
+  pub unsafe fn new(slices: impl IntoIterator<Item = (*const T, usize)>) -> Self {
+    Self {
+      inner: slices.into_iter()
+        .map(|(ptr, len)| {
+          let ptr = if len == 0 { std::ptr::NonNull::dangling().as_ptr() } else { ptr };
+          std::ptr::slice_from_raw_parts(ptr, len)
+        })
+        .collect(),
+    }
+  }
+
+  /// Gets the slices for as long as `self` is borrowed.
+  pub fn as_slices(&self) -> &[&[T]] {
+    // SAFETY: References and raw pointers to the same type have the same layout, every pointer is non-null,
+    // and valid for reads as guaranteed by the contract of `RawSlices::new`.
+    unsafe { std::slice::from_raw_parts(self.inner.as_ptr().cast::<&[T]>(), self.inner.len()) }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  //! Independent of _Live2D® Cubism SDK Core_, so that they can run under Miri:
+  //! `cargo +nightly miri test memory::`
+
+  use super::*;
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn aligned_storage() {
+    use crate::core::allocator::{SystemStorageAllocator, PooledStorageAllocator};
+
+    let allocator: Arc<dyn StorageAllocator> = Arc::new(SystemStorageAllocator);
+    let storage = AlignedStorage::new_zeroed(100, 64, &allocator).unwrap();
+    assert_eq!(storage.as_ptr() as usize % 64, 0);
+    assert!(storage.iter().all(|&byte| byte == 0));
+    assert!(AlignedStorage::new(0, 16, &allocator).unwrap().is_empty());
+    assert!(AlignedStorage::new(usize::MAX, 16, &allocator).is_err());
+
+    let pool = Arc::new(PooledStorageAllocator::new(SystemStorageAllocator, 1024));
+    let pool_allocator: Arc<dyn StorageAllocator> = pool.clone();
+    let mut storage = AlignedStorage::new(100, 16, &pool_allocator).unwrap();
+    storage.fill(1);
+    drop(storage);
+    assert_eq!(pool.cached_bytes(), 100);
+    assert!(AlignedStorage::new_zeroed(100, 16, &pool_allocator).unwrap().iter().all(|&byte| byte == 0));
+  }
+
+  #[test]
+  fn raw_slices() {
+    let mut values = vec![1.0f32, 2.0, 3.0];
+    let mut raw_slice = unsafe { RawSlice::new(values.as_mut_ptr(), values.len()) };
+    raw_slice.as_mut_slice()[1] = 5.0;
+    assert_eq!(raw_slice.as_slice(), &[1.0, 5.0, 3.0]);
+    let empty = unsafe { RawSlice::<f32>::new(std::ptr::null_mut(), 0) };
+    assert!(empty.as_slice().is_empty());
+
+    let raw_slices = unsafe { RawSlices::new([(values.as_ptr(), 2), (std::ptr::null(), 0), (values.as_ptr().add(1), 2)]) };
+    assert_eq!(raw_slices.as_slices(), &[&[1.0, 5.0][..], &[], &[5.0, 3.0]]);
+  }
+}
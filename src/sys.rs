@@ -5,3 +5,6 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+/// Whether the debug build of the Core library is linked, by the `debug-lib` feature on a platform that has one.
+pub const LINKED_DEBUG_CORE_LIB: bool = cfg!(live2d_cubism_core_debug_lib);